@@ -20,9 +20,7 @@ extern crate gcollections;
 
 use pcp::kernel::*;
 use pcp::propagators::*;
-use pcp::variable::ops::*;
 use pcp::term::*;
-use pcp::search::search_tree_visitor::Status::*;
 use pcp::search::*;
 use interval::interval::*;
 use gcollections::ops::*;
@@ -51,25 +49,19 @@ fn nqueens(n: usize) {
   space.cstore.alloc(Distinct::new(queens));
 
   // Search step.
-  let mut search = one_solution_engine();
-  search.start(&space);
-  let (mut space, status) = search.enter(space);
-  let label = space.label();
-  let space = space.restore(label);
+  let mut solutions = Solutions::new(one_solution_engine(), space);
 
   // Print result.
-  match status {
-    Satisfiable => {
+  match solutions.next() {
+    Some(solution) => {
       print!("{}-queens problem is satisfiable. The first solution is:\n[", n);
-      for dom in space.vstore.iter() {
+      for dom in solution {
         // At this stage, dom.lower() == dom.upper().
         print!("{}, ", dom.lower());
       }
       println!("]");
     }
-    Unsatisfiable => println!("{}-queens problem is unsatisfiable.", n),
-    Unknown(_) => unreachable!(
-      "After the search step, the problem instance should be either satisfiable or unsatisfiable.")
+    None => println!("{}-queens problem is unsatisfiable.", n)
   }
 }
 
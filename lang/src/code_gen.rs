@@ -95,6 +95,10 @@ impl<'a, 'b> CodeGenerator<'a, 'b>
       IndexedExpr(ref var, ref index) => {
         self.gen_indexed_expr(unquote, var.clone(), index.clone())
       },
+      UnaryArithExpr(_, ref expr) => {
+        let expr = self.gen_arith_expr(unquote, expr.clone());
+        quote_expr!(self.cx, -$expr)
+      }
       x => panic!(format!("gen_arith_expr: {:?}: Not implemented", x))
     }
   }
@@ -135,6 +139,14 @@ impl<'a, 'b> CodeGenerator<'a, 'b>
 
   fn gen_binary_constraint(&self, unquote: &Unquote, constraint: pcp::BinaryConstraint) -> RExpr {
     use grammar::pcp::RelationalOp::*;
+    if let Eq = constraint.rel_op {
+      if let Some(element) = self.gen_element_constraint(unquote, &constraint) {
+        return element;
+      }
+      if let Some(product) = self.gen_product_constraint(unquote, &constraint) {
+        return product;
+      }
+    }
     let x = self.gen_var_view(unquote, constraint.left);
     let y = self.gen_var_view(unquote, constraint.right);
     match constraint.rel_op {
@@ -147,6 +159,46 @@ impl<'a, 'b> CodeGenerator<'a, 'b>
     }
   }
 
+  // `y == array[index]` (or `array[index] == y`) where `index` is not a literal generates an element constraint. The array is indexed directly if `index` is an `usize` value, otherwise `index` is a variable or a view such as `x + 1`.
+  fn gen_element_constraint(&self, unquote: &Unquote, constraint: &pcp::BinaryConstraint) -> Option<RExpr> {
+    use grammar::pcp::ArithExpr::*;
+    let (indexed, y) = match (&*constraint.left, &*constraint.right) {
+      (&IndexedExpr(_, ref index), _) if !is_literal(index) => (&constraint.left, &constraint.right),
+      (_, &IndexedExpr(_, ref index)) if !is_literal(index) => (&constraint.right, &constraint.left),
+      _ => return None
+    };
+    match **indexed {
+      IndexedExpr(ref array, ref index) => {
+        let array = self.gen_ident(unquote, array.clone());
+        let index = match **index {
+          BinaryArithExpr(..) | UnaryArithExpr(..) => self.gen_var_view(unquote, index.clone()),
+          _ => self.gen_arith_expr(unquote, index.clone())
+        };
+        let y = self.gen_var_view(unquote, y.clone());
+        Some(quote_expr!(self.cx, element(&$array, $index, $y)))
+      }
+      _ => None
+    }
+  }
+
+  // `x * y == z` (or `z == x * y`) where neither `x` nor `y` is a literal generates the constraint `XTimesYEqZ`. The variable store is not known here, a product of two variables elsewhere must be allocated with `x_times_y(x, y, &mut variables, &mut constraints)` before the constraint.
+  fn gen_product_constraint(&self, unquote: &Unquote, constraint: &pcp::BinaryConstraint) -> Option<RExpr> {
+    let (product, z) = match (is_product(&constraint.left), is_product(&constraint.right)) {
+      (true, _) => (&constraint.left, &constraint.right),
+      (_, true) => (&constraint.right, &constraint.left),
+      _ => return None
+    };
+    match **product {
+      pcp::ArithExpr::BinaryArithExpr(_, ref x, ref y) => {
+        let x = self.gen_var_view(unquote, x.clone());
+        let y = self.gen_var_view(unquote, y.clone());
+        let z = self.gen_var_view(unquote, z.clone());
+        Some(quote_expr!(self.cx, XTimesYEqZ::new($x, $y, $z)))
+      }
+      _ => None
+    }
+  }
+
   fn gen_var_view(&self, unquote: &Unquote, arith_expr: pcp::AExpr) -> RExpr {
     use grammar::pcp::ArithExpr::*;
     match *arith_expr {
@@ -158,8 +210,13 @@ impl<'a, 'b> CodeGenerator<'a, 'b>
       IndexedExpr(ref var, ref index) => {
         self.gen_indexed_expr(unquote, var.clone(), index.clone())
       }
-      UnaryArithExpr(..) => {
-        panic!("gen_var_view: UnaryArithExpr: unimplemented.")
+      UnaryArithExpr(_, ref expr) if is_literal(expr) => {
+        let lit = self.gen_arith_expr(unquote, expr.clone());
+        quote_expr!(self.cx, Constant::new(-$lit))
+      }
+      UnaryArithExpr(_, ref expr) => {
+        let x = self.gen_var_view(unquote, expr.clone());
+        quote_expr!(self.cx, Negation::new($x))
       }
       BinaryArithExpr(op, ref x, ref y) => {
         self.gen_bin_arith_expr(unquote, op, x.clone(), y.clone())
@@ -167,16 +224,41 @@ impl<'a, 'b> CodeGenerator<'a, 'b>
     }
   }
 
+  // A literal on the left is moved to the right, `c - x` being `-x + c`. The right operand of an addition is a constant (a literal or a Rust value), `x - y` is `x + -y` and the right operand of a product is a literal, the product of two variables is generated by `gen_product_constraint`.
   fn gen_bin_arith_expr(&self, unquote: &Unquote, op: pcp::BinArithOp,
     x: pcp::AExpr, y: pcp::AExpr) -> RExpr
   {
     use grammar::pcp::BinArithOp::*;
+    let swap = is_literal(&x) && !is_literal(&y);
+    let (x, y) = if swap { (y, x) } else { (x, y) };
+    let literal = is_literal(&y);
     let x = self.gen_var_view(unquote, x);
-    let y = self.gen_var_view(unquote, y);
     match op {
-      Add => quote_expr!(self.cx, Addition::new($x, $y)),
-      Sub => panic!("gen_bin_arith_expr: Sub: unimplemented."),
-      Mul => panic!("gen_bin_arith_expr: Mul: unimplemented.")
+      Add => {
+        let y = self.gen_arith_expr(unquote, y);
+        quote_expr!(self.cx, Addition::new($x, $y))
+      }
+      Sub if swap => {
+        let y = self.gen_arith_expr(unquote, y);
+        quote_expr!(self.cx, Addition::new(Negation::new($x), $y))
+      }
+      Sub if literal => {
+        let y = self.gen_arith_expr(unquote, y);
+        quote_expr!(self.cx, Addition::new($x, -$y))
+      }
+      Sub => {
+        let y = self.gen_var_view(unquote, y);
+        quote_expr!(self.cx, Addition::new($x, Negation::new($y)))
+      }
+      Mul if literal => {
+        let y = self.gen_arith_expr(unquote, y);
+        quote_expr!(self.cx, Times::new($x, $y))
+      }
+      Mul => {
+        self.cx.span_err(unquote.span,
+          "A product of two variables must be alone on a side of an equality (`x * y == z`), otherwise allocate it with `x_times_y(x, y, &mut variables, &mut constraints)`.");
+        quote_expr!(self.cx, ())
+      }
     }
   }
 
@@ -194,3 +276,19 @@ impl<'a, 'b> CodeGenerator<'a, 'b>
     quote_expr!(self.cx, $var[$index])
   }
 }
+
+// Product of two operands which are not literals.
+fn is_product(expr: &pcp::AExpr) -> bool {
+  match **expr {
+    pcp::ArithExpr::BinaryArithExpr(pcp::BinArithOp::Mul, ref x, ref y) => !is_literal(x) && !is_literal(y),
+    _ => false
+  }
+}
+
+fn is_literal(expr: &pcp::AExpr) -> bool {
+  match **expr {
+    pcp::ArithExpr::Number(_) => true,
+    pcp::ArithExpr::UnaryArithExpr(_, ref expr) => is_literal(expr),
+    _ => false
+  }
+}
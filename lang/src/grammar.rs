@@ -367,4 +367,18 @@ mod test
     | Failure(_) => assert!(false)
     };
   }
+
+  #[test]
+  fn test_grammar_product()
+  {
+    let state = pcp::recognize_program(
+      "let x = variables <- 0..10;
+      let y = variables <- 0..10;
+      constraints <- x * y == width;".into_state());
+    match state.into_result() {
+      Success(_) => (),
+      Partial(_, _)
+    | Failure(_) => assert!(false)
+    };
+  }
 }
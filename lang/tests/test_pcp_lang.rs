@@ -30,8 +30,11 @@ mod test
   use pcp::variable::memory::*;
   use pcp::kernel::*;
   use pcp::term::*;
+  use pcp::term::ops::*;
   use pcp::propagators::cmp::*;
   use pcp::propagators::distinct::*;
+  use pcp::propagators::element::*;
+  use pcp::propagators::arith::*;
 
   use pcp::search::search_tree_visitor::*;
   use pcp::search::space::*;
@@ -86,4 +89,121 @@ mod test
     }
     assert!(true);
   }
+
+  #[test]
+  fn test_element()
+  {
+    pcp! {
+      let mut variables: VStore = VStore::empty();
+      let mut constraints: CStore = CStore::empty();
+      let mut costs = vec![];
+      costs.push(#(variables <- 4..6));
+      costs.push(#(variables <- 1..3));
+      costs.push(#(variables <- 7..8));
+      let index = #(variables <- 0..2);
+      let cost = #(variables <- 0..3);
+      #(constraints <- cost == costs[index]);
+      // An `usize` index selects the variable in the array.
+      let first: usize = 0;
+      let cheapest = #(variables <- 0..10);
+      #(constraints <- cheapest == costs[first]);
+      // The index `shifted - 1` is a view.
+      let shifted = #(variables <- 1..3);
+      let most_expensive = #(variables <- 7..10);
+      #(constraints <- most_expensive == costs[shifted - 1]);
+
+      let space = FDSpace::new(variables, constraints);
+      let mut search: OneSolution<_, Vector<_>, FDSpace> =
+        OneSolution::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+      search.start(&space);
+      let (mut frozen_space, status) = search.enter(space);
+      assert_eq!(status, Status::Satisfiable);
+      let label = frozen_space.label();
+      let space = frozen_space.restore(label);
+      assert_eq!(index.read(&space.vstore), Interval::singleton(1));
+      assert_eq!(cheapest.read(&space.vstore), Interval::singleton(4));
+      assert_eq!(shifted.read(&space.vstore), Interval::singleton(3));
+    }
+  }
+
+  #[test]
+  fn test_product()
+  {
+    pcp! {
+      let mut variables: VStore = VStore::empty();
+      let mut constraints: CStore = CStore::empty();
+      let x = #(variables <- 0..10);
+      let y = #(variables <- 0..10);
+      #{
+        constraints <- x * 3 == y;
+        constraints <- y - 2 > 4;
+      }
+
+      let space = FDSpace::new(variables, constraints);
+      let mut search: OneSolution<_, Vector<_>, FDSpace> =
+        OneSolution::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+      search.start(&space);
+      let (mut frozen_space, status) = search.enter(space);
+      assert_eq!(status, Status::Satisfiable);
+      let label = frozen_space.label();
+      let space = frozen_space.restore(label);
+      assert_eq!(x.read(&space.vstore), Interval::singleton(3));
+      assert_eq!(y.read(&space.vstore), Interval::singleton(9));
+    }
+  }
+
+  #[test]
+  fn test_product_of_variables()
+  {
+    pcp! {
+      let mut variables: VStore = VStore::empty();
+      let mut constraints: CStore = CStore::empty();
+      let x = #(variables <- 0..5);
+      let y = #(variables <- 0..5);
+      let z = #(variables <- 12..12);
+      #{
+        constraints <- x * y == z;
+        constraints <- x - y == 1;
+      }
+
+      let space = FDSpace::new(variables, constraints);
+      let mut search: OneSolution<_, Vector<_>, FDSpace> =
+        OneSolution::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+      search.start(&space);
+      let (mut frozen_space, status) = search.enter(space);
+      assert_eq!(status, Status::Satisfiable);
+      let label = frozen_space.label();
+      let space = frozen_space.restore(label);
+      assert_eq!(x.read(&space.vstore), Interval::singleton(4));
+      assert_eq!(y.read(&space.vstore), Interval::singleton(3));
+    }
+  }
+
+  #[test]
+  fn test_negation()
+  {
+    pcp! {
+      let mut variables: VStore = VStore::empty();
+      let mut constraints: CStore = CStore::empty();
+      let x = #(variables <- -5..5);
+      let y = #(variables <- 0..10);
+      #{
+        constraints <- -x == y;
+        constraints <- 10 - y < 3 * x + 20;
+      }
+
+      let space = FDSpace::new(variables, constraints);
+      let mut search: OneSolution<_, Vector<_>, FDSpace> =
+        OneSolution::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+      search.start(&space);
+      let (mut frozen_space, status) = search.enter(space);
+      assert_eq!(status, Status::Satisfiable);
+      let label = frozen_space.label();
+      let space = frozen_space.restore(label);
+      let x = x.read(&space.vstore).lower();
+      let y = y.read(&space.vstore).lower();
+      assert_eq!(-x, y);
+      assert!(10 - y < 3 * x + 20);
+    }
+  }
 }
@@ -27,3 +27,12 @@ pub trait Snapshot : Sized
   fn restore(self, label: Self::Label) -> Self::State;
 }
 
+
+/// A snapshot whose labels can be sent to another thread. The labels of `Snapshot` are usually built on `Rc` and must stay on the thread of their frozen state. Instead, a `SendLabel` is a standalone description of the state which is restored from another frozen state of the same problem, for example the root of the search tree built by each thread.
+pub trait SendSnapshot : Snapshot
+{
+  type SendLabel : Send;
+
+  fn send_label(&mut self) -> Self::SendLabel;
+  fn restore_send(self, label: Self::SendLabel) -> Self::State;
+}
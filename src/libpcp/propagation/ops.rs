@@ -33,6 +33,12 @@ pub trait PropagatorDependencies<Event>
   fn dependencies(&self) -> Vec<(usize, Event)>;
 }
 
+pub trait NumPropagations
+{
+  /// Number of propagator executions performed since the creation or the restoration of the store.
+  fn num_propagations(&self) -> usize;
+}
+
 pub trait BoxedClone<VStore, Event>
 {
   fn boxed_clone(&self) -> Box<PropagatorConcept<VStore, Event>>;
@@ -19,6 +19,7 @@ use kernel::Trilean::*;
 use propagation::Reactor;
 use propagation::Scheduler;
 use propagation::concept::*;
+use propagation::ops::*;
 use variable::ops::*;
 use gcollections::ops::*;
 use std::rc::*;
@@ -28,7 +29,8 @@ pub struct Store<VStore, Event, Reactor, Scheduler>
 {
   propagators: Vec<Box<PropagatorConcept<VStore, Event> + 'static>>,
   reactor: Reactor,
-  scheduler: Scheduler
+  scheduler: Scheduler,
+  num_propagations: usize
 }
 
 impl<VStore, Event, R, S> Empty for Store<VStore, Event, R, S> where
@@ -40,7 +42,8 @@ impl<VStore, Event, R, S> Empty for Store<VStore, Event, R, S> where
     Store {
       propagators: vec![],
       reactor: Reactor::new(0,0),
-      scheduler: Scheduler::new(0)
+      scheduler: Scheduler::new(0),
+      num_propagations: 0
     }
   }
 }
@@ -59,7 +62,7 @@ impl<VStore, Event, R, S> Store<VStore, Event, R, S> where
   fn init_reactor(&mut self, store: &VStore) {
     self.reactor = Reactor::new(store.size(), Event::size());
     for (p_idx, p) in self.propagators.iter().enumerate() {
-      let p_deps = p.dependencies();
+      let p_deps = merge_dependencies(p.dependencies());
       for (v, ev) in p_deps {
         self.reactor.subscribe(v, ev, p_idx);
       }
@@ -88,6 +91,7 @@ impl<VStore, Event, R, S> Store<VStore, Event, R, S> where
   }
 
   fn propagate_one(&mut self, p_idx: usize, store: &mut VStore) -> bool {
+    self.num_propagations += 1;
     let subsumed = self.propagators[p_idx].consistency(store);
     match subsumed {
       False => return false,
@@ -115,13 +119,41 @@ impl<VStore, Event, R, S> Store<VStore, Event, R, S> where
 
   fn unlink_prop(&mut self, p_idx: usize) {
     self.scheduler.unschedule(p_idx);
-    let deps = self.propagators[p_idx].dependencies();
+    let deps = merge_dependencies(self.propagators[p_idx].dependencies());
     for &(var, ev) in deps.iter() {
       self.reactor.unsubscribe(var, ev, p_idx)
     }
   }
 }
 
+// A variable can occur several times in the dependencies of a propagator (e.g. `x` in `[x, y] <lex [y, x]`), it is subscribed once with the event reacting to the most modifications.
+fn merge_dependencies<Event>(mut deps: Vec<(usize, Event)>) -> Vec<(usize, Event)> where
+ Event: EventIndex
+{
+  deps.sort_by_key(|d| d.0);
+  let mut merged: Vec<(usize, Event)> = vec![];
+  for (v, ev) in deps {
+    let duplicate = match merged.last_mut() {
+      Some(last) if last.0 == v => {
+        if ev.to_index() > last.1.to_index() { last.1 = ev; }
+        true
+      }
+      _ => false
+    };
+    if !duplicate {
+      merged.push((v, ev));
+    }
+  }
+  merged
+}
+
+impl<VStore, Event, R, S> NumPropagations for Store<VStore, Event, R, S>
+{
+  fn num_propagations(&self) -> usize {
+    self.num_propagations
+  }
+}
+
 impl<Prop, VStore, Event, R, S> Alloc<Prop> for Store<VStore, Event, R, S> where
  Prop: PropagatorConcept<VStore, Event> + 'static
 {
@@ -175,6 +207,7 @@ pub struct FrozenStore<VStore, Event, R, S> where
  S: Scheduler
 {
   cstore: Rc<(Vec<Box<PropagatorConcept<VStore, Event> + 'static>>, R)>,
+  num_propagations: usize,
   phantom_scheduler: PhantomData<S>
 }
 
@@ -186,11 +219,22 @@ impl<VStore, Event, R, S> FrozenStore<VStore, Event, R, S> where
   fn new(store: Store<VStore, Event, R, S>) -> Self {
     FrozenStore {
       cstore: Rc::new((store.propagators, store.reactor)),
+      num_propagations: store.num_propagations,
       phantom_scheduler: PhantomData
     }
   }
 }
 
+impl<VStore, Event, R, S> NumPropagations for FrozenStore<VStore, Event, R, S> where
+ Event: EventIndex,
+ R: Reactor + Clone,
+ S: Scheduler
+{
+  fn num_propagations(&self) -> usize {
+    self.num_propagations
+  }
+}
+
 impl<VStore, Event, R, S> Snapshot for FrozenStore<VStore, Event, R, S> where
  Event: EventIndex,
  R: Reactor + Clone,
@@ -211,11 +255,32 @@ impl<VStore, Event, R, S> Snapshot for FrozenStore<VStore, Event, R, S> where
     Store {
       propagators: props,
       reactor: reactor,
-      scheduler: Scheduler::new(0)
+      scheduler: Scheduler::new(0),
+      num_propagations: 0
     }
   }
 }
 
+// Propagators are not `Send`, so they are never transferred: the label only records their number and the store is restored with the propagators of the frozen store, which must be the root (or an ancestor) of the labelled node. Propagators are re-executed on the current domains so their root state is valid in the node. The propagators added since then, such as the decisions of the branching, are dropped and must be entailed by the domains of the node.
+impl<VStore, Event, R, S> SendSnapshot for FrozenStore<VStore, Event, R, S> where
+ Event: EventIndex,
+ R: Reactor + Clone,
+ S: Scheduler
+{
+  type SendLabel = usize;
+
+  fn send_label(&mut self) -> Self::SendLabel {
+    self.cstore.0.len()
+  }
+
+  fn restore_send(mut self, label: Self::SendLabel) -> Self::State {
+    assert!(self.cstore.0.len() <= label,
+      "The constraint store must be restored from an ancestor of the labelled node.");
+    let own_label = self.label();
+    self.restore(own_label)
+  }
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -256,6 +321,28 @@ mod test {
     assert_eq!(constraints.consistency(variables), True);
   }
 
+  #[test]
+  fn merge_dependencies_test() {
+    use propagation::events::FDEvent::*;
+    assert_eq!(merge_dependencies(vec![(1, Assignment), (0, Bound), (1, Inner), (0, Assignment)]),
+      vec![(0, Bound), (1, Inner)]);
+    assert_eq!(merge_dependencies(vec![(2, Bound), (0, Assignment)]),
+      vec![(0, Assignment), (2, Bound)]);
+  }
+
+  #[test]
+  fn duplicate_dependencies() {
+    let variables: &mut VStore = &mut VStore::empty();
+    let mut constraints: CStore = CStore::empty();
+    let var1 = variables.alloc(Interval::new(1,4));
+    let var2 = variables.alloc(Interval::new(3,3));
+
+    // Each variable is subscribed once, `var2` is unsubscribed once since its propagator is entailed.
+    constraints.alloc(XLessY::new(var1, Addition::new(var1, 1)));
+    constraints.alloc(XLessY::new(var2, Addition::new(var2, 1)));
+    assert_eq!(constraints.consistency(variables), Unknown);
+  }
+
   fn chained_lt(n: usize, expect: Trilean) {
     // X1 < X2 < X3 < ... < XN, all in dom [1, 10]
     let variables: &mut VStore = &mut VStore::empty();
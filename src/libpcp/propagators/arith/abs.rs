@@ -0,0 +1,157 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::arith::*;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+// Bounds of `{|x|}`, a bound is `None` if it overflows.
+fn abs_bounds<B: PrimInt>(x: (B, B)) -> (Option<B>, Option<B>) {
+  if x.0 >= B::zero() { (Some(x.0), Some(x.1)) }
+  else if x.1 <= B::zero() { (B::zero().checked_sub(&x.1), B::zero().checked_sub(&x.0)) }
+  else { (Some(B::zero()), max_abs(x)) }
+}
+
+/// `|x| = y`.
+#[derive(Clone, Copy)]
+pub struct Abs<X, Y>
+{
+  x: X,
+  y: Y
+}
+
+impl<X, Y> PropagatorKind for Abs<X, Y> {}
+
+impl<X, Y> Abs<X, Y> {
+  pub fn new(x: X, y: Y) -> Abs<X, Y> {
+    Abs { x: x, y: y }
+  }
+}
+
+impl<X, Y> Debug for Abs<X, Y> where
+  X: Debug,
+  Y: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("|{:?}| = {:?}", self.x, self.y))
+  }
+}
+
+impl<Store, Domain, B, X, Y> Subsumption<Store> for Abs<X, Y> where
+  X: StoreRead<Store, Value=Domain>,
+  Y: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let x = bounds(&self.x, store);
+    let y = bounds(&self.y, store);
+    let (lb, ub) = abs_bounds(x);
+    if lb.map_or(false, |lb| y.1 < lb) || ub.map_or(false, |ub| y.0 > ub) { False }
+    // `|x|` is not representable if it overflows.
+    else if is_fixed(x) && is_fixed(y) { if lb.is_some() { True } else { False } }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, X, Y> Propagator<Store> for Abs<X, Y> where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let (yl, yu) = bounds(&self.y, store);
+    if !shrink_bounds(&self.y, store, (max(yl, B::zero()), yu)) {
+      return false;
+    }
+    // `x` is in `[-yu..-yl] U [yl..yu]`.
+    let (yl, yu) = bounds(&self.y, store);
+    let (mut xl, mut xu) = bounds(&self.x, store);
+    xl = max(xl, B::zero() - yu);
+    xu = min(xu, yu);
+    if xl > B::zero() - yl { xl = max(xl, yl); }
+    if xu < yl { xu = min(xu, B::zero() - yl); }
+    if !shrink_bounds(&self.x, store, (xl, xu)) {
+      return false;
+    }
+    let (lb, ub) = abs_bounds(bounds(&self.x, store));
+    shrink_bounds(&self.y, store, (lb.map_or(yl, |lb| max(yl, lb)), ub.map_or(yu, |ub| min(yu, ub))))
+  }
+}
+
+impl<X, Y> PropagatorDependencies<FDEvent> for Abs<X, Y> where
+  X: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.x.dependencies(FDEvent::Bound);
+    deps.append(&mut self.y.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use interval::interval::*;
+  use propagators::test::*;
+  use propagators::arith::test::*;
+
+  #[test]
+  fn abs_test() {
+    let dom_5_3 = (-5,3).to_interval();
+    let dom_5_m1 = (-5,-1).to_interval();
+    let dom0_10 = (0,10).to_interval();
+    let dom_10_10 = (-10,10).to_interval();
+    let dom2_4 = (2,4).to_interval();
+    let dom4_6 = (4,6).to_interval();
+    let three = (3,3).to_interval();
+    let m3 = (-3,-3).to_interval();
+
+    abs_test_one(1, dom_5_3, dom_10_10, Unknown, Unknown, vec![(1, Bound)], true);
+    abs_test_one(2, dom_5_m1, dom2_4, Unknown, Unknown, vec![(0, Bound)], true);
+    abs_test_one(3, dom_5_3, dom4_6, Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    abs_test_one(4, m3, dom0_10, Unknown, True, vec![(1, Assignment)], true);
+    abs_test_one(5, m3, three, True, True, vec![], true);
+    abs_test_one(6, dom2_4, dom4_6, Unknown, True, vec![(0, Assignment), (1, Assignment)], true);
+    abs_test_one(7, dom_5_m1, (6,8).to_interval(), False, False, vec![], false);
+  }
+
+  fn abs_test_one(test_num: u32, x: Interval<i32>, y: Interval<i32>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    binary_propagator_test(test_num, Abs::new, x, y, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn exhaustive_abs() {
+    exhaustive_check(|vars| Abs::new(vars[0], vars[1]), |x, y, _| x.abs() == y);
+    boundary_check(|vars| Abs::new(vars[0], vars[1]), |x, y, _| x.abs() == y);
+    bounds_consistency_check(|vars| Abs::new(vars[0], vars[1]), |x, y, _| x.abs() == y);
+  }
+}
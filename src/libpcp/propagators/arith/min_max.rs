@@ -0,0 +1,286 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::arith::*;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+/// `min(vars) = z`.
+#[derive(Clone)]
+pub struct Min<V, Z>
+{
+  vars: Vec<V>,
+  z: Z
+}
+
+impl<V, Z> PropagatorKind for Min<V, Z> {}
+
+impl<V, Z> Min<V, Z> {
+  pub fn new(vars: Vec<V>, z: Z) -> Min<V, Z> {
+    assert!(!vars.is_empty(), "The minimum of an empty set of variables is not defined.");
+    Min { vars: vars, z: z }
+  }
+}
+
+impl<V, Z> Debug for Min<V, Z> where
+  V: Debug,
+  Z: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("min({:?}) = {:?}", self.vars, self.z))
+  }
+}
+
+impl<Store, Domain, B, V, Z> Subsumption<Store> for Min<V, Z> where
+  V: StoreRead<Store, Value=Domain>,
+  Z: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let xs: Vec<_> = self.vars.iter().map(|x| bounds(x, store)).collect();
+    let z = bounds(&self.z, store);
+    let lb = xs.iter().map(|x| x.0).min().unwrap();
+    let ub = xs.iter().map(|x| x.1).min().unwrap();
+    if z.1 < lb || z.0 > ub { False }
+    else if is_fixed(z) && lb == z.0 && ub == z.0 { True }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, V, Z> Propagator<Store> for Min<V, Z> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Z: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let xs: Vec<_> = self.vars.iter().map(|x| bounds(x, store)).collect();
+    let z = bounds(&self.z, store);
+    let lb = xs.iter().map(|x| x.0).min().unwrap();
+    let ub = xs.iter().map(|x| x.1).min().unwrap();
+    if !shrink_bounds(&self.z, store, (max(z.0, lb), min(z.1, ub))) {
+      return false;
+    }
+    let z = bounds(&self.z, store);
+    for (x, &(l, u)) in self.vars.iter().zip(xs.iter()) {
+      if !shrink_bounds(x, store, (max(l, z.0), u)) {
+        return false;
+      }
+    }
+    // If a single variable can be smaller than the upper bound of `z`, it is the minimum.
+    let mut support = self.vars.iter().filter(|x| bounds(*x, store).0 <= z.1);
+    match (support.next(), support.next()) {
+      (Some(x), None) => {
+        let (l, u) = bounds(x, store);
+        shrink_bounds(x, store, (l, min(u, z.1)))
+      }
+      (None, _) => false,
+      _ => true
+    }
+  }
+}
+
+impl<V, Z> PropagatorDependencies<FDEvent> for Min<V, Z> where
+  V: ViewDependencies<FDEvent>,
+  Z: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps: Vec<_> = self.vars.iter().flat_map(|x| x.dependencies(FDEvent::Bound)).collect();
+    deps.append(&mut self.z.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+/// `max(vars) = z`.
+#[derive(Clone)]
+pub struct Max<V, Z>
+{
+  vars: Vec<V>,
+  z: Z
+}
+
+impl<V, Z> PropagatorKind for Max<V, Z> {}
+
+impl<V, Z> Max<V, Z> {
+  pub fn new(vars: Vec<V>, z: Z) -> Max<V, Z> {
+    assert!(!vars.is_empty(), "The maximum of an empty set of variables is not defined.");
+    Max { vars: vars, z: z }
+  }
+}
+
+impl<V, Z> Debug for Max<V, Z> where
+  V: Debug,
+  Z: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("max({:?}) = {:?}", self.vars, self.z))
+  }
+}
+
+impl<Store, Domain, B, V, Z> Subsumption<Store> for Max<V, Z> where
+  V: StoreRead<Store, Value=Domain>,
+  Z: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let xs: Vec<_> = self.vars.iter().map(|x| bounds(x, store)).collect();
+    let z = bounds(&self.z, store);
+    let lb = xs.iter().map(|x| x.0).max().unwrap();
+    let ub = xs.iter().map(|x| x.1).max().unwrap();
+    if z.1 < lb || z.0 > ub { False }
+    else if is_fixed(z) && lb == z.0 && ub == z.0 { True }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, V, Z> Propagator<Store> for Max<V, Z> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Z: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let xs: Vec<_> = self.vars.iter().map(|x| bounds(x, store)).collect();
+    let z = bounds(&self.z, store);
+    let lb = xs.iter().map(|x| x.0).max().unwrap();
+    let ub = xs.iter().map(|x| x.1).max().unwrap();
+    if !shrink_bounds(&self.z, store, (max(z.0, lb), min(z.1, ub))) {
+      return false;
+    }
+    let z = bounds(&self.z, store);
+    for (x, &(l, u)) in self.vars.iter().zip(xs.iter()) {
+      if !shrink_bounds(x, store, (l, min(u, z.1))) {
+        return false;
+      }
+    }
+    // If a single variable can be greater than the lower bound of `z`, it is the maximum.
+    let mut support = self.vars.iter().filter(|x| bounds(*x, store).1 >= z.0);
+    match (support.next(), support.next()) {
+      (Some(x), None) => {
+        let (l, u) = bounds(x, store);
+        shrink_bounds(x, store, (max(l, z.0), u))
+      }
+      (None, _) => false,
+      _ => true
+    }
+  }
+}
+
+impl<V, Z> PropagatorDependencies<FDEvent> for Max<V, Z> where
+  V: ViewDependencies<FDEvent>,
+  Z: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps: Vec<_> = self.vars.iter().flat_map(|x| x.dependencies(FDEvent::Bound)).collect();
+    deps.append(&mut self.z.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use interval::interval::*;
+  use propagators::test::*;
+  use propagators::arith::test::*;
+  use term::*;
+  use variable::test::*;
+
+  #[test]
+  fn min_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom5_10 = (5,10).to_interval();
+    let dom2_4 = (2,4).to_interval();
+    let dom6_8 = (6,8).to_interval();
+    let three = (3,3).to_interval();
+    let five = (5,5).to_interval();
+
+    min_test_one(1, dom0_10, dom0_10, dom0_10, Unknown, Unknown, vec![], true);
+    min_test_one(2, dom5_10, dom0_10, dom2_4, Unknown, Unknown, vec![(1, Bound)], true);
+    min_test_one(3, dom5_10, dom6_8, dom0_10, Unknown, Unknown, vec![(2, Bound)], true);
+    min_test_one(4, five, dom6_8, dom0_10, Unknown, True, vec![(2, Assignment)], true);
+    min_test_one(5, three, five, three, True, True, vec![], true);
+    min_test_one(6, dom5_10, dom6_8, dom2_4, False, False, vec![], false);
+  }
+
+  fn min_test_one(test_num: u32, x: Interval<i32>, y: Interval<i32>, z: Interval<i32>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| Min::new(vec![vars[0], vars[1]], vars[2]),
+      vec![x, y, z], before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn max_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom0_5 = (0,5).to_interval();
+    let dom6_8 = (6,8).to_interval();
+    let dom7_12 = (7,12).to_interval();
+    let dom11_12 = (11,12).to_interval();
+    let five = (5,5).to_interval();
+
+    max_test_one(1, dom0_10, dom0_10, dom0_10, Unknown, Unknown, vec![], true);
+    max_test_one(2, dom0_5, dom0_10, dom7_12, Unknown, Unknown, vec![(1, Bound), (2, Bound)], true);
+    max_test_one(3, dom0_5, dom6_8, dom0_10, Unknown, Unknown, vec![(2, Bound)], true);
+    max_test_one(4, dom0_5, five, five, True, True, vec![], true);
+    max_test_one(5, dom0_5, dom6_8, dom11_12, False, False, vec![], false);
+  }
+
+  fn max_test_one(test_num: u32, x: Interval<i32>, y: Interval<i32>, z: Interval<i32>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| Max::new(vec![vars[0], vars[1]], vars[2]),
+      vec![x, y, z], before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn exhaustive_min_max() {
+    exhaustive_check(|vars| Min::new(vec![vars[0], vars[1]], vars[2]), |x, y, z| min(x, y) == z);
+    exhaustive_check(|vars| Max::new(vec![vars[0], vars[1]], vars[2]), |x, y, z| max(x, y) == z);
+    bounds_consistency_check(|vars| Min::new(vec![vars[0], vars[1]], vars[2]), |x, y, z| min(x, y) == z);
+    bounds_consistency_check(|vars| Max::new(vec![vars[0], vars[1]], vars[2]), |x, y, z| max(x, y) == z);
+  }
+
+  #[test]
+  fn makespan() {
+    // The makespan of three tasks starting at `s_i` with the durations 2, 3 and 4.
+    let mut store = StoreI32::empty();
+    let starts: Vec<_> = (0..3).map(|_| store.alloc((0,10).to_interval())).collect();
+    let makespan = store.alloc((0,7).to_interval());
+    let ends: Vec<_> = starts.iter().zip(vec![2, 3, 4])
+      .map(|(&s, d)| Addition::new(s, d))
+      .collect();
+    let mut max = Max::new(ends, makespan);
+    assert!(max.propagate(&mut store));
+    assert_eq!(makespan.read(&store), (4,7).to_interval());
+    assert_eq!(starts[0].read(&store), (0,5).to_interval());
+    assert_eq!(starts[2].read(&store), (0,3).to_interval());
+  }
+}
@@ -0,0 +1,175 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Arithmetic constraints: `x * y = z`, `x / y = z`, `x % y = z`, `min(vars) = z`, `max(vars) = z` and `|x| = y`. The division and the remainder follow the semantics of Rust, the quotient is rounded toward zero and the remainder has the sign of `x`.
+//!
+//! The filtering is done on the bounds of the domains, the products are computed with overflow checks and no pruning is done when an overflow occurs.
+
+pub mod x_times_y_eq_z;
+pub mod x_div_y_eq_z;
+pub mod x_mod_y_eq_z;
+pub mod min_max;
+pub mod abs;
+
+pub use propagators::arith::x_times_y_eq_z::{XTimesYEqZ, Factor, x_times_y};
+pub use propagators::arith::x_div_y_eq_z::XDivYEqZ;
+pub use propagators::arith::x_mod_y_eq_z::XModYEqZ;
+pub use propagators::arith::min_max::{Min, Max};
+pub use propagators::arith::abs::Abs;
+
+use term::ops::*;
+use gcollections::ops::*;
+use propagators::bounds::{bounds, shrink_bounds};
+use propagators::division::{div_floor, div_ceil};
+use num::PrimInt;
+use std::cmp::{min, max};
+
+fn is_fixed<B: PrimInt>(x: (B, B)) -> bool {
+  x.0 == x.1
+}
+
+fn contains_zero<B: PrimInt>(x: (B, B)) -> bool {
+  x.0 <= B::zero() && B::zero() <= x.1
+}
+
+fn hull<B: PrimInt>(a: Option<(B, B)>, b: Option<(B, B)>) -> Option<(B, B)> {
+  match (a, b) {
+    (Some(a), Some(b)) => Some((min(a.0, b.0), max(a.1, b.1))),
+    (a, None) => a,
+    (None, b) => b
+  }
+}
+
+// Negative and positive parts of `x`, zero excluded.
+fn split_sign<B: PrimInt>(x: (B, B)) -> (Option<(B, B)>, Option<(B, B)>) {
+  let neg = if x.0 < B::zero() { Some((x.0, min(x.1, B::zero() - B::one()))) } else { None };
+  let pos = if x.1 > B::zero() { Some((max(x.0, B::one()), x.1)) } else { None };
+  (neg, pos)
+}
+
+// Bounds of `{x * y}`, `None` if a product overflows.
+fn mul_bounds<B: PrimInt>(x: (B, B), y: (B, B)) -> Option<(B, B)> {
+  let corners = [x.0.checked_mul(&y.0), x.0.checked_mul(&y.1), x.1.checked_mul(&y.0), x.1.checked_mul(&y.1)];
+  if corners.iter().any(|c| c.is_none()) {
+    return None;
+  }
+  let corners: Vec<B> = corners.iter().map(|c| c.unwrap()).collect();
+  Some((*corners.iter().min().unwrap(), *corners.iter().max().unwrap()))
+}
+
+// Bounds of the integers `x` such that `x * y = z` with `y` in a range not containing `0`, `None` if there is none. A quotient only overflows for `MIN / -1`, it is then approximated by `MAX`.
+fn exact_div_bounds<B: PrimInt>(z: (B, B), y: (B, B)) -> Option<(B, B)> {
+  let ceil = |a, b| div_ceil(a, b).unwrap_or(B::max_value());
+  let floor = |a, b| div_floor(a, b).unwrap_or(B::max_value());
+  let lower = [ceil(z.0, y.0), ceil(z.0, y.1), ceil(z.1, y.0), ceil(z.1, y.1)];
+  let upper = [floor(z.0, y.0), floor(z.0, y.1), floor(z.1, y.0), floor(z.1, y.1)];
+  let (lb, ub) = (*lower.iter().min().unwrap(), *upper.iter().max().unwrap());
+  if lb <= ub { Some((lb, ub)) } else { None }
+}
+
+// Bounds of the integers `x` such that `x * y = z` for some `y` in `y` and `z` in `z`. `Some(None)` if there is none and `None` if `x` is not constrained (`0 * 0 = 0`).
+fn factor_bounds<B: PrimInt>(z: (B, B), y: (B, B)) -> Option<Option<(B, B)>> {
+  if contains_zero(y) && contains_zero(z) {
+    None
+  }
+  else {
+    let (neg, pos) = split_sign(y);
+    Some(hull(neg.and_then(|n| exact_div_bounds(z, n)), pos.and_then(|p| exact_div_bounds(z, p))))
+  }
+}
+
+// Bounds of `{x / y}` (rounded toward zero) with `y` in a range not containing `0`, `None` if a division overflows.
+fn trunc_div_bounds<B: PrimInt>(x: (B, B), y: (B, B)) -> Option<(B, B)> {
+  let corners = [x.0.checked_div(&y.0), x.0.checked_div(&y.1), x.1.checked_div(&y.0), x.1.checked_div(&y.1)];
+  if corners.iter().any(|c| c.is_none()) {
+    return None;
+  }
+  let corners: Vec<B> = corners.iter().map(|c| c.unwrap()).collect();
+  Some((*corners.iter().min().unwrap(), *corners.iter().max().unwrap()))
+}
+
+// `x % y` where `y != 0`, it does not overflow when `y = -1`.
+fn rem<B: PrimInt>(x: B, y: B) -> B {
+  if y < B::zero() && y + B::one() == B::zero() { B::zero() }
+  else { x % y }
+}
+
+// Largest absolute value in `x`, `None` if it overflows.
+fn max_abs<B: PrimInt>(x: (B, B)) -> Option<B> {
+  B::zero().checked_sub(&x.0).map(|l| max(l, x.1))
+}
+
+// Shrinks the domain of `y` such that it does not contain `0` at its bounds.
+fn shrink_non_zero<Store, Domain, B, V>(v: &V, store: &mut Store) -> bool where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  let (mut lb, mut ub) = bounds(v, store);
+  if lb == B::zero() { lb = B::one(); }
+  if ub == B::zero() { ub = B::zero() - B::one(); }
+  shrink_bounds(v, store, (lb, ub))
+}
+
+#[cfg(test)]
+mod test {
+  use propagation::*;
+  use variable::test::*;
+  use propagators::test::*;
+  use gcollections::ops::*;
+  use interval::ops::Whole;
+
+  // Checks that the propagation of `make_prop` keeps every solution of `relation` and is exact when the variables are fixed.
+  pub fn exhaustive_check<P, F, R>(make_prop: F, relation: R) where
+    P: Propagator<StoreI32>,
+    F: Fn(Vec<FDVar>) -> P,
+    R: Fn(i32, i32, i32) -> bool
+  {
+    for doms in interval_tuples(&intervals(-3, 3), 3) {
+      let result = fixpoint(&make_prop, &doms);
+      let expected = solutions_hull(&doms, |w| relation(w[0], w[1], w[2]));
+      check_soundness(&doms, &result, &expected);
+    }
+  }
+
+  // Same as `exhaustive_check` with domains at the limits of the interval domains, where the products and the quotients overflow. `relation` is computed on `i64` so it does not overflow.
+  pub fn boundary_check<P, F, R>(make_prop: F, relation: R) where
+    P: Propagator<StoreI32>,
+    F: Fn(Vec<FDVar>) -> P,
+    R: Fn(i64, i64, i64) -> bool
+  {
+    let whole = DomainI32::whole();
+    let ivs: Vec<(i32, i32)> = vec![whole.lower(), -2, whole.upper() - 4].into_iter()
+      .flat_map(|lb| intervals(0, 4).into_iter().map(move |(l, u)| (lb + l, lb + u)))
+      .collect();
+    for doms in interval_tuples(&ivs, 3) {
+      let result = fixpoint(&make_prop, &doms);
+      let expected = solutions_hull(&doms, |w| relation(w[0] as i64, w[1] as i64, w[2] as i64));
+      check_soundness(&doms, &result, &expected);
+    }
+  }
+
+  // Checks that the fixed point of `make_prop` is the hull of the solutions of `relation`.
+  pub fn bounds_consistency_check<P, F, R>(make_prop: F, relation: R) where
+    P: Propagator<StoreI32>,
+    F: Fn(Vec<FDVar>) -> P,
+    R: Fn(i32, i32, i32) -> bool
+  {
+    for doms in interval_tuples(&intervals(-3, 3), 3) {
+      let expected = solutions_hull(&doms, |w| relation(w[0], w[1], w[2]));
+      assert_eq!(fixpoint(&make_prop, &doms), expected, "{:?}", doms);
+    }
+  }
+}
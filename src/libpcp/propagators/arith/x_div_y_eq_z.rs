@@ -0,0 +1,176 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::arith::*;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::fmt::{Formatter, Debug, Error};
+
+/// `x / y = z` where the quotient is rounded toward zero and `y != 0`.
+#[derive(Clone, Copy)]
+pub struct XDivYEqZ<X, Y, Z>
+{
+  x: X,
+  y: Y,
+  z: Z
+}
+
+impl<X, Y, Z> PropagatorKind for XDivYEqZ<X, Y, Z> {}
+
+impl<X, Y, Z> XDivYEqZ<X, Y, Z> {
+  pub fn new(x: X, y: Y, z: Z) -> XDivYEqZ<X, Y, Z> {
+    XDivYEqZ { x: x, y: y, z: z }
+  }
+}
+
+impl<X, Y, Z> Debug for XDivYEqZ<X, Y, Z> where
+  X: Debug,
+  Y: Debug,
+  Z: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} / {:?} = {:?}", self.x, self.y, self.z))
+  }
+}
+
+// Bounds of `{x / y}` where `y` is split in its negative and positive parts.
+fn quotient_bounds<B: PrimInt>(x: (B, B), y: (B, B)) -> Option<Option<(B, B)>> {
+  let (neg, pos) = split_sign(y);
+  let neg = match neg { Some(n) => match trunc_div_bounds(x, n) { None => return None, q => q }, None => None };
+  let pos = match pos { Some(p) => match trunc_div_bounds(x, p) { None => return None, q => q }, None => None };
+  Some(hull(neg, pos))
+}
+
+impl<Store, Domain, B, X, Y, Z> Subsumption<Store> for XDivYEqZ<X, Y, Z> where
+  X: StoreRead<Store, Value=Domain>,
+  Y: StoreRead<Store, Value=Domain>,
+  Z: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let x = bounds(&self.x, store);
+    let y = bounds(&self.y, store);
+    let z = bounds(&self.z, store);
+    match quotient_bounds(x, y) {
+      Some(None) => False,
+      Some(Some(q)) if q.1 < z.0 || z.1 < q.0 => False,
+      Some(Some(q)) if is_fixed(q) && is_fixed(z) && is_fixed(x) && is_fixed(y) => True,
+      _ => Unknown
+    }
+  }
+}
+
+impl<Store, Domain, B, X, Y, Z> Propagator<Store> for XDivYEqZ<X, Y, Z> where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Z: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    if !shrink_non_zero(&self.y, store) {
+      return false;
+    }
+    let y = bounds(&self.y, store);
+    if let Some(q) = quotient_bounds(bounds(&self.x, store), y) {
+      match q {
+        Some(q) => if !shrink_bounds(&self.z, store, q) { return false; },
+        None => return false
+      }
+    }
+    // `x = y * z + r` where `|r| < |y|`.
+    let z = bounds(&self.z, store);
+    if let (Some(yz), Some(m)) = (mul_bounds(y, z), max_abs(y)) {
+      let r = m - B::one();
+      if let (Some(lb), Some(ub)) = (yz.0.checked_sub(&r), yz.1.checked_add(&r)) {
+        if !shrink_bounds(&self.x, store, (lb, ub)) {
+          return false;
+        }
+      }
+    }
+    // `|x| >= |y| * |z|` so `|y| <= |x| / |z|` when `z` does not contain `0`. Nothing is pruned if `|z|` overflows.
+    if !contains_zero(z) {
+      let min_z = if z.0 > B::zero() { Some(z.0) } else { B::zero().checked_sub(&z.1) };
+      if let (Some(min_z), Some(m)) = (min_z, max_abs(bounds(&self.x, store))) {
+        let k = m / min_z;
+        return shrink_bounds(&self.y, store, (B::zero() - k, k)) && shrink_non_zero(&self.y, store);
+      }
+    }
+    true
+  }
+}
+
+impl<X, Y, Z> PropagatorDependencies<FDEvent> for XDivYEqZ<X, Y, Z> where
+  X: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>,
+  Z: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.x.dependencies(FDEvent::Bound);
+    deps.append(&mut self.y.dependencies(FDEvent::Bound));
+    deps.append(&mut self.z.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use interval::interval::*;
+  use propagators::test::*;
+  use propagators::arith::test::*;
+
+  #[test]
+  fn x_div_y_eq_z_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom_10_10 = (-10,10).to_interval();
+    let dom0_2 = (0,2).to_interval();
+    let dom2_3 = (2,3).to_interval();
+    let zero = (0,0).to_interval();
+    let three = (3,3).to_interval();
+    let seven = (7,7).to_interval();
+
+    x_div_y_eq_z_test_one(1, seven, dom2_3, dom0_10, Unknown, Unknown, vec![(2, Bound)], true);
+    x_div_y_eq_z_test_one(2, seven, dom0_2, dom0_10, Unknown, Unknown, vec![(1, Bound), (2, Bound)], true);
+    x_div_y_eq_z_test_one(3, dom_10_10, three, dom2_3, Unknown, Unknown, vec![(0, Bound)], true);
+    x_div_y_eq_z_test_one(4, seven, zero, dom0_10, False, False, vec![], false);
+    x_div_y_eq_z_test_one(5, (-7,-7).to_interval(), dom2_3, dom_10_10, Unknown, Unknown, vec![(2, Bound)], true);
+  }
+
+  fn x_div_y_eq_z_test_one(test_num: u32, x: Interval<i32>, y: Interval<i32>, z: Interval<i32>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| XDivYEqZ::new(vars[0], vars[1], vars[2]),
+      vec![x, y, z], before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn exhaustive_x_div_y_eq_z() {
+    exhaustive_check(|vars| XDivYEqZ::new(vars[0], vars[1], vars[2]), |x, y, z| y != 0 && x / y == z);
+    boundary_check(|vars| XDivYEqZ::new(vars[0], vars[1], vars[2]), |x, y, z| y != 0 && x / y == z);
+  }
+}
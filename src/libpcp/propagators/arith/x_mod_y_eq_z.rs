@@ -0,0 +1,183 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::arith::*;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+/// `x % y = z` where the remainder has the sign of `x` and `y != 0`.
+#[derive(Clone, Copy)]
+pub struct XModYEqZ<X, Y, Z>
+{
+  x: X,
+  y: Y,
+  z: Z
+}
+
+impl<X, Y, Z> PropagatorKind for XModYEqZ<X, Y, Z> {}
+
+impl<X, Y, Z> XModYEqZ<X, Y, Z> {
+  pub fn new(x: X, y: Y, z: Z) -> XModYEqZ<X, Y, Z> {
+    XModYEqZ { x: x, y: y, z: z }
+  }
+}
+
+impl<X, Y, Z> Debug for XModYEqZ<X, Y, Z> where
+  X: Debug,
+  Y: Debug,
+  Z: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} % {:?} = {:?}", self.x, self.y, self.z))
+  }
+}
+
+impl<Store, Domain, B, X, Y, Z> Subsumption<Store> for XModYEqZ<X, Y, Z> where
+  X: StoreRead<Store, Value=Domain>,
+  Y: StoreRead<Store, Value=Domain>,
+  Z: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let x = bounds(&self.x, store);
+    let y = bounds(&self.y, store);
+    let z = bounds(&self.z, store);
+    if y == (B::zero(), B::zero()) {
+      False
+    }
+    else if is_fixed(x) && is_fixed(y) && is_fixed(z) {
+      if rem(x.0, y.0) == z.0 { True } else { False }
+    }
+    else {
+      Unknown
+    }
+  }
+}
+
+impl<Store, Domain, B, X, Y, Z> Propagator<Store> for XModYEqZ<X, Y, Z> where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Z: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    // `|y| > |z|`, in particular `y != 0`. There is no such `y` if `|z|` is at least `MAX`.
+    let z = bounds(&self.z, store);
+    let min_z =
+      if contains_zero(z) { Some(B::zero()) }
+      else if z.0 > B::zero() { Some(z.0) }
+      else { B::zero().checked_sub(&z.1) };
+    let min_y = match min_z.and_then(|m| m.checked_add(&B::one())) {
+      Some(m) => m,
+      None => return false
+    };
+    let (mut lb, mut ub) = bounds(&self.y, store);
+    if B::one() - min_y <= lb && lb < min_y { lb = min_y; }
+    if B::one() - min_y <= ub && ub < min_y { ub = B::zero() - min_y; }
+    if !shrink_bounds(&self.y, store, (lb, ub)) {
+      return false;
+    }
+    // `z` has the sign of `x`, `|z| <= |x|` and `|z| < |y|`.
+    let x = bounds(&self.x, store);
+    let y = bounds(&self.y, store);
+    let mut z_bounds = (min(B::zero(), x.0), max(B::zero(), x.1));
+    if let Some(m) = max_abs(y) {
+      z_bounds = (max(z_bounds.0, B::one() - m), min(z_bounds.1, m - B::one()));
+    }
+    if is_fixed(x) && is_fixed(y) {
+      let r = rem(x.0, y.0);
+      z_bounds = (r, r);
+    }
+    if !shrink_bounds(&self.z, store, z_bounds) {
+      return false;
+    }
+    let z = bounds(&self.z, store);
+    if z.0 > B::zero() {
+      shrink_bounds(&self.x, store, (z.0, x.1))
+    }
+    else if z.1 < B::zero() {
+      shrink_bounds(&self.x, store, (x.0, z.1))
+    }
+    else {
+      true
+    }
+  }
+}
+
+impl<X, Y, Z> PropagatorDependencies<FDEvent> for XModYEqZ<X, Y, Z> where
+  X: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>,
+  Z: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.x.dependencies(FDEvent::Bound);
+    deps.append(&mut self.y.dependencies(FDEvent::Bound));
+    deps.append(&mut self.z.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use interval::interval::*;
+  use propagators::test::*;
+  use propagators::arith::test::*;
+
+  #[test]
+  fn x_mod_y_eq_z_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom_10_10 = (-10,10).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let dom2_3 = (2,3).to_interval();
+    let zero = (0,0).to_interval();
+    let three = (3,3).to_interval();
+    let seven = (7,7).to_interval();
+
+    x_mod_y_eq_z_test_one(1, dom0_10, three, dom_10_10, Unknown, Unknown, vec![(2, Bound)], true);
+    x_mod_y_eq_z_test_one(2, seven, three, dom0_10, Unknown, True, vec![(2, Assignment)], true);
+    x_mod_y_eq_z_test_one(3, dom_10_10, dom0_3, dom2_3, Unknown, Unknown, vec![(0, Bound), (1, Assignment), (2, Assignment)], true);
+    x_mod_y_eq_z_test_one(4, seven, zero, dom0_10, False, False, vec![], false);
+    x_mod_y_eq_z_test_one(5, seven, three, dom2_3, Unknown, Unknown, vec![], false);
+  }
+
+  fn x_mod_y_eq_z_test_one(test_num: u32, x: Interval<i32>, y: Interval<i32>, z: Interval<i32>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| XModYEqZ::new(vars[0], vars[1], vars[2]),
+      vec![x, y, z], before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn exhaustive_x_mod_y_eq_z() {
+    exhaustive_check(|vars| XModYEqZ::new(vars[0], vars[1], vars[2]), |x, y, z| y != 0 && x % y == z);
+    boundary_check(|vars| XModYEqZ::new(vars[0], vars[1], vars[2]), |x, y, z| y != 0 && x % y == z);
+  }
+}
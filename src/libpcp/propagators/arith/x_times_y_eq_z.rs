@@ -0,0 +1,234 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::arith::*;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use term::Times;
+use gcollections::ops::*;
+use interval::ops::{Range, Whole};
+use num::PrimInt;
+use std::fmt::{Formatter, Debug, Error};
+
+#[derive(Clone, Copy)]
+pub struct XTimesYEqZ<X, Y, Z>
+{
+  x: X,
+  y: Y,
+  z: Z
+}
+
+impl<X, Y, Z> PropagatorKind for XTimesYEqZ<X, Y, Z> {}
+
+impl<X, Y, Z> XTimesYEqZ<X, Y, Z> {
+  pub fn new(x: X, y: Y, z: Z) -> XTimesYEqZ<X, Y, Z> {
+    XTimesYEqZ { x: x, y: y, z: z }
+  }
+}
+
+impl<X, Y, Z> Debug for XTimesYEqZ<X, Y, Z> where
+  X: Debug,
+  Y: Debug,
+  Z: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} * {:?} = {:?}", self.x, self.y, self.z))
+  }
+}
+
+impl<Store, Domain, B, X, Y, Z> Subsumption<Store> for XTimesYEqZ<X, Y, Z> where
+  X: StoreRead<Store, Value=Domain>,
+  Y: StoreRead<Store, Value=Domain>,
+  Z: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let x = bounds(&self.x, store);
+    let y = bounds(&self.y, store);
+    let z = bounds(&self.z, store);
+    match mul_bounds(x, y) {
+      Some(xy) if xy.1 < z.0 || z.1 < xy.0 => False,
+      Some(xy) if is_fixed(xy) && is_fixed(z) => True,
+      _ => Unknown
+    }
+  }
+}
+
+impl<Store, Domain, B, X, Y, Z> Propagator<Store> for XTimesYEqZ<X, Y, Z> where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Z: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    if let Some(xy) = mul_bounds(bounds(&self.x, store), bounds(&self.y, store)) {
+      if !shrink_bounds(&self.z, store, xy) {
+        return false;
+      }
+    }
+    let z = bounds(&self.z, store);
+    // `x = z / y` and `y = z / x`.
+    match factor_bounds(z, bounds(&self.y, store)) {
+      Some(Some(x)) => if !shrink_bounds(&self.x, store, x) { return false; },
+      Some(None) => return false,
+      None => ()
+    }
+    match factor_bounds(z, bounds(&self.x, store)) {
+      Some(Some(y)) => shrink_bounds(&self.y, store, y),
+      Some(None) => false,
+      None => true
+    }
+  }
+}
+
+impl<X, Y, Z> PropagatorDependencies<FDEvent> for XTimesYEqZ<X, Y, Z> where
+  X: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>,
+  Z: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.x.dependencies(FDEvent::Bound);
+    deps.append(&mut self.y.dependencies(FDEvent::Bound));
+    deps.append(&mut self.z.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+/// Right operand of a product `x * y` in the `pcp!` macro. A constant gives the view `Times`, a view gives a fresh variable `z` and the constraint `x * y = z` is added to the constraint store.
+pub trait Factor<X, VStore, CStore> {
+  type Product;
+  fn times(self, x: X, vstore: &mut VStore, cstore: &mut CStore) -> Self::Product;
+}
+
+macro_rules! constant_factor
+{
+  ( $( $t: ty ),* ) =>
+  {$(
+    impl<X, VStore, CStore> Factor<X, VStore, CStore> for $t
+    {
+      type Product = Times<X, $t>;
+      fn times(self, x: X, _vstore: &mut VStore, _cstore: &mut CStore) -> Times<X, $t> {
+        Times::new(x, self)
+      }
+    }
+  )*}
+}
+
+constant_factor!(i8, i16, i32, i64, isize);
+
+impl<X, Y, VStore, CStore, Domain, B> Factor<X, VStore, CStore> for Y where
+  X: StoreRead<VStore, Value=Domain>,
+  Y: StoreRead<VStore, Value=Domain> + ViewDependencies<FDEvent>,
+  VStore: Alloc<Domain>,
+  VStore::Location: Clone,
+  CStore: Alloc<XTimesYEqZ<X, Y, VStore::Location>>,
+  Domain: Bounded<Bound=B> + Range<B> + Whole,
+  B: PrimInt
+{
+  type Product = VStore::Location;
+  fn times(self, x: X, vstore: &mut VStore, cstore: &mut CStore) -> VStore::Location {
+    let whole = Domain::whole();
+    let dom = match mul_bounds(bounds(&x, vstore), bounds(&self, vstore)) {
+      Some((lb, ub)) if lb >= whole.lower() && ub <= whole.upper() => Domain::new(lb, ub),
+      _ => whole
+    };
+    let z = vstore.alloc(dom);
+    cstore.alloc(XTimesYEqZ::new(x, self, z.clone()));
+    z
+  }
+}
+
+/// Product `x * y` where `y` is a constant or a view, see `Factor`.
+pub fn x_times_y<X, Y, VStore, CStore>(x: X, y: Y, vstore: &mut VStore, cstore: &mut CStore) -> Y::Product where
+  Y: Factor<X, VStore, CStore>
+{
+  y.times(x, vstore, cstore)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use interval::interval::*;
+  use propagators::test::*;
+  use propagators::arith::test::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use variable::test::*;
+
+  #[test]
+  fn x_times_y_eq_z_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom2_3 = (2,3).to_interval();
+    let dom_3_2 = (-3,2).to_interval();
+    let dom7_8 = (7,8).to_interval();
+    let dom7_9 = (7,9).to_interval();
+    let zero = (0,0).to_interval();
+    let three = (3,3).to_interval();
+    let six = (6,6).to_interval();
+
+    x_times_y_eq_z_test_one(1, dom2_3, dom2_3, dom0_10, Unknown, Unknown, vec![(2, Bound)], true);
+    x_times_y_eq_z_test_one(2, dom2_3, dom0_10, dom7_8, Unknown, Unknown, vec![(1, Bound)], true);
+    x_times_y_eq_z_test_one(3, three, dom0_10, dom7_9, Unknown, Unknown, vec![(1, Assignment)], true);
+    // Only the bounds are considered, `x` in `[-3..-2]` is not deduced.
+    x_times_y_eq_z_test_one(4, dom_3_2, dom_3_2, six, Unknown, Unknown, vec![], true);
+    x_times_y_eq_z_test_one(5, zero, dom0_10, dom0_10, Unknown, True, vec![(2, Assignment)], true);
+    x_times_y_eq_z_test_one(6, three, dom2_3, dom7_8, Unknown, Unknown, vec![], false);
+    x_times_y_eq_z_test_one(7, three, dom2_3, (-1000,1000).to_interval(), Unknown, Unknown, vec![(2, Bound)], true);
+  }
+
+  fn x_times_y_eq_z_test_one(test_num: u32, x: Interval<i32>, y: Interval<i32>, z: Interval<i32>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| XTimesYEqZ::new(vars[0], vars[1], vars[2]),
+      vec![x, y, z], before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn exhaustive_x_times_y_eq_z() {
+    exhaustive_check(|vars| XTimesYEqZ::new(vars[0], vars[1], vars[2]), |x, y, z| x * y == z);
+    boundary_check(|vars| XTimesYEqZ::new(vars[0], vars[1], vars[2]), |x, y, z| x * y == z);
+  }
+
+  #[test]
+  fn x_times_y_test() {
+    type CStore = ::propagation::store::Store<StoreI32, FDEvent, IndexedDeps, RelaxedFifo>;
+    let mut vstore = StoreI32::empty();
+    let mut cstore = CStore::empty();
+    let x = vstore.alloc((2,3).to_interval());
+    let y = vstore.alloc((-1,4).to_interval());
+    // A constant factor is a view.
+    let x3 = x_times_y(x, 3i32, &mut vstore, &mut cstore);
+    assert_eq!(x3.read(&vstore), (6,9).to_interval());
+    // The product of two variables is a fresh variable.
+    let z = x_times_y(x, y, &mut vstore, &mut cstore);
+    assert_eq!(z.read(&vstore), (-3,12).to_interval());
+    assert_eq!(cstore.consistency(&mut vstore), Unknown);
+    z.update(&mut vstore, (10,12).to_interval());
+    assert_eq!(cstore.consistency(&mut vstore), True);
+    assert_eq!(x.read(&vstore), (3,3).to_interval());
+    assert_eq!(y.read(&vstore), (4,4).to_interval());
+  }
+}
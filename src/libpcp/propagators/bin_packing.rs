@@ -0,0 +1,435 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The bin packing constraint assigns items of fixed sizes to bins such that the load of each bin is the sum of the sizes of its items.
+//!
+//! The filtering follows Shaw, "A constraint for bin packing", CP 2004. The items fixed to a bin are required, the others containing the bin in their domain are candidates. The loads are bounded by the required and possible sizes of the bins and by the total size of the items (load maintenance and load and size coherence). A knapsack reasoning on the candidates of each bin, approximated by the `no_sum` procedure, tightens the loads and excludes or commits items.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::bounds::{read_bounds, to_index};
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+/// `bins[i]` is the bin (in `[0..loads.len()-1]`) of the item `i` of size `sizes[i]` and `loads[j]` is the sum of the sizes of the items in the bin `j`.
+#[derive(Clone)]
+pub struct BinPacking<V, B>
+{
+  bins: Vec<V>,
+  sizes: Vec<B>,
+  loads: Vec<V>
+}
+
+impl<V, B> PropagatorKind for BinPacking<V, B> {}
+
+impl<V, B> BinPacking<V, B> where
+  B: PrimInt
+{
+  pub fn new(bins: Vec<V>, sizes: Vec<B>, loads: Vec<V>) -> BinPacking<V, B> {
+    assert_eq!(bins.len(), sizes.len(), "Each item of a bin packing must have a size.");
+    assert!(!loads.is_empty(), "A bin packing must have at least one bin.");
+    assert!(sizes.iter().all(|&s| s >= B::zero()), "The sizes of the items must be positive.");
+    assert!(checked_sum(sizes.iter().cloned()).is_some(), "The total size of the items must not overflow.");
+    BinPacking {
+      bins: bins,
+      sizes: sizes,
+      loads: loads
+    }
+  }
+}
+
+impl<V, B> Debug for BinPacking<V, B> where
+  V: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("bin_packing({:?}, {:?}, {:?})", self.bins, self.sizes, self.loads))
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for BinPacking<V, B> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let bins = read_bounds(&self.bins, store);
+    let loads = read_bounds(&self.loads, store);
+    if bin_packing_consistency(bins.clone(), &self.sizes, loads.clone()).is_none() {
+      False
+    }
+    else if bins.iter().chain(loads.iter()).all(|&(l, u)| l == u) {
+      True
+    }
+    else {
+      Unknown
+    }
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for BinPacking<V, B> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let bins = read_bounds(&self.bins, store);
+    let loads = read_bounds(&self.loads, store);
+    match bin_packing_consistency(bins, &self.sizes, loads) {
+      None => false,
+      Some((bins, loads)) => {
+        let vars = self.bins.iter().zip(bins.into_iter())
+          .chain(self.loads.iter().zip(loads.into_iter()));
+        for (x, (l, u)) in vars {
+          let d = x.read(store);
+          if d.lower() != l || d.upper() != u {
+            if !x.update(store, d.shrink_left(l).shrink_right(u)) {
+              return false;
+            }
+          }
+        }
+        true
+      }
+    }
+  }
+}
+
+impl<V, B> PropagatorDependencies<FDEvent> for BinPacking<V, B> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.bins.iter().chain(self.loads.iter())
+      .flat_map(|x| x.dependencies(FDEvent::Bound))
+      .collect()
+  }
+}
+
+// Sum of `xs`, `None` if it overflows.
+fn checked_sum<B: PrimInt, I: Iterator<Item=B>>(xs: I) -> Option<B> {
+  xs.fold(Some(B::zero()), |a, x| a.and_then(|a| a.checked_add(&x)))
+}
+
+// Shaw's `NoSum(xs, alpha, beta)` where `xs` is sorted in decreasing order. It returns `Some((a, b))` if it proves that no subset of `xs` sums to a value in `[alpha..beta]`, then `a < alpha` and `b > beta` are sums of subsets of `xs`. `None` means that there might be such a subset.
+fn no_sum<B: PrimInt>(xs: &[B], alpha: B, beta: B) -> Option<(B, B)> {
+  let n = xs.len();
+  let total = match checked_sum(xs.iter().cloned()) {
+    Some(total) => total,
+    None => return None
+  };
+  if alpha <= B::zero() || beta >= total {
+    return None;
+  }
+  // `sum_a` is the sum of the `k` largest items, `sum_c` the sum of the `k2` smallest ones and `sum_b` the sum of the `k+1` items following them.
+  let (mut sum_a, mut sum_c) = (B::zero(), B::zero());
+  let (mut k, mut k2) = (0, 0);
+  while sum_c + xs[n-1-k2] < alpha {
+    sum_c = sum_c + xs[n-1-k2];
+    k2 += 1;
+  }
+  let mut sum_b = xs[n-1-k2];
+  while sum_a < alpha && sum_b <= beta {
+    k += 1;
+    sum_a = sum_a + xs[k-1];
+    if sum_a < alpha {
+      k2 -= 1;
+      sum_b = sum_b + xs[n-1-k2];
+      sum_c = sum_c - xs[n-1-k2];
+      while sum_a + sum_c >= alpha {
+        k2 -= 1;
+        sum_c = sum_c - xs[n-1-k2];
+        sum_b = sum_b + xs[n-1-k2] - xs[n-1-k2-k-1];
+      }
+    }
+  }
+  if sum_a < alpha { Some((sum_a + sum_c, sum_b)) }
+  else { None }
+}
+
+// Reduces the domains of the items `bins` and of the `loads` until a fixed point is reached, `None` if the bin packing is not satisfiable. The total size of the items must not overflow, so neither do the sums of the sizes of some items.
+fn bin_packing_consistency<B: PrimInt>(mut bins: Vec<(B, B)>, sizes: &Vec<B>, mut loads: Vec<(B, B)>)
+  -> Option<(Vec<(B, B)>, Vec<(B, B)>)>
+{
+  let m = loads.len();
+  let last = B::from(m - 1).unwrap();
+  let total = checked_sum(sizes.iter().cloned()).expect("The total size of the items must not overflow.");
+  let empty = |d: &(B, B)| d.0 > d.1;
+  loop {
+    let (old_bins, old_loads) = (bins.clone(), loads.clone());
+    for b in &mut bins {
+      *b = (max(b.0, B::zero()), min(b.1, last));
+    }
+    if bins.iter().any(&empty) {
+      return None;
+    }
+    let mut required = vec![B::zero(); m];
+    let mut candidates = vec![vec![]; m];
+    for (i, b) in bins.iter().enumerate() {
+      if b.0 == b.1 {
+        required[to_index(b.0)] = required[to_index(b.0)] + sizes[i];
+      }
+      else {
+        for j in to_index(b.0)..to_index(b.1)+1 {
+          candidates[j].push(i);
+        }
+      }
+    }
+    let possible: Vec<B> = (0..m)
+      .map(|j| candidates[j].iter().fold(required[j], |a, &i| a + sizes[i]))
+      .collect();
+
+    // Load maintenance, and load and size coherence. The coherence is skipped when the sum of the bounds of the other loads overflows.
+    let sum_lb = checked_sum(loads.iter().map(|l| l.0));
+    let sum_ub = checked_sum(loads.iter().map(|l| l.1));
+    for j in 0..m {
+      let l = loads[j];
+      let (mut lb, mut ub) = (max(l.0, required[j]), min(l.1, possible[j]));
+      if let Some(c) = sum_ub.and_then(|s| s.checked_sub(&l.1)).and_then(|others| total.checked_sub(&others)) {
+        lb = max(lb, c);
+      }
+      if let Some(c) = sum_lb.and_then(|s| s.checked_sub(&l.0)).and_then(|others| total.checked_sub(&others)) {
+        ub = min(ub, c);
+      }
+      loads[j] = (lb, ub);
+    }
+    if loads.iter().any(&empty) {
+      return None;
+    }
+
+    // Knapsack reasoning on the candidates of each bin.
+    for j in 0..m {
+      candidates[j].sort_by(|&a, &b| sizes[b].cmp(&sizes[a]));
+      let xs: Vec<B> = candidates[j].iter().map(|&i| sizes[i]).collect();
+      let (alpha, beta) = match (loads[j].0.checked_sub(&required[j]), loads[j].1.checked_sub(&required[j])) {
+        (Some(alpha), Some(beta)) => (alpha, beta),
+        _ => continue
+      };
+      if no_sum(&xs, alpha, beta).is_some() {
+        return None;
+      }
+      if let Some((_, b)) = no_sum(&xs, alpha, alpha) {
+        loads[j].0 = required[j] + b;
+      }
+      if let Some((a, _)) = no_sum(&xs, beta, beta) {
+        loads[j].1 = required[j] + a;
+      }
+      let bin = B::from(j).unwrap();
+      for (k, &i) in candidates[j].iter().enumerate() {
+        let s = sizes[i];
+        let others: Vec<B> = xs[..k].iter().chain(xs[k+1..].iter()).cloned().collect();
+        if required[j] + s > loads[j].1 || no_sum(&others, alpha - s, beta - s).is_some() {
+          if bins[i].0 == bin { bins[i].0 = bin + B::one(); }
+          if bins[i].1 == bin { bins[i].1 = bin - B::one(); }
+        }
+        else if possible[j] - s < loads[j].0 || no_sum(&others, alpha, beta).is_some() {
+          bins[i] = (max(bins[i].0, bin), min(bins[i].1, bin));
+        }
+      }
+      if bins.iter().any(&empty) {
+        return None;
+      }
+    }
+    if bins == old_bins && loads == old_loads {
+      return Some((bins, loads));
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagation::store::Store;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::arith::*;
+  use propagators::test::*;
+  use variable::test::*;
+  use search::space::*;
+  use search::search_tree_visitor::*;
+  use search::propagation::*;
+  use search::engine::branch_and_bound::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use gcollections::VectorStack;
+  use test::Bencher;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  fn subset_sums(xs: &[i32]) -> Vec<i32> {
+    let mut sums = vec![0];
+    for &x in xs {
+      let with_x: Vec<i32> = sums.iter().map(|s| s + x).collect();
+      sums.extend(with_x);
+    }
+    sums
+  }
+
+  #[test]
+  fn exhaustive_no_sum() {
+    // Every multiset of at most 5 sizes in `[1..6]`, in decreasing order.
+    let mut multisets: Vec<Vec<i32>> = vec![vec![]];
+    for _ in 0..5 {
+      let next: Vec<Vec<i32>> = multisets.iter()
+        .flat_map(|xs| (1..7).filter(move |&x| xs.last().map_or(true, |&l| x <= l))
+          .map(move |x| { let mut xs = xs.clone(); xs.push(x); xs }))
+        .collect();
+      multisets.extend(next.into_iter().filter(|xs| xs.len() > 0));
+      multisets.sort();
+      multisets.dedup();
+    }
+    for xs in multisets {
+      let sums = subset_sums(&xs);
+      let total: i32 = xs.iter().sum();
+      for alpha in -1..total+2 {
+        for beta in alpha..total+2 {
+          if let Some((a, b)) = no_sum(&xs, alpha, beta) {
+            assert!(sums.iter().all(|&s| s < alpha || s > beta),
+              "no_sum({:?}, {}, {}) but a subset sums in the interval", xs, alpha, beta);
+            assert!(a < alpha && sums.contains(&a), "no_sum({:?}, {}, {}): {} is not a sum below alpha", xs, alpha, beta, a);
+            assert!(b > beta && sums.contains(&b), "no_sum({:?}, {}, {}): {} is not a sum above beta", xs, alpha, beta, b);
+          }
+        }
+      }
+    }
+  }
+
+  fn bin_packing_test_one(test_num: u32, sizes: Vec<i32>, bins: Vec<Interval<i32>>, loads: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    let n = bins.len();
+    nary_propagator_test(test_num,
+      |vars: Vec<FDVar>| BinPacking::new(vars[..n].to_vec(), sizes, vars[n..].to_vec()),
+      bins.into_iter().chain(loads.into_iter()).collect(), before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn bin_packing_test() {
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let dom0_1 = (0,1).to_interval();
+    let dom0_10 = (0,10).to_interval();
+
+    bin_packing_test_one(1, vec![3, 3], vec![dom0_1, dom0_1], vec![dom0_10, dom0_10], Unknown, Unknown, vec![(2, Bound), (3, Bound)], true);
+    // Load maintenance: the load of a bin is the sum of its fixed items.
+    bin_packing_test_one(2, vec![3, 4], vec![zero, one], vec![dom0_10, dom0_10], Unknown, True, vec![(2, Assignment), (3, Assignment)], true);
+    // The item of size 4 does not fit in the first bin.
+    bin_packing_test_one(3, vec![4, 2], vec![dom0_1, dom0_1], vec![(0,3).to_interval(), dom0_10], Unknown, Unknown,
+      vec![(0, Assignment), (2, Bound), (3, Bound)], true);
+    // Knapsack reasoning: no subset of {3, 3} sums to 4 or 5.
+    bin_packing_test_one(4, vec![3, 3, 5], vec![dom0_1, dom0_1, (1,2).to_interval()], vec![(4,5).to_interval(), dom0_10, dom0_10], False, False, vec![], false);
+    bin_packing_test_one(5, vec![3, 3], vec![zero, zero], vec![(6,6).to_interval(), zero], True, True, vec![], true);
+  }
+
+  #[test]
+  fn exhaustive_bin_packing_soundness() {
+    let sizes = vec![2, 3, 4];
+    let load_doms: Vec<(i32, i32)> = vec![(0,9), (0,4), (2,5), (3,3), (5,7), (6,9), (7,7)];
+    for bins in interval_tuples(&intervals(0, 2), 3) {
+      for loads in interval_tuples(&load_doms, 3) {
+        let doms: Vec<(i32, i32)> = bins.iter().chain(loads.iter()).cloned().collect();
+        // `w[..3]` are the bins of the items and `w[3..]` the loads of the bins.
+        let expected = solutions_hull(&doms, |w| (0..3).all(|j|
+          w[3 + j] == (0..3).filter(|&i| w[i] == j as i32).map(|i| sizes[i]).sum::<i32>()));
+        let result = bin_packing_consistency(bins.clone(), &sizes, loads.clone())
+          .map(|(mut b, l)| { b.extend(l); b });
+        check_soundness(&doms, &result, &expected);
+      }
+    }
+  }
+
+  #[test]
+  fn wide_loads() {
+    let max = i32::max_value();
+    assert_eq!(bin_packing_consistency(vec![(0, 1), (0, 1)], &vec![3, 4], vec![(0, max), (0, max)]),
+      Some((vec![(0, 1), (0, 1)], vec![(0, 7), (0, 7)])));
+    assert_eq!(bin_packing_consistency(vec![(0, 1), (1, 1)], &vec![3, 4], vec![(-max, max), (max - 1, max)]), None);
+  }
+
+  // Minimizes the number of bins of capacity `capacity` needed to pack items of `sizes`.
+  fn bin_packing(sizes: &Vec<i32>, capacity: i32) -> Option<i32> {
+    let n = sizes.len() as i32;
+    let mut space = FDSpace::empty();
+    // The item `i` is in one of the `i+1` first bins, which removes the symmetries between empty bins.
+    let bins: Vec<_> = (0..n).map(|i| space.vstore.alloc((0, i).to_interval())).collect();
+    let loads: Vec<_> = (0..n).map(|_| space.vstore.alloc((0, capacity).to_interval())).collect();
+    let last_bin = space.vstore.alloc((0, n - 1).to_interval());
+    space.cstore.alloc(BinPacking::new(bins.clone(), sizes.clone(), loads));
+    space.cstore.alloc(Max::new(bins, last_bin));
+
+    let mut search: BranchAndBound<_, VectorStack<_>, _, _, _> = BranchAndBound::minimize(last_bin,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, Status::Satisfiable);
+    search.best_value().map(|b| b + 1)
+  }
+
+  // Smallest number of bins by trying every assignment of the items to the bins already opened or to a new one.
+  fn min_bins(sizes: &[i32], capacity: i32, loads: &mut Vec<i32>, best: &mut usize) {
+    if loads.len() >= *best { return; }
+    match sizes.split_first() {
+      None => *best = loads.len(),
+      Some((&s, rest)) => {
+        for j in 0..loads.len() {
+          if loads[j] + s <= capacity {
+            loads[j] += s;
+            min_bins(rest, capacity, loads, best);
+            loads[j] -= s;
+          }
+        }
+        loads.push(s);
+        min_bins(rest, capacity, loads, best);
+        loads.pop();
+      }
+    }
+  }
+
+  fn small_instance() -> Vec<i32> {
+    vec![7, 5, 5, 4, 4, 3, 3, 2, 2, 1]
+  }
+
+  // Items of sizes between 20 and 50 in bins of capacity 100. They sum to 500 but can not be packed in 5 bins, the optimal solution uses 6 bins.
+  fn medium_instance() -> Vec<i32> {
+    vec![49, 46, 44, 41, 38, 37, 35, 33, 30, 28, 27, 25, 24, 22, 21]
+  }
+
+  #[test]
+  fn example_bin_packing() {
+    let sizes = small_instance();
+    let mut best = sizes.len() + 1;
+    min_bins(&sizes, 10, &mut vec![], &mut best);
+    assert_eq!(bin_packing(&sizes, 10), Some(best as i32));
+  }
+
+  #[bench]
+  fn bench_bin_packing(b: &mut Bencher) {
+    b.iter(|| {
+      assert_eq!(bin_packing(&medium_instance(), 100), Some(6))
+    });
+  }
+}
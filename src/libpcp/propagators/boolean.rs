@@ -0,0 +1,540 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Propagators over boolean variables, that is variables with a domain included in `[0..1]` where 1 stands for true.
+//!
+//! The domains of the variables are first reduced to `[0..1]`, the propagators are domain consistent. The negation of a boolean variable is the view `bool_not(x)`, hence `r = not x` is `XEqY::new(r, bool_not(x))`.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::fmt::{Formatter, Debug, Error};
+
+// `None` if the domain contains neither 0 nor 1, otherwise the value of the variable if it is fixed.
+fn bool_value<Domain, B>(dom: &Domain) -> Option<Trilean> where
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  let l = if dom.lower() > B::zero() { dom.lower() } else { B::zero() };
+  let u = if dom.upper() < B::one() { dom.upper() } else { B::one() };
+  if l > u { None }
+  else if l < u { Some(Unknown) }
+  else if l == B::one() { Some(True) }
+  else { Some(False) }
+}
+
+fn read_values<Store, Domain, B, V>(vars: &[V], store: &Store) -> Option<Vec<Trilean>> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  vars.iter().map(|x| bool_value(&x.read(store))).collect()
+}
+
+// Reduces the domains of `vars` to `[0..1]` and returns their values.
+fn restrict_values<Store, Domain, B, V>(vars: &[V], store: &mut Store) -> Option<Vec<Trilean>> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  for x in vars {
+    let dom = x.read(store);
+    if !x.update(store, dom.shrink_left(B::zero()).shrink_right(B::one())) {
+      return None;
+    }
+  }
+  read_values(vars, store)
+}
+
+fn fix<Store, Domain, B, V>(x: &V, store: &mut Store, value: bool) -> bool where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  let value = if value { B::one() } else { B::zero() };
+  let dom = x.read(store);
+  x.update(store, dom.shrink_left(value).shrink_right(value))
+}
+
+fn to_trilean(b: bool) -> Trilean {
+  if b { True } else { False }
+}
+
+/// Propagator of the clause `x_1 \/ ... \/ x_n \/ !y_1 \/ ... \/ !y_m`.
+#[derive(Clone)]
+pub struct Clause<V>
+{
+  pos: Vec<V>,
+  neg: Vec<V>
+}
+
+impl<V> PropagatorKind for Clause<V> {}
+
+impl<V> Clause<V> {
+  /// The clause is satisfied if one of the `pos` variables is 1 or one of the `neg` variables is 0.
+  pub fn new(pos: Vec<V>, neg: Vec<V>) -> Clause<V> {
+    Clause {
+      pos: pos,
+      neg: neg
+    }
+  }
+
+  // Values of the literals, the first `pos.len()` are positive.
+  fn literals(&self, values: Vec<Trilean>) -> Vec<Trilean> {
+    let n = self.pos.len();
+    values.into_iter().enumerate()
+      .map(|(i, v)| if i < n { v } else { !v })
+      .collect()
+  }
+}
+
+impl<V> Debug for Clause<V> where
+  V: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    let mut first = true;
+    for (x, sign) in self.pos.iter().map(|x| (x, "")).chain(self.neg.iter().map(|y| (y, "!"))) {
+      if !first {
+        try!(formatter.write_str(" \\/ "));
+      }
+      first = false;
+      try!(formatter.write_fmt(format_args!("{}{:?}", sign, x)));
+    }
+    Ok(())
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for Clause<V> where
+  V: StoreRead<Store, Value=Domain> + Clone,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let vars: Vec<V> = self.pos.iter().chain(self.neg.iter()).cloned().collect();
+    match read_values(&vars, store) {
+      None => False,
+      Some(values) => {
+        let literals = self.literals(values);
+        if literals.iter().any(|&l| l == True) { True }
+        else if literals.iter().all(|&l| l == False) { False }
+        else { Unknown }
+      }
+    }
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for Clause<V> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain> + Clone,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let vars: Vec<V> = self.pos.iter().chain(self.neg.iter()).cloned().collect();
+    let literals = match restrict_values(&vars, store) {
+      None => return false,
+      Some(values) => self.literals(values)
+    };
+    if literals.iter().any(|&l| l == True) {
+      return true;
+    }
+    let mut unknowns = literals.iter().enumerate().filter(|&(_, &l)| l == Unknown);
+    match (unknowns.next(), unknowns.next()) {
+      (None, _) => false,
+      (Some((i, _)), None) => fix(&vars[i], store, i < self.pos.len()),
+      _ => true
+    }
+  }
+}
+
+impl<V> PropagatorDependencies<FDEvent> for Clause<V> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.pos.iter().chain(self.neg.iter())
+      .flat_map(|x| x.dependencies(FDEvent::Assignment))
+      .collect()
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Connective {
+  And,
+  Or,
+  Xor
+}
+
+/// Propagator of `r = x_1 op ... op x_n` where `op` is a boolean connective.
+#[derive(Clone)]
+pub struct BoolConnective<V>
+{
+  op: Connective,
+  xs: Vec<V>,
+  r: V
+}
+
+impl<V> PropagatorKind for BoolConnective<V> {}
+
+impl<V> BoolConnective<V> {
+  pub fn new(op: Connective, xs: Vec<V>, r: V) -> BoolConnective<V> {
+    BoolConnective {
+      op: op,
+      xs: xs,
+      r: r
+    }
+  }
+
+  pub fn and(xs: Vec<V>, r: V) -> BoolConnective<V> {
+    BoolConnective::new(Connective::And, xs, r)
+  }
+
+  pub fn or(xs: Vec<V>, r: V) -> BoolConnective<V> {
+    BoolConnective::new(Connective::Or, xs, r)
+  }
+
+  pub fn xor(xs: Vec<V>, r: V) -> BoolConnective<V> {
+    BoolConnective::new(Connective::Xor, xs, r)
+  }
+
+  // Value of `x_1 op ... op x_n`, `Unknown` if it depends on unfixed variables.
+  fn eval(&self, values: &[Trilean]) -> Trilean {
+    match self.op {
+      Connective::And =>
+        if values.iter().any(|&v| v == False) { False }
+        else if values.iter().all(|&v| v == True) { True }
+        else { Unknown },
+      Connective::Or =>
+        if values.iter().any(|&v| v == True) { True }
+        else if values.iter().all(|&v| v == False) { False }
+        else { Unknown },
+      Connective::Xor =>
+        if values.iter().any(|&v| v == Unknown) { Unknown }
+        else { to_trilean(values.iter().filter(|&&v| v == True).count() % 2 == 1) }
+    }
+  }
+}
+
+impl<V> Debug for BoolConnective<V> where
+  V: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    let op = match self.op {
+      Connective::And => "and",
+      Connective::Or => "or",
+      Connective::Xor => "xor"
+    };
+    formatter.write_fmt(format_args!("{:?} = {}{:?}", self.r, op, self.xs))
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for BoolConnective<V> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    match (read_values(&self.xs, store), bool_value(&self.r.read(store))) {
+      (Some(values), Some(r)) => {
+        let f = self.eval(&values);
+        if f == Unknown || r == Unknown { Unknown }
+        else { to_trilean(f == r) }
+      }
+      _ => False
+    }
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for BoolConnective<V> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain> + Clone,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let r = match restrict_values(&[self.r.clone()], store) {
+      None => return false,
+      Some(r) => r[0]
+    };
+    let values = match restrict_values(&self.xs, store) {
+      None => return false,
+      Some(values) => values
+    };
+    let f = self.eval(&values);
+    if f != Unknown {
+      return fix(&self.r, store, f == True);
+    }
+    if r == Unknown {
+      return true;
+    }
+    let unknowns: Vec<usize> = (0..values.len()).filter(|&i| values[i] == Unknown).collect();
+    match self.op {
+      // `r` is not the absorbing element: all the variables are fixed to it.
+      Connective::And | Connective::Or if (r == True) == (self.op == Connective::And) =>
+        unknowns.into_iter().all(|i| fix(&self.xs[i], store, r == True)),
+      // `r` is the absorbing element: the last unfixed variable must be equal to it.
+      Connective::And | Connective::Or =>
+        if unknowns.len() == 1 { fix(&self.xs[unknowns[0]], store, r == True) }
+        else { true },
+      Connective::Xor =>
+        if unknowns.len() == 1 {
+          let ones = values.iter().filter(|&&v| v == True).count();
+          fix(&self.xs[unknowns[0]], store, (ones % 2 == 1) != (r == True))
+        }
+        else { true }
+    }
+  }
+}
+
+impl<V> PropagatorDependencies<FDEvent> for BoolConnective<V> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps: Vec<_> = self.xs.iter()
+      .flat_map(|x| x.dependencies(FDEvent::Assignment))
+      .collect();
+    deps.append(&mut self.r.dependencies(FDEvent::Assignment));
+    deps
+  }
+}
+
+/// Propagator of `x_1 + ... + x_n = y` where the `x_i` are boolean variables.
+#[derive(Clone)]
+pub struct BoolSum<V, Y>
+{
+  xs: Vec<V>,
+  y: Y
+}
+
+impl<V, Y> PropagatorKind for BoolSum<V, Y> {}
+
+impl<V, Y> BoolSum<V, Y> {
+  pub fn new(xs: Vec<V>, y: Y) -> BoolSum<V, Y> {
+    BoolSum {
+      xs: xs,
+      y: y
+    }
+  }
+}
+
+impl<V, Y> Debug for BoolSum<V, Y> where
+  V: Debug,
+  Y: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("sum{:?} = {:?}", self.xs, self.y))
+  }
+}
+
+// Number of variables fixed to 1 and of unfixed variables.
+fn count(values: &[Trilean]) -> (usize, usize) {
+  (values.iter().filter(|&&v| v == True).count(),
+   values.iter().filter(|&&v| v == Unknown).count())
+}
+
+impl<Store, Domain, DomY, B, BY, V, Y> Subsumption<Store> for BoolSum<V, Y> where
+  V: StoreRead<Store, Value=Domain>,
+  Y: StoreRead<Store, Value=DomY>,
+  Domain: Bounded<Bound=B>,
+  DomY: Bounded<Bound=BY>,
+  B: PrimInt,
+  BY: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    match read_values(&self.xs, store) {
+      None => False,
+      Some(values) => {
+        let (ones, unknowns) = count(&values);
+        let min = BY::from(ones).unwrap();
+        let max = BY::from(ones + unknowns).unwrap();
+        let y = self.y.read(store);
+        if y.upper() < min || y.lower() > max { False }
+        else if unknowns == 0 && y.lower() == min && y.upper() == min { True }
+        else { Unknown }
+      }
+    }
+  }
+}
+
+impl<Store, Domain, DomY, B, BY, V, Y> Propagator<Store> for BoolSum<V, Y> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=DomY> + StoreMonotonicUpdate<Store, DomY>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  DomY: Bounded<Bound=BY> + ShrinkLeft<BY> + ShrinkRight<BY>,
+  B: PrimInt,
+  BY: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let values = match restrict_values(&self.xs, store) {
+      None => return false,
+      Some(values) => values
+    };
+    let (ones, unknowns) = count(&values);
+    let min = BY::from(ones).unwrap();
+    let max = BY::from(ones + unknowns).unwrap();
+    let y = self.y.read(store);
+    if !self.y.update(store, y.shrink_left(min).shrink_right(max)) {
+      return false;
+    }
+    let y = self.y.read(store);
+    let value =
+      if unknowns == 0 { return true; }
+      else if y.lower() == max { true }
+      else if y.upper() == min { false }
+      else { return true; };
+    (0..values.len())
+      .filter(|&i| values[i] == Unknown)
+      .all(|i| fix(&self.xs[i], store, value))
+  }
+}
+
+impl<V, Y> PropagatorDependencies<FDEvent> for BoolSum<V, Y> where
+  V: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps: Vec<_> = self.xs.iter()
+      .flat_map(|x| x.dependencies(FDEvent::Assignment))
+      .collect();
+    deps.append(&mut self.y.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagators::cmp::*;
+  use propagators::test::*;
+  use term::*;
+  use interval::interval::*;
+
+  #[test]
+  fn clause_test() {
+    let b = (0,1).to_interval();
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+
+    clause_test_one(1, vec![b, b, b], Unknown, Unknown, vec![], true);
+    clause_test_one(2, vec![zero, b, b], Unknown, Unknown, vec![], true);
+    clause_test_one(3, vec![zero, b, one], Unknown, True, vec![(1, Assignment)], true);
+    clause_test_one(4, vec![zero, zero, b], Unknown, True, vec![(2, Assignment)], true);
+    clause_test_one(5, vec![b, one, b], True, True, vec![], true);
+    clause_test_one(6, vec![b, b, zero], True, True, vec![], true);
+    clause_test_one(7, vec![zero, zero, one], False, False, vec![], false);
+    clause_test_one(8, vec![(0,5).to_interval(), zero, one], Unknown, True, vec![(0, Assignment)], true);
+  }
+
+  // Clause `x \/ y \/ !z`.
+  fn clause_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars: Vec<FDVar>| Clause::new(vec![vars[0].clone(), vars[1].clone()], vec![vars[2].clone()]),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn connective_test() {
+    let b = (0,1).to_interval();
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+
+    // r = and(x, y)
+    connective_test_one(1, Connective::And, vec![b, b, b], Unknown, Unknown, vec![], true);
+    connective_test_one(2, Connective::And, vec![zero, b, b], Unknown, True, vec![(2, Assignment)], true);
+    connective_test_one(3, Connective::And, vec![one, one, b], Unknown, True, vec![(2, Assignment)], true);
+    connective_test_one(4, Connective::And, vec![b, b, one], Unknown, True, vec![(0, Assignment), (1, Assignment)], true);
+    connective_test_one(5, Connective::And, vec![one, b, zero], Unknown, True, vec![(1, Assignment)], true);
+    connective_test_one(6, Connective::And, vec![b, b, zero], Unknown, Unknown, vec![], true);
+    connective_test_one(7, Connective::And, vec![one, one, zero], False, False, vec![], false);
+
+    // r = or(x, y)
+    connective_test_one(8, Connective::Or, vec![one, b, b], Unknown, True, vec![(2, Assignment)], true);
+    connective_test_one(9, Connective::Or, vec![zero, zero, b], Unknown, True, vec![(2, Assignment)], true);
+    connective_test_one(10, Connective::Or, vec![b, b, zero], Unknown, True, vec![(0, Assignment), (1, Assignment)], true);
+    connective_test_one(11, Connective::Or, vec![zero, b, one], Unknown, True, vec![(1, Assignment)], true);
+    connective_test_one(12, Connective::Or, vec![b, b, one], Unknown, Unknown, vec![], true);
+    connective_test_one(13, Connective::Or, vec![zero, one, zero], False, False, vec![], false);
+
+    // r = xor(x, y)
+    connective_test_one(14, Connective::Xor, vec![one, b, b], Unknown, Unknown, vec![], true);
+    connective_test_one(15, Connective::Xor, vec![one, zero, b], Unknown, True, vec![(2, Assignment)], true);
+    connective_test_one(16, Connective::Xor, vec![one, b, one], Unknown, True, vec![(1, Assignment)], true);
+    connective_test_one(17, Connective::Xor, vec![b, one, zero], Unknown, True, vec![(0, Assignment)], true);
+    connective_test_one(18, Connective::Xor, vec![one, one, one], False, False, vec![], false);
+    connective_test_one(19, Connective::Xor, vec![one, one, zero], True, True, vec![], true);
+  }
+
+  fn connective_test_one(test_num: u32, op: Connective, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars: Vec<FDVar>| BoolConnective::new(op, vec![vars[0].clone(), vars[1].clone()], vars[2].clone()),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn bool_sum_test() {
+    let b = (0,1).to_interval();
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+
+    bool_sum_test_one(1, vec![b, b, b, (0,3).to_interval()], Unknown, Unknown, vec![], true);
+    bool_sum_test_one(2, vec![b, b, b, (0,10).to_interval()], Unknown, Unknown, vec![(3, Bound)], true);
+    bool_sum_test_one(3, vec![one, b, b, (0,1).to_interval()], Unknown, True,
+      vec![(1, Assignment), (2, Assignment), (3, Assignment)], true);
+    bool_sum_test_one(4, vec![b, b, one, (3,5).to_interval()], Unknown, True,
+      vec![(0, Assignment), (1, Assignment), (3, Assignment)], true);
+    bool_sum_test_one(5, vec![one, zero, b, (2,2).to_interval()], Unknown, True, vec![(2, Assignment)], true);
+    bool_sum_test_one(6, vec![one, one, zero, (2,2).to_interval()], True, True, vec![], true);
+    bool_sum_test_one(7, vec![one, one, b, (0,1).to_interval()], False, False, vec![], false);
+  }
+
+  fn bool_sum_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars: Vec<FDVar>| BoolSum::new(vec![vars[0].clone(), vars[1].clone(), vars[2].clone()], vars[3].clone()),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn bool_not_test() {
+    let b = (0,1).to_interval();
+    let one = (1,1).to_interval();
+
+    // r = not x
+    bool_not_test_one(1, b, b, Unknown, Unknown, vec![], true);
+    bool_not_test_one(2, one, b, Unknown, True, vec![(1, Assignment)], true);
+    bool_not_test_one(3, b, one, Unknown, True, vec![(0, Assignment)], true);
+    bool_not_test_one(4, one, one, False, False, vec![], false);
+  }
+
+  fn bool_not_test_one(test_num: u32, x: Interval<i32>, r: Interval<i32>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    binary_propagator_test(test_num, |x, r| XEqY::new(r, bool_not(x)), x, r, before, after, delta_expected, propagate_success);
+  }
+}
@@ -0,0 +1,63 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers shared by the propagators filtering the bounds of their variables, a domain is represented by the pair `(lower, upper)`.
+
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+
+pub fn bounds<Store, Domain, B, V>(v: &V, store: &Store) -> (B, B) where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>
+{
+  let d = v.read(store);
+  (d.lower(), d.upper())
+}
+
+pub fn read_bounds<Store, Domain, B, V>(vars: &Vec<V>, store: &Store) -> Vec<(B, B)> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>
+{
+  vars.iter().map(|x| bounds(x, store)).collect()
+}
+
+/// Shrinks the domain of `v` to `[lb..ub]`, fails if the result is empty.
+pub fn shrink_bounds<Store, Domain, B, V>(v: &V, store: &mut Store, bounds: (B, B)) -> bool where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  let d = v.read(store);
+  if bounds.0 > d.lower() || bounds.1 < d.upper() {
+    v.update(store, d.shrink_left(bounds.0).shrink_right(bounds.1))
+  }
+  else {
+    true
+  }
+}
+
+/// Shrinks the domain of each variable of `vars` to the corresponding bounds, fails as soon as a domain becomes empty.
+pub fn write_bounds<Store, Domain, B, V>(vars: &Vec<V>, store: &mut Store, bounds: Vec<(B, B)>) -> bool where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  vars.iter().zip(bounds.into_iter()).all(|(x, b)| shrink_bounds(x, store, b))
+}
+
+/// Index of a non-negative bound.
+pub fn to_index<B: PrimInt>(b: B) -> usize {
+  b.to_usize().unwrap()
+}
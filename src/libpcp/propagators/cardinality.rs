@@ -0,0 +1,469 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Constraints over the number of occurrences of values in a sequence of variables.
+//!
+//! `GlobalCardinality` bounds the number of occurrences of several values, `Among` counts the variables taking a value in a set and `Count` the variables equal to a value.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::bounds::{read_bounds, write_bounds};
+use propagators::graph::flow_consistency;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::fmt::{Formatter, Debug, Error};
+
+/// The number of variables equal to `values[i]` is in `[low[i]..up[i]]`, the other values are not restricted.
+///
+/// The filtering is based on a flow between the variables and the values (Régin, 1996).
+#[derive(Clone)]
+pub struct GlobalCardinality<V, B>
+{
+  vars: Vec<V>,
+  values: Vec<B>,
+  low: Vec<usize>,
+  up: Vec<usize>
+}
+
+impl<V, B> PropagatorKind for GlobalCardinality<V, B> {}
+
+impl<V, B> GlobalCardinality<V, B> {
+  pub fn new(vars: Vec<V>, values: Vec<B>, low: Vec<usize>, up: Vec<usize>) -> GlobalCardinality<V, B> {
+    assert!(values.len() == low.len() && values.len() == up.len(),
+      "Cardinality bounds must be given for each value of a global cardinality constraint.");
+    assert!(low.iter().zip(up.iter()).all(|(l, u)| l <= u),
+      "The lower cardinality of a value must not exceed its upper cardinality.");
+    GlobalCardinality {
+      vars: vars,
+      values: values,
+      low: low,
+      up: up
+    }
+  }
+}
+
+impl<V, B> Debug for GlobalCardinality<V, B> where
+  V: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    try!(formatter.write_fmt(format_args!("gcc({:?}, ", self.vars)));
+    for i in 0..self.values.len() {
+      try!(formatter.write_fmt(format_args!("{:?} in [{}..{}] ", self.values[i], self.low[i], self.up[i])));
+    }
+    formatter.write_str(")")
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for GlobalCardinality<V, B> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let doms = read_bounds(&self.vars, store);
+    let assigned = doms.iter().all(|&(l, u)| l == u);
+    for i in 0..self.values.len() {
+      let v = self.values[i];
+      let fixed = doms.iter().filter(|&&(l, u)| l == v && u == v).count();
+      let possible = doms.iter().filter(|&&(l, u)| l <= v && v <= u).count();
+      if fixed > self.up[i] || possible < self.low[i] {
+        return False;
+      }
+    }
+    if assigned { True }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for GlobalCardinality<V, B> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let doms = read_bounds(&self.vars, store);
+    let cards: Vec<(B, usize, usize)> = (0..self.values.len())
+      .map(|i| (self.values[i], self.low[i], self.up[i]))
+      .collect();
+    match gcc_consistency(doms, &cards) {
+      None => false,
+      Some(doms) => write_bounds(&self.vars, store, doms)
+    }
+  }
+}
+
+impl<V, B> PropagatorDependencies<FDEvent> for GlobalCardinality<V, B> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.vars.iter().flat_map(|x| x.dependencies(FDEvent::Bound)).collect()
+  }
+}
+
+// Domain consistency algorithm of Régin, "Generalized arc consistency for global cardinality constraint" (AAAI 1996) over the hull of the domains, see `flow_consistency`.
+fn gcc_consistency<B: PrimInt>(ivs: Vec<(B, B)>, cards: &[(B, usize, usize)]) -> Option<Vec<(B, B)>> {
+  let n = ivs.len();
+  flow_consistency(ivs, cards, n)
+}
+
+/// The number of variables taking a value in `set` is equal to `n`.
+///
+/// A variable is counted when its domain is included in `set` and it can be counted while its domain intersects `set`.
+#[derive(Clone)]
+pub struct Among<V, Y, B>
+{
+  vars: Vec<V>,
+  set: Vec<B>,
+  n: Y
+}
+
+impl<V, Y, B> PropagatorKind for Among<V, Y, B> {}
+
+impl<V, Y, B> Among<V, Y, B> where
+  B: Ord
+{
+  pub fn new(vars: Vec<V>, mut set: Vec<B>, n: Y) -> Among<V, Y, B> {
+    set.sort();
+    set.dedup();
+    Among {
+      vars: vars,
+      set: set,
+      n: n
+    }
+  }
+}
+
+impl<V, Y, B> Debug for Among<V, Y, B> where
+  V: Debug,
+  Y: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("among({:?}, {:?}) = {:?}", self.vars, self.set, self.n))
+  }
+}
+
+impl<V, Y, B> Among<V, Y, B> where
+  B: PrimInt
+{
+  fn contains(&self, b: B) -> bool {
+    self.set.binary_search(&b).is_ok()
+  }
+
+  // Number of values of `set` in `[l..u]`.
+  fn count_in(&self, l: B, u: B) -> usize {
+    let first = match self.set.binary_search(&l) { Ok(i) | Err(i) => i };
+    let last = match self.set.binary_search(&u) { Ok(i) => i + 1, Err(i) => i };
+    if first < last { last - first } else { 0 }
+  }
+
+  // `(included, intersects)` of the domain `[l..u]` with `set`, a domain wider than `B` cannot be included.
+  fn status(&self, l: B, u: B) -> (bool, bool) {
+    let count = self.count_in(l, u);
+    let width = u.checked_sub(&l).and_then(|w| w.to_usize()).and_then(|w| w.checked_add(1));
+    (width == Some(count), count > 0)
+  }
+
+  fn statuses<Store, Domain>(&self, store: &Store) -> Vec<(bool, bool)> where
+    V: StoreRead<Store, Value=Domain>,
+    Domain: Bounded<Bound=B>
+  {
+    self.vars.iter()
+      .map(|x| { let d = x.read(store); self.status(d.lower(), d.upper()) })
+      .collect()
+  }
+
+  // Bounds of `[l..u]` once the values of `set` are removed from its extremities.
+  fn exclude(&self, mut l: B, mut u: B) -> (B, B) {
+    while l <= u && self.contains(l) {
+      if l == u { return (B::one(), B::zero()); }
+      l = l + B::one();
+    }
+    while l < u && self.contains(u) { u = u - B::one(); }
+    (l, u)
+  }
+
+  // Hull of the values of `set` in `[l..u]`.
+  fn include(&self, l: B, u: B) -> (B, B) {
+    let first = match self.set.binary_search(&l) { Ok(i) | Err(i) => i };
+    let last = match self.set.binary_search(&u) { Ok(i) => i + 1, Err(i) => i };
+    if first < last { (self.set[first], self.set[last - 1]) }
+    else { (B::one(), B::zero()) }
+  }
+}
+
+// Number of variables included in the set and intersecting it.
+fn counts(statuses: &[(bool, bool)]) -> (usize, usize) {
+  (statuses.iter().filter(|s| s.0).count(),
+   statuses.iter().filter(|s| s.1).count())
+}
+
+impl<Store, Domain, DomY, B, BY, V, Y> Subsumption<Store> for Among<V, Y, B> where
+  V: StoreRead<Store, Value=Domain>,
+  Y: StoreRead<Store, Value=DomY>,
+  Domain: Bounded<Bound=B>,
+  DomY: Bounded<Bound=BY>,
+  B: PrimInt,
+  BY: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let (min, max) = counts(&self.statuses(store));
+    let (min, max) = (BY::from(min).unwrap(), BY::from(max).unwrap());
+    let n = self.n.read(store);
+    if n.upper() < min || n.lower() > max { False }
+    else if min == max && n.lower() == min && n.upper() == min { True }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, DomY, B, BY, V, Y> Propagator<Store> for Among<V, Y, B> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=DomY> + StoreMonotonicUpdate<Store, DomY>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  DomY: Bounded<Bound=BY> + ShrinkLeft<BY> + ShrinkRight<BY>,
+  B: PrimInt,
+  BY: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let statuses = self.statuses(store);
+    let (min, max) = counts(&statuses);
+    let (min, max) = (BY::from(min).unwrap(), BY::from(max).unwrap());
+    let n = self.n.read(store);
+    if !self.n.update(store, n.shrink_left(min).shrink_right(max)) {
+      return false;
+    }
+    let n = self.n.read(store);
+    if min == max { return true; }
+    let (exclude, include) = (n.upper() == min, n.lower() == max);
+    for (x, &(included, intersects)) in self.vars.iter().zip(statuses.iter()) {
+      if included || !intersects { continue; }
+      let d = x.read(store);
+      let (l, u) =
+        if exclude { self.exclude(d.lower(), d.upper()) }
+        else if include { self.include(d.lower(), d.upper()) }
+        else { continue; };
+      if !x.update(store, d.shrink_left(l).shrink_right(u)) {
+        return false;
+      }
+    }
+    true
+  }
+}
+
+impl<V, Y, B> PropagatorDependencies<FDEvent> for Among<V, Y, B> where
+  V: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps: Vec<_> = self.vars.iter()
+      .flat_map(|x| x.dependencies(FDEvent::Bound))
+      .collect();
+    deps.append(&mut self.n.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+/// The number of variables equal to `value` is equal to `n`, this is `Among` with a singleton set.
+#[derive(Clone)]
+pub struct Count<V, Y, B>
+{
+  among: Among<V, Y, B>
+}
+
+impl<V, Y, B> PropagatorKind for Count<V, Y, B> {}
+
+impl<V, Y, B> Count<V, Y, B> where
+  B: Ord
+{
+  pub fn new(vars: Vec<V>, value: B, n: Y) -> Count<V, Y, B> {
+    Count { among: Among::new(vars, vec![value], n) }
+  }
+}
+
+impl<V, Y, B> Debug for Count<V, Y, B> where
+  V: Debug,
+  Y: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("count({:?}, {:?}) = {:?}",
+      self.among.vars, self.among.set[0], self.among.n))
+  }
+}
+
+impl<Store, V, Y, B> Subsumption<Store> for Count<V, Y, B> where
+  Among<V, Y, B>: Subsumption<Store>
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    self.among.is_subsumed(store)
+  }
+}
+
+impl<Store, V, Y, B> Propagator<Store> for Count<V, Y, B> where
+  Among<V, Y, B>: Propagator<Store>
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    self.among.propagate(store)
+  }
+}
+
+impl<V, Y, B> PropagatorDependencies<FDEvent> for Count<V, Y, B> where
+  Among<V, Y, B>: PropagatorDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.among.dependencies()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagators::test::*;
+  use interval::interval::*;
+
+  // Every value in [0..2] occurs between `low` and `up` times.
+  fn gcc_test_one(test_num: u32, low: Vec<usize>, up: Vec<usize>, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| GlobalCardinality::new(vars, vec![0, 1, 2], low, up),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn gcc_test() {
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+    let dom0_1 = (0,1).to_interval();
+    let dom0_2 = (0,2).to_interval();
+    let dom1_2 = (1,2).to_interval();
+
+    gcc_test_one(1, vec![0,0,0], vec![1,1,1], vec![zero, one, two], True, True, vec![], true);
+    gcc_test_one(2, vec![0,0,0], vec![1,1,1], vec![zero, zero, dom0_2], False, False, vec![], false);
+    // The value 2 must occur twice.
+    gcc_test_one(3, vec![0,0,2], vec![3,3,2], vec![dom0_2, dom0_1, dom0_1], False, False, vec![], false);
+    gcc_test_one(4, vec![0,0,2], vec![3,3,2], vec![dom0_2, dom1_2, dom0_1], Unknown, Unknown,
+      vec![(0, Assignment), (1, Assignment)], true);
+    // The value 2 can not occur more than twice.
+    gcc_test_one(5, vec![0,0,2], vec![3,3,2], vec![two, two, dom0_2, dom1_2], Unknown, Unknown,
+      vec![(2, Bound), (3, Assignment)], true);
+    // At most one 0 and one 1: the third variable must be 2.
+    gcc_test_one(6, vec![0,0,0], vec![1,1,3], vec![dom0_1, dom0_1, dom0_2], Unknown, Unknown, vec![(2, Assignment)], true);
+    gcc_test_one(7, vec![1,0,0], vec![1,3,3], vec![zero, dom0_2, dom0_2], Unknown, Unknown, vec![(1, Bound), (2, Bound)], true);
+  }
+
+  #[test]
+  fn exhaustive_gcc_consistency() {
+    let cards_list = vec![
+      vec![(0, 1, 1), (1, 0, 1), (2, 1, 2)],
+      vec![(1, 2, 2), (3, 0, 0)],
+      vec![(0, 0, 1), (1, 0, 1), (2, 0, 1), (3, 0, 1)],
+      vec![(2, 1, 4), (4, 0, 1)]
+    ];
+    for cards in &cards_list {
+      for ivs in interval_tuples(&intervals(0, 3), 4) {
+        let expected = solutions_hull(&ivs, |w| cards.iter().all(|&(v, l, u)| {
+          let c = w.iter().filter(|&&x| x == v).count();
+          l <= c && c <= u
+        }));
+        assert_eq!(gcc_consistency(ivs.clone(), cards), expected, "gcc consistency of {:?} with {:?}", ivs, cards);
+      }
+    }
+  }
+
+  #[test]
+  fn wide_domains_gcc_consistency() {
+    let (min, max) = (i32::min_value() + 1, i32::max_value());
+    assert_eq!(gcc_consistency(vec![(min, max)], &[(0, 1, 1)]), Some(vec![(0, 0)]));
+    assert_eq!(gcc_consistency(vec![(min, max), (0, 0)], &[(0, 0, 1), (max, 1, 1)]), Some(vec![(max, max), (0, 0)]));
+    assert_eq!(gcc_consistency(vec![(min, max), (min, 0)], &[(min, 0, 0), (max, 0, 0)]), Some(vec![(min + 1, max - 1), (min + 1, 0)]));
+    assert_eq!(gcc_consistency(vec![(min, max), (min, max)], &[(5, 3, 3)]), None);
+  }
+
+  // Among([x,y,z], {1,2,4}, n)
+  fn among_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars: Vec<FDVar>| Among::new(vec![vars[0].clone(), vars[1].clone(), vars[2].clone()], vec![4, 2, 1], vars[3].clone()),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn among_test() {
+    let one = (1,1).to_interval();
+    let three = (3,3).to_interval();
+    let dom0_5 = (0,5).to_interval();
+    let dom1_2 = (1,2).to_interval();
+    let dom2_3 = (2,3).to_interval();
+
+    among_test_one(1, vec![one, dom1_2, three, (0,3).to_interval()], Unknown, True, vec![(3, Assignment)], true);
+    among_test_one(2, vec![one, dom2_3, dom0_5, (0,10).to_interval()], Unknown, Unknown, vec![(3, Bound)], true);
+    // No other variable than `x` belongs to the set: `y = 3` and the bounds of `z` are not in the set.
+    among_test_one(3, vec![one, dom2_3, dom0_5, one], Unknown, Unknown, vec![(1, Assignment)], true);
+    // All the variables belong to the set.
+    among_test_one(4, vec![one, dom2_3, (3,5).to_interval(), three], Unknown, True,
+      vec![(1, Assignment), (2, Assignment)], true);
+    among_test_one(5, vec![one, three, three, (2,3).to_interval()], False, False, vec![], false);
+    among_test_one(6, vec![three, (5,6).to_interval(), (0,0).to_interval(), (0,0).to_interval()], True, True, vec![], true);
+  }
+
+  #[test]
+  fn extreme_among() {
+    use std::i32;
+    let (min, max) = (i32::MIN, i32::MAX);
+    let among: Among<FDVar, i32, i32> = Among::new(vec![], vec![min, 0, max], 0);
+    assert_eq!(among.status(min, max), (false, true));
+    assert_eq!(among.status(max, max), (true, true));
+    assert_eq!(among.exclude(min, max), (min + 1, max - 1));
+    assert_eq!(among.exclude(max - 1, max), (max - 1, max - 1));
+    let (l, u) = among.exclude(max, max);
+    assert!(l > u);
+  }
+
+  #[test]
+  fn count_test() {
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let dom0_1 = (0,1).to_interval();
+    let dom1_3 = (1,3).to_interval();
+
+    count_test_one(1, vec![one, dom0_1, dom1_3, (0,3).to_interval()], Unknown, Unknown, vec![(3, Bound)], true);
+    count_test_one(2, vec![one, dom0_1, dom1_3, one], Unknown, True, vec![(1, Assignment), (2, Bound)], true);
+    count_test_one(3, vec![one, dom0_1, dom1_3, (3,3).to_interval()], Unknown, True, vec![(1, Assignment), (2, Assignment)], true);
+    count_test_one(4, vec![zero, zero, zero, (0,0).to_interval()], True, True, vec![], true);
+    count_test_one(5, vec![one, one, dom0_1, zero], False, False, vec![], false);
+  }
+
+  // count([x,y,z], 1) = n
+  fn count_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars: Vec<FDVar>| Count::new(vec![vars[0].clone(), vars[1].clone(), vars[2].clone()], 1, vars[3].clone()),
+      doms, before, after, delta_expected, propagate_success);
+  }
+}
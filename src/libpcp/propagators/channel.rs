@@ -0,0 +1,388 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Channeling constraints linking two representations of the same assignment.
+//!
+//! `Inverse` links a permutation to its inverse: `xs[i] = j <-> ys[j] = i`. `BoolChannel` links an integer variable to its 0/1 encoding: `x = j <-> bs[j] = 1`. Each equivalence is propagated in both directions until a fixed point is reached.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::bounds::{read_bounds, write_bounds, to_index};
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+fn intersect<B: PrimInt>(a: (B, B), b: (B, B)) -> (B, B) {
+  (max(a.0, b.0), min(a.1, b.1))
+}
+
+fn is_empty<B: PrimInt>(a: (B, B)) -> bool {
+  a.0 > a.1
+}
+
+fn contains<B: PrimInt>(a: (B, B), v: B) -> bool {
+  a.0 <= v && v <= a.1
+}
+
+// Reduces `xs` such that `xs[i] = j` only if `i` is in `ys[j]`, and fixes `xs[i]` to `j` when `ys[j]` is fixed to `i`. Returns `false` if a domain becomes empty.
+fn inverse_step<B: PrimInt>(xs: &mut Vec<(B, B)>, ys: &Vec<(B, B)>) -> bool {
+  let last = B::from(ys.len() - 1).unwrap();
+  for (i, x) in xs.iter_mut().enumerate() {
+    let i = B::from(i).unwrap();
+    *x = intersect(*x, (B::zero(), last));
+    while !is_empty(*x) && !contains(ys[to_index(x.0)], i) {
+      x.0 = x.0 + B::one();
+    }
+    while !is_empty(*x) && !contains(ys[to_index(x.1)], i) {
+      x.1 = x.1 - B::one();
+    }
+    if is_empty(*x) {
+      return false;
+    }
+  }
+  for (j, y) in ys.iter().enumerate() {
+    if y.0 == y.1 && contains((B::zero(), last), y.0) {
+      let i = to_index(y.0);
+      let j = B::from(j).unwrap();
+      xs[i] = intersect(xs[i], (j, j));
+      if is_empty(xs[i]) {
+        return false;
+      }
+    }
+  }
+  true
+}
+
+// Reduces the bounds of `xs` and `ys` such that `xs[i] = j <-> ys[j] = i`, `None` if a domain becomes empty.
+fn inverse_consistency<B: PrimInt>(mut xs: Vec<(B, B)>, mut ys: Vec<(B, B)>)
+  -> Option<(Vec<(B, B)>, Vec<(B, B)>)>
+{
+  loop {
+    let (old_xs, old_ys) = (xs.clone(), ys.clone());
+    if !inverse_step(&mut xs, &ys) || !inverse_step(&mut ys, &xs) {
+      return None;
+    }
+    if xs == old_xs && ys == old_ys {
+      return Some((xs, ys));
+    }
+  }
+}
+
+/// `xs[i] = j <-> ys[j] = i`, the values of `xs` and `ys` are in `[0..n-1]` where `n` is the length of both vectors.
+#[derive(Clone)]
+pub struct Inverse<V>
+{
+  xs: Vec<V>,
+  ys: Vec<V>
+}
+
+impl<V> PropagatorKind for Inverse<V> {}
+
+impl<V> Inverse<V> {
+  pub fn new(xs: Vec<V>, ys: Vec<V>) -> Inverse<V> {
+    assert_eq!(xs.len(), ys.len(), "A permutation and its inverse must have the same length.");
+    assert!(!xs.is_empty(), "The inverse constraint must have at least one variable.");
+    Inverse { xs: xs, ys: ys }
+  }
+}
+
+impl<V> Debug for Inverse<V> where
+  V: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("inverse({:?}, {:?})", self.xs, self.ys))
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for Inverse<V> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let xs = read_bounds(&self.xs, store);
+    let ys = read_bounds(&self.ys, store);
+    if inverse_consistency(xs.clone(), ys.clone()).is_none() { False }
+    else if xs.iter().chain(ys.iter()).all(|&(l, u)| l == u) { True }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for Inverse<V> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let xs = read_bounds(&self.xs, store);
+    let ys = read_bounds(&self.ys, store);
+    match inverse_consistency(xs, ys) {
+      Some((xs, ys)) => write_bounds(&self.xs, store, xs) && write_bounds(&self.ys, store, ys),
+      None => false
+    }
+  }
+}
+
+impl<V> PropagatorDependencies<FDEvent> for Inverse<V> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.xs.iter().chain(self.ys.iter())
+      .flat_map(|x| x.dependencies(FDEvent::Bound))
+      .collect()
+  }
+}
+
+// Reduces the bounds of `x` and of the 0/1 variables `bs` such that `x = j <-> bs[j] = 1`, `None` if a domain becomes empty.
+fn bool_channel_consistency<B: PrimInt>(mut x: (B, B), mut bs: Vec<(B, B)>)
+  -> Option<((B, B), Vec<(B, B)>)>
+{
+  let (zero, one) = (B::zero(), B::one());
+  let last = B::from(bs.len() - 1).unwrap();
+  loop {
+    let (old_x, old_bs) = (x, bs.clone());
+    for b in &mut bs {
+      *b = intersect(*b, (zero, one));
+    }
+    // `x` can not take a value `j` such that `bs[j] = 0`.
+    x = intersect(x, (zero, last));
+    while !is_empty(x) && bs[to_index(x.0)].1 == zero {
+      x.0 = x.0 + one;
+    }
+    while !is_empty(x) && bs[to_index(x.1)].1 == zero {
+      x.1 = x.1 - one;
+    }
+    if is_empty(x) || bs.iter().any(|&b| is_empty(b)) {
+      return None;
+    }
+    for (j, b) in bs.iter_mut().enumerate() {
+      let j = B::from(j).unwrap();
+      if !contains(x, j) {
+        *b = intersect(*b, (zero, zero));
+      }
+      else if x.0 == x.1 {
+        *b = intersect(*b, (one, one));
+      }
+      if b.0 == one {
+        x = intersect(x, (j, j));
+      }
+      if is_empty(*b) || is_empty(x) {
+        return None;
+      }
+    }
+    if x == old_x && bs == old_bs {
+      return Some((x, bs));
+    }
+  }
+}
+
+/// `x = j <-> bs[j] = 1` where `bs` are 0/1 variables, the values of `x` are in `[0..bs.len()-1]`.
+#[derive(Clone)]
+pub struct BoolChannel<X, V>
+{
+  x: X,
+  bs: Vec<V>
+}
+
+impl<X, V> PropagatorKind for BoolChannel<X, V> {}
+
+impl<X, V> BoolChannel<X, V> {
+  pub fn new(x: X, bs: Vec<V>) -> BoolChannel<X, V> {
+    assert!(!bs.is_empty(), "The boolean channeling constraint must have at least one boolean variable.");
+    BoolChannel { x: x, bs: bs }
+  }
+}
+
+impl<X, V> Debug for BoolChannel<X, V> where
+  X: Debug,
+  V: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("bool_channel({:?}, {:?})", self.x, self.bs))
+  }
+}
+
+impl<Store, Domain, B, X, V> Subsumption<Store> for BoolChannel<X, V> where
+  X: StoreRead<Store, Value=Domain>,
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let x = self.x.read(store);
+    let x = (x.lower(), x.upper());
+    let bs = read_bounds(&self.bs, store);
+    if bool_channel_consistency(x, bs.clone()).is_none() { False }
+    else if x.0 == x.1 && bs.iter().all(|&(l, u)| l == u) { True }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, X, V> Propagator<Store> for BoolChannel<X, V> where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let d = self.x.read(store);
+    let bs = read_bounds(&self.bs, store);
+    match bool_channel_consistency((d.lower(), d.upper()), bs) {
+      Some(((l, u), bs)) =>
+        (d.lower() == l && d.upper() == u || self.x.update(store, d.shrink_left(l).shrink_right(u)))
+        && write_bounds(&self.bs, store, bs),
+      None => false
+    }
+  }
+}
+
+impl<X, V> PropagatorDependencies<FDEvent> for BoolChannel<X, V> where
+  X: ViewDependencies<FDEvent>,
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.x.dependencies(FDEvent::Bound);
+    deps.extend(self.bs.iter().flat_map(|b| b.dependencies(FDEvent::Bound)));
+    deps
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagation::store::Store;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::test::*;
+  use variable::test::*;
+  use search::space::*;
+  use search::solutions::*;
+  use search::propagation::*;
+  use search::engine::one_solution::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use gcollections::VectorStack;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  fn inverse_test_one(test_num: u32, xs: Vec<Interval<i32>>, ys: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    let n = xs.len();
+    nary_propagator_test(test_num, |vars: Vec<FDVar>| Inverse::new(vars[..n].to_vec(), vars[n..].to_vec()),
+      xs.into_iter().chain(ys.into_iter()).collect(), before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn inverse_test() {
+    let dom0_2 = (0,2).to_interval();
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+
+    inverse_test_one(1, vec![dom0_2, dom0_2, dom0_2], vec![dom0_2, dom0_2, dom0_2], Unknown, Unknown, vec![], true);
+    inverse_test_one(2, vec![one, dom0_2, dom0_2], vec![dom0_2, dom0_2, dom0_2], Unknown, Unknown,
+      vec![(3, Bound), (4, Assignment), (5, Bound)], true);
+    inverse_test_one(3, vec![one, zero, dom0_2], vec![dom0_2, dom0_2, dom0_2], Unknown, True,
+      vec![(2, Assignment), (3, Assignment), (4, Assignment), (5, Assignment)], true);
+    inverse_test_one(4, vec![one, one, dom0_2], vec![dom0_2, dom0_2, dom0_2], False, False, vec![], false);
+    inverse_test_one(5, vec![two, zero, one], vec![one, two, zero], True, True, vec![], true);
+  }
+
+  #[test]
+  fn exhaustive_inverse_soundness() {
+    // `w[..3]` and `w[3..]` are inverse permutations of `[0..2]`.
+    let inverse = |w: &[i32]| (0..3).all(|i| 0 <= w[i] && w[i] < 3 && w[3 + w[i] as usize] == i as i32);
+    for doms in interval_tuples(&intervals(-1, 2), 6) {
+      let expected = solutions_hull(&doms, &inverse);
+      let result = inverse_consistency(doms[..3].to_vec(), doms[3..].to_vec())
+        .map(|(mut xs, ys)| { xs.extend(ys); xs });
+      check_soundness(&doms, &result, &expected);
+    }
+  }
+
+  #[test]
+  fn assignment_example() {
+    // `task[p]` is the task of the person `p` and `person[t]` the person doing the task `t`.
+    let n = 4;
+    let mut space = FDSpace::empty();
+    let task: Vec<_> = (0..n).map(|_| space.vstore.alloc((0, n - 1).to_interval())).collect();
+    let person: Vec<_> = (0..n).map(|_| space.vstore.alloc((0, n - 1).to_interval())).collect();
+    space.cstore.alloc(Inverse::new(task, person));
+    let search: OneSolution<_, VectorStack<_>, FDSpace> =
+      OneSolution::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    let mut count = 0;
+    for solution in Solutions::new(search, space) {
+      let values: Vec<i32> = solution.iter().map(|d| d.lower()).collect();
+      for p in 0..n as usize {
+        assert_eq!(values[n as usize + values[p] as usize], p as i32);
+      }
+      count += 1;
+    }
+    assert_eq!(count, 24);
+  }
+
+  fn bool_channel_test_one(test_num: u32, x: Interval<i32>, bs: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars: Vec<FDVar>| BoolChannel::new(vars[0], vars[1..].to_vec()),
+      Some(x).into_iter().chain(bs.into_iter()).collect(), before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn bool_channel_test() {
+    let dom0_1 = (0,1).to_interval();
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+
+    bool_channel_test_one(1, (0,2).to_interval(), vec![dom0_1, dom0_1, dom0_1], Unknown, Unknown, vec![], true);
+    bool_channel_test_one(2, (-5,5).to_interval(), vec![zero, dom0_1, dom0_1], Unknown, Unknown, vec![(0, Bound)], true);
+    bool_channel_test_one(3, (0,2).to_interval(), vec![dom0_1, one, dom0_1], Unknown, True,
+      vec![(0, Assignment), (1, Assignment), (3, Assignment)], true);
+    bool_channel_test_one(4, (1,1).to_interval(), vec![dom0_1, dom0_1, dom0_1], Unknown, True,
+      vec![(1, Assignment), (2, Assignment), (3, Assignment)], true);
+    bool_channel_test_one(5, (0,2).to_interval(), vec![one, dom0_1, one], False, False, vec![], false);
+    bool_channel_test_one(6, (0,1).to_interval(), vec![zero, zero, dom0_1], False, False, vec![], false);
+  }
+
+  #[test]
+  fn exhaustive_bool_channel() {
+    // `w[0] = j` with `w[1 + j] = 1` and the other booleans at 0.
+    let channel = |w: &[i32]| 0 <= w[0] && w[0] < 3 && (0..3).all(|k| w[1 + k as usize] == if k == w[0] { 1 } else { 0 });
+    for x in intervals(-1, 3) {
+      for bs in interval_tuples(&intervals(0, 1), 3) {
+        let doms: Vec<_> = Some(x).into_iter().chain(bs.clone().into_iter()).collect();
+        let expected = solutions_hull(&doms, &channel);
+        let result = bool_channel_consistency(x, bs.clone())
+          .map(|(x, mut bs)| { bs.insert(0, x); bs });
+        assert_eq!(result, expected, "x = {:?}, bs = {:?}", x, bs);
+      }
+    }
+  }
+}
@@ -0,0 +1,536 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Circuit constraints over successor variables: `succ[i] = j` means that the node `j` is visited right after the node `i`, the nodes are numbered from 0.
+//!
+//! `Circuit` forces the successors to form a single Hamiltonian circuit, a single node forms a circuit with itself (`succ[0] = 0`). `SubCircuit` forces the nodes that are not their own successor to form a single circuit, the other nodes are excluded from it.
+//!
+//! Both propagators first filter the successors with a bounds consistent `Distinct`, then:
+//!  * a chain of fixed successors can not be closed into a circuit missing some nodes (subtour elimination),
+//!  * the nodes of the circuit must belong to the same strongly connected component of the graph of the possible successors.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::distinct::*;
+use propagators::graph::strongly_connected_components;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::fmt::{Formatter, Debug, Error};
+
+#[derive(Clone)]
+pub struct Circuit<V>
+{
+  vars: Vec<V>,
+  distinct: Distinct<V>
+}
+
+impl<V> PropagatorKind for Circuit<V> {}
+
+impl<V> Circuit<V> where
+  V: Clone
+{
+  pub fn new(vars: Vec<V>) -> Circuit<V> {
+    Circuit {
+      distinct: Distinct::with_consistency(vars.clone(), DistinctConsistency::Bounds),
+      vars: vars
+    }
+  }
+}
+
+impl<V> Debug for Circuit<V> where
+  V: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("circuit({:?})", self.vars))
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for Circuit<V> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    circuit_subsumption(&self.vars, store, false)
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for Circuit<V> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + Cardinality + ShrinkLeft<B> + ShrinkRight<B>,
+  Domain: Difference<B, Output=Domain>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    propagate_circuit(&self.vars, &mut self.distinct, store, false)
+  }
+}
+
+impl<V> PropagatorDependencies<FDEvent> for Circuit<V> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.vars.iter().flat_map(|x| x.dependencies(FDEvent::Bound)).collect()
+  }
+}
+
+#[derive(Clone)]
+pub struct SubCircuit<V>
+{
+  vars: Vec<V>,
+  distinct: Distinct<V>
+}
+
+impl<V> PropagatorKind for SubCircuit<V> {}
+
+impl<V> SubCircuit<V> where
+  V: Clone
+{
+  pub fn new(vars: Vec<V>) -> SubCircuit<V> {
+    SubCircuit {
+      distinct: Distinct::with_consistency(vars.clone(), DistinctConsistency::Bounds),
+      vars: vars
+    }
+  }
+}
+
+impl<V> Debug for SubCircuit<V> where
+  V: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("subcircuit({:?})", self.vars))
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for SubCircuit<V> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    circuit_subsumption(&self.vars, store, true)
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for SubCircuit<V> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + Cardinality + ShrinkLeft<B> + ShrinkRight<B>,
+  Domain: Difference<B, Output=Domain>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    propagate_circuit(&self.vars, &mut self.distinct, store, true)
+  }
+}
+
+impl<V> PropagatorDependencies<FDEvent> for SubCircuit<V> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.vars.iter().flat_map(|x| x.dependencies(FDEvent::Bound)).collect()
+  }
+}
+
+// Checks that the successors form a (sub)circuit once they are all assigned.
+fn circuit_subsumption<Store, Domain, B, V>(vars: &Vec<V>, store: &Store, sub: bool) -> Trilean where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  let n = vars.len();
+  let mut succ = vec![];
+  for x in vars {
+    let d = x.read(store);
+    if d.lower() != d.upper() {
+      return Unknown;
+    }
+    match d.lower().to_usize() {
+      Some(j) if j < n => succ.push(j),
+      _ => return False
+    }
+  }
+  // A single node is a circuit on its own.
+  if !sub && n == 1 {
+    return True;
+  }
+  let in_circuit: Vec<usize> = (0..n).filter(|&i| succ[i] != i).collect();
+  if !sub && in_circuit.len() < n {
+    return False;
+  }
+  match in_circuit.first() {
+    None => True,
+    Some(&start) => {
+      let mut len = 1;
+      let mut i = succ[start];
+      while i != start && len <= n {
+        i = succ[i];
+        len += 1;
+      }
+      if i == start && len == in_circuit.len() { True }
+      else { False }
+    }
+  }
+}
+
+fn propagate_circuit<Store, Domain, B, V>(vars: &Vec<V>, distinct: &mut Distinct<V>, store: &mut Store, sub: bool) -> bool where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + Cardinality + ShrinkLeft<B> + ShrinkRight<B>,
+  Domain: Difference<B, Output=Domain>,
+  B: PrimInt
+{
+  let n = vars.len();
+  if n == 0 {
+    return true;
+  }
+  let last = B::from(n - 1).unwrap();
+  for x in vars {
+    let d = x.read(store);
+    if !x.update(store, d.shrink_left(B::zero()).shrink_right(last)) {
+      return false;
+    }
+  }
+  if !distinct.propagate(store) {
+    return false;
+  }
+  let ivs: Vec<(usize, usize)> = vars.iter()
+    .map(|x| { let d = x.read(store); (d.lower().to_usize().unwrap(), d.upper().to_usize().unwrap()) })
+    .collect();
+  match circuit_consistency(ivs, sub) {
+    None => false,
+    Some(ivs) => {
+      for (x, (l, u)) in vars.iter().zip(ivs.into_iter()) {
+        let (l, u) = (B::from(l).unwrap(), B::from(u).unwrap());
+        let d = x.read(store);
+        if d.lower() != l || d.upper() != u {
+          if !x.update(store, d.shrink_left(l).shrink_right(u)) {
+            return false;
+          }
+        }
+      }
+      true
+    }
+  }
+}
+
+// Removes `v` from the interval `iv` if it is one of its bounds, returns `false` if the interval becomes empty.
+fn remove_bound(iv: &mut (usize, usize), v: usize) -> bool {
+  if iv.0 == v { iv.0 += 1; }
+  else if iv.1 == v { iv.1 -= 1; }
+  iv.0 <= iv.1
+}
+
+// Filters the successor intervals `ivs` of a circuit (or of a subcircuit if `sub` is true). Returns `None` on failure.
+fn circuit_consistency(mut ivs: Vec<(usize, usize)>, sub: bool) -> Option<Vec<(usize, usize)>> {
+  let n = ivs.len();
+  // A single node is a circuit on its own.
+  if !sub && n == 1 {
+    return if ivs[0].0 == 0 && ivs[0].0 <= ivs[0].1 { Some(vec![(0, 0)]) } else { None };
+  }
+  // Every node belongs to a circuit. In a subcircuit, the nodes that can not be their own successor belong to the circuit, and so do their fixed successors.
+  let mut mandatory = vec![!sub; n];
+  let mut changed = true;
+  while changed {
+    changed = false;
+    for i in 0..n {
+      if mandatory[i] && (ivs[i].0 == i || ivs[i].1 == i) {
+        if !remove_bound(&mut ivs[i], i) {
+          return None;
+        }
+        changed = true;
+      }
+      let (l, u) = ivs[i];
+      if !mandatory[i] && (i < l || i > u) {
+        mandatory[i] = true;
+        changed = true;
+      }
+      if mandatory[i] && l == u && !mandatory[l] {
+        mandatory[l] = true;
+        changed = true;
+      }
+    }
+  }
+  let fixed: Vec<Option<usize>> = ivs.iter().enumerate()
+    .map(|(i, &(l, u))| if l == u && l != i { Some(l) } else { None })
+    .collect();
+  let mut has_pred = vec![false; n];
+  for &j in fixed.iter().flat_map(|j| j.iter()) {
+    if has_pred[j] { return None; }
+    has_pred[j] = true;
+  }
+
+  // Subtour elimination: the end of a chain of fixed successors can only go back to its start if the chain contains all the nodes of the circuit.
+  let mut visited = vec![false; n];
+  for start in 0..n {
+    if fixed[start].is_none() || has_pred[start] {
+      continue;
+    }
+    let mut chain = vec![false; n];
+    let mut end = start;
+    chain[start] = true;
+    visited[start] = true;
+    while let Some(j) = fixed[end] {
+      chain[j] = true;
+      visited[j] = true;
+      end = j;
+    }
+    if (0..n).any(|i| mandatory[i] && !chain[i]) {
+      if !remove_bound(&mut ivs[end], start) {
+        return None;
+      }
+    }
+  }
+  // The remaining fixed successors form closed circuits.
+  for start in 0..n {
+    if fixed[start].is_none() || visited[start] {
+      continue;
+    }
+    let mut cycle = vec![false; n];
+    let mut i = start;
+    while !cycle[i] {
+      cycle[i] = true;
+      visited[i] = true;
+      i = fixed[i].unwrap();
+    }
+    for k in 0..n {
+      if !cycle[k] {
+        if mandatory[k] { return None; }
+        ivs[k] = (k, k);
+      }
+    }
+  }
+
+  // Strongly connected components of the graph of the possible successors, self-loops excluded.
+  let succ: Vec<Vec<usize>> = ivs.iter().enumerate()
+    .map(|(i, &(l, u))| (l..u+1).filter(|&j| j != i).collect())
+    .collect();
+  let component = strongly_connected_components(&succ);
+  if let Some(first) = (0..n).find(|&i| mandatory[i]) {
+    let c = component[first];
+    for i in 0..n {
+      if component[i] != c {
+        if mandatory[i] { return None; }
+        ivs[i] = (i, i);
+      }
+    }
+  }
+  Some(ivs)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagation::store::Store;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::element::*;
+  use propagators::linear::*;
+  use variable::test::*;
+  use term::ops::*;
+  use search::search_tree_visitor::*;
+  use search::space::*;
+  use search::propagation::*;
+  use search::engine::branch_and_bound::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use propagators::test::*;
+  use gcollections::VectorStack;
+  use gcollections::ops::*;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  #[test]
+  fn circuit_test() {
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+    let three = (3,3).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let dom0_1 = (0,1).to_interval();
+
+    circuit_test_one(1, vec![one, two, three, zero], True, True, vec![], true);
+    circuit_test_one(2, vec![one, zero, three, two], False, False, vec![], false);
+    circuit_test_one(3, vec![dom0_3, dom0_3, dom0_3, dom0_3], Unknown, Unknown,
+      vec![(0, Bound), (3, Bound)], true);
+    // 0 -> 1 -> 2 is a chain: 2 can not go back to 0 and 3 can not be its own successor.
+    circuit_test_one(4, vec![one, two, dom0_3, dom0_3], Unknown, Unknown,
+      vec![(2, Bound), (3, Bound)], true);
+    // {0,1} and {2,3} are two strongly connected components.
+    circuit_test_one(5, vec![dom0_1, dom0_1, (2,3).to_interval(), (2,3).to_interval()], Unknown, Unknown,
+      vec![], false);
+    // 0 -> 1 -> 3 -> 0 is a closed circuit without 2, which can not be its own successor.
+    assert_eq!(circuit_consistency(vec![(1,1), (3,3), (0,3), (0,0)], false), None);
+    // A single node is its own successor.
+    circuit_test_one(6, vec![zero], True, True, vec![], true);
+    circuit_test_one(7, vec![dom0_3], Unknown, True, vec![(0, Assignment)], true);
+  }
+
+  fn circuit_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, Circuit::new, doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn subcircuit_test() {
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+    let three = (3,3).to_interval();
+    let dom0_3 = (0,3).to_interval();
+
+    subcircuit_test_one(1, vec![one, zero, two, three], True, True, vec![], true);
+    subcircuit_test_one(2, vec![zero, one, two, three], True, True, vec![], true);
+    subcircuit_test_one(3, vec![one, zero, three, two], False, False, vec![], false);
+    subcircuit_test_one(4, vec![dom0_3, dom0_3, dom0_3, dom0_3], Unknown, Unknown, vec![], true);
+    // The circuit 0 -> 1 -> 0 is closed, 2 and 3 are excluded.
+    subcircuit_test_one(5, vec![one, zero, dom0_3, dom0_3], Unknown, True,
+      vec![(2, Assignment), (3, Assignment)], true);
+    // [0..2] is a Hall interval so 2 -> 3, the chains 0 -> 1 and 2 -> 3 can not be closed.
+    subcircuit_test_one(6, vec![one, (0,2).to_interval(), dom0_3, (0,2).to_interval()], Unknown, Unknown,
+      vec![(1, Bound), (2, Assignment), (3, Bound)], true);
+    // 0 and 3 belong to the circuit but can not reach each other.
+    subcircuit_test_one(7, vec![one, (0,1).to_interval(), (2,3).to_interval(), two], Unknown, False,
+      vec![], false);
+    // 1 -> 0 puts 0 in the subcircuit, so it can not be its own successor.
+    assert_eq!(circuit_consistency(vec![(0,0), (0,0), (0,2), (0,3)], true), None);
+  }
+
+  fn subcircuit_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, SubCircuit::new, doms, before, after, delta_expected, propagate_success);
+  }
+
+  // The successors `w` form a single circuit, the nodes being their own successor are left out of a subcircuit.
+  fn is_circuit(w: &[i32], sub: bool) -> bool {
+    let nodes: Vec<usize> = (0..w.len()).filter(|&i| !sub || w[i] != i as i32).collect();
+    match nodes.first() {
+      None => true,
+      Some(&start) => {
+        let mut i = start;
+        for k in 0..nodes.len() {
+          i = w[i] as usize;
+          if (i == start) != (k == nodes.len() - 1) {
+            return false;
+          }
+        }
+        true
+      }
+    }
+  }
+
+  #[test]
+  fn exhaustive_soundness() {
+    for &sub in &[false, true] {
+      for ivs in interval_tuples(&intervals(0, 3), 4) {
+        let expected = solutions_hull(&ivs, |w| is_circuit(w, sub));
+        let result = circuit_consistency(ivs.iter().map(|&(l, u)| (l as usize, u as usize)).collect(), sub)
+          .map(|res| res.into_iter().map(|(l, u)| (l as i32, u as i32)).collect());
+        check_soundness(&ivs, &result, &expected);
+      }
+    }
+  }
+
+  // Length of the circuit `succ` in the graph `dist`.
+  fn tour_length(dist: &Vec<Vec<i32>>, succ: &Vec<usize>) -> i32 {
+    (0..succ.len()).map(|i| dist[i][succ[i]]).sum()
+  }
+
+  // Length of the shortest tour starting and ending in the node 0.
+  fn shortest_tour(dist: &Vec<Vec<i32>>) -> i32 {
+    fn search(dist: &Vec<Vec<i32>>, path: &mut Vec<usize>, best: &mut i32) {
+      let n = dist.len();
+      if path.len() == n {
+        let mut succ = vec![0; n];
+        for i in 0..n {
+          succ[path[i]] = path[(i + 1) % n];
+        }
+        *best = ::std::cmp::min(*best, tour_length(dist, &succ));
+        return;
+      }
+      for j in 1..n {
+        if !path.contains(&j) {
+          path.push(j);
+          search(dist, path, best);
+          path.pop();
+        }
+      }
+    }
+    let mut best = i32::max_value();
+    search(dist, &mut vec![0], &mut best);
+    best
+  }
+
+  // Travelling salesman problem: the length of the circuit over the successor variables is minimized with a branch and bound.
+  fn tsp(dist: Vec<Vec<i32>>) -> Option<i32> {
+    let n = dist.len();
+    let n_i32 = n as i32;
+    let max_dist = dist.iter().flat_map(|row| row.iter()).cloned().max().unwrap();
+    let mut space = FDSpace::empty();
+    let succ: Vec<_> = (0..n).map(|_| space.vstore.alloc((0, n_i32 - 1).to_interval())).collect();
+    let costs: Vec<_> = (0..n).map(|_| space.vstore.alloc((0, max_dist).to_interval())).collect();
+    let length = space.vstore.alloc((0, n_i32 * max_dist).to_interval());
+    for i in 0..n {
+      space.cstore.alloc(Element::new(dist[i].clone(), succ[i], costs[i]));
+    }
+    let mut vars = costs.clone();
+    vars.push(length);
+    let mut coefs = vec![1; n];
+    coefs.push(-1);
+    space.cstore.alloc(Linear::equal(coefs, vars, 0));
+    space.cstore.alloc(Circuit::new(succ.clone()));
+
+    let mut search: BranchAndBound<_, VectorStack<_>, _, _, _> = BranchAndBound::minimize(length,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    search.start(&space);
+    let (frozen, status) = search.enter(space);
+    if status != Status::Satisfiable {
+      return None;
+    }
+    assert!(search.is_optimal());
+    let length = search.best_value();
+    let space = frozen.restore(search.take_best_solution().unwrap());
+    assert_eq!(circuit_subsumption(&succ, &space.vstore, false), True);
+    let succ: Vec<usize> = succ.iter().map(|s| s.read(&space.vstore).lower() as usize).collect();
+    assert_eq!(Some(tour_length(&dist, &succ)), length);
+    length
+  }
+
+  #[test]
+  fn tsp_example() {
+    let dist = vec![
+      vec![0, 3, 9, 8, 4, 7],
+      vec![3, 0, 2, 6, 9, 5],
+      vec![9, 2, 0, 3, 7, 8],
+      vec![8, 6, 3, 0, 2, 9],
+      vec![4, 9, 7, 2, 0, 6],
+      vec![7, 5, 8, 9, 6, 0]
+    ];
+    assert_eq!(tsp(dist.clone()), Some(shortest_tour(&dist)));
+  }
+}
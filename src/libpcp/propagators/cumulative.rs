@@ -0,0 +1,489 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Cumulative resource constraint: the tasks `i` start at `s_i`, last `d_i` units of time and use `r_i` units of a resource of capacity `c`. At any time `t`, the sum of the demands of the tasks running at `t` (i.e. `s_i <= t < s_i + d_i`) must not exceed `c`.
+//!
+//! Two filtering algorithms are available:
+//!
+//! * *Time-table*: the compulsory parts `[lst_i..ect_i)` of the tasks form a profile of the resource usage, the tasks are pushed out of the times where the profile leaves not enough capacity for them.
+//! * *Edge-finding*: in addition to time-table, the energy `d_i * r_i` of the tasks is reasoned on every time window `[est_j..lct_k)`. If a task can not end inside a window together with the other tasks of the window, it must end after all of them. This is the O(n^3) formulation of the rule of Nuijten (1994) where only the window itself is used to compute the new bound.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::division::div_ceil;
+use propagation::events::*;
+use propagation::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CumulativeFiltering {
+  TimeTable,
+  EdgeFinding
+}
+
+#[derive(Clone)]
+pub struct Cumulative<V, B>
+{
+  starts: Vec<V>,
+  durations: Vec<B>,
+  demands: Vec<B>,
+  capacity: B,
+  filtering: CumulativeFiltering
+}
+
+impl<V, B> PropagatorKind for Cumulative<V, B> {}
+
+impl<V, B> Cumulative<V, B> where
+  B: PrimInt
+{
+  /// Cumulative constraint with time-table filtering.
+  pub fn new(starts: Vec<V>, durations: Vec<B>, demands: Vec<B>, capacity: B) -> Cumulative<V, B> {
+    Cumulative::with_filtering(starts, durations, demands, capacity, CumulativeFiltering::TimeTable)
+  }
+
+  pub fn with_filtering(starts: Vec<V>, durations: Vec<B>, demands: Vec<B>, capacity: B,
+    filtering: CumulativeFiltering) -> Cumulative<V, B>
+  {
+    assert!(starts.len() == durations.len() && starts.len() == demands.len(),
+      "Every task of a cumulative constraint must have a start, a duration and a demand.");
+    assert!(durations.iter().chain(demands.iter()).all(|&x| x >= B::zero()),
+      "Durations and demands must be positive.");
+    assert!(demands.iter().all(|&r| r <= capacity),
+      "A demand can not exceed the capacity of the resource.");
+    Cumulative {
+      starts: starts,
+      durations: durations,
+      demands: demands,
+      capacity: capacity,
+      filtering: filtering
+    }
+  }
+}
+
+impl<V, B> Debug for Cumulative<V, B> where
+  V: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("cumulative(starts: {:?}, durations: {:?}, demands: {:?}, capacity: {:?})",
+      self.starts, self.durations, self.demands, self.capacity))
+  }
+}
+
+// A task with its earliest and latest start times.
+#[derive(Clone, Copy, Debug)]
+struct Task<B> {
+  est: B,
+  lst: B,
+  duration: B,
+  demand: B
+}
+
+impl<B: PrimInt> Task<B> {
+  // The earliest completion time is saturated at the largest value of `B`, it only shortens the compulsory part.
+  fn ect(&self) -> B { self.est.saturating_add(self.duration) }
+  // The latest completion time is `None` if it overflows `B`.
+  fn lct(&self) -> Option<B> { self.lst.checked_add(&self.duration) }
+  fn energy(&self) -> Option<B> { self.duration.checked_mul(&self.demand) }
+
+  fn ends_before(&self, b: B) -> bool {
+    self.lct().map_or(false, |lct| lct <= b)
+  }
+
+  fn has_compulsory_part(&self) -> bool {
+    self.lst < self.ect()
+  }
+
+  // Task in the reversed time, where the start `s` becomes `-(s + d)`. The bounds overflowing `B` are relaxed to the limits of `B`.
+  fn mirror(&self) -> Task<B> {
+    Task {
+      est: self.lct().and_then(|lct| B::zero().checked_sub(&lct)).unwrap_or(B::min_value()),
+      lst: B::zero().checked_sub(&self.ect()).unwrap_or(B::max_value()),
+      duration: self.duration,
+      demand: self.demand
+    }
+  }
+
+  fn is_relevant(&self) -> bool {
+    self.duration > B::zero() && self.demand > B::zero()
+  }
+}
+
+// Sorted segments `[start..end)` of the profile with a non-zero height, `None` if the height overflows `B`. The tasks leaving at a time are removed before the tasks arriving are added, so the height only overflows if it exceeds the largest value of `B`.
+fn profile<B: PrimInt>(tasks: &Vec<Task<B>>) -> Option<Vec<(B, B, B)>> {
+  let mut events = vec![];
+  for t in tasks {
+    if t.has_compulsory_part() {
+      events.push((t.lst, t.demand));
+      events.push((t.ect(), B::zero() - t.demand));
+    }
+  }
+  events.sort();
+  let mut segments = vec![];
+  let mut height = B::zero();
+  let mut i = 0;
+  while i < events.len() {
+    let time = events[i].0;
+    while i < events.len() && events[i].0 == time {
+      height = match height.checked_add(&events[i].1) {
+        Some(height) => height,
+        None => return None
+      };
+      i += 1;
+    }
+    if i < events.len() && height > B::zero() {
+      segments.push((time, events[i].0, height));
+    }
+  }
+  Some(segments)
+}
+
+// Time-table filtering of the earliest start times, `None` if the resource is overloaded.
+fn time_table_est<B: PrimInt>(tasks: &Vec<Task<B>>, capacity: B) -> Option<Vec<B>> {
+  let segments = match profile(tasks) {
+    Some(segments) => segments,
+    None => return None
+  };
+  if segments.iter().any(|&(_, _, h)| h > capacity) {
+    return None;
+  }
+  let mut ests = vec![];
+  for t in tasks {
+    let mut est = t.est;
+    if t.is_relevant() {
+      for &(start, end, height) in &segments {
+        if start >= est.saturating_add(t.duration) { break; }
+        if end <= est { continue; }
+        // The compulsory part of the task itself is not counted.
+        let own = if t.has_compulsory_part() && t.lst <= start && end <= t.ect() { t.demand } else { B::zero() };
+        if (height - own).checked_add(&t.demand).map_or(true, |h| h > capacity) {
+          est = end;
+        }
+      }
+      if est > t.lst {
+        return None;
+      }
+    }
+    ests.push(est);
+  }
+  Some(ests)
+}
+
+// Energy `capacity * (b - a)` available in the window `[a..b)`, `None` on overflow.
+fn available_energy<B: PrimInt>(capacity: B, a: B, b: B) -> Option<B> {
+  b.checked_sub(&a).and_then(|width| capacity.checked_mul(&width))
+}
+
+// Edge-finding on the earliest start times, `None` if the resource is overloaded. A window or a task whose energy overflows `B` is not used for pruning.
+fn edge_finding_est<B: PrimInt>(tasks: &Vec<Task<B>>, capacity: B) -> Option<Vec<B>> {
+  let mut ests: Vec<B> = tasks.iter().map(|t| t.est).collect();
+  let relevant: Vec<&Task<B>> = tasks.iter().filter(|t| t.is_relevant()).collect();
+  for a in relevant.iter().map(|t| t.est) {
+    for b in relevant.iter().filter_map(|t| t.lct()) {
+      if a >= b { continue; }
+      // Tasks of the window `[a..b)`.
+      let energy = relevant.iter()
+        .filter(|t| t.est >= a && t.ends_before(b))
+        .fold(Some(B::zero()), |e, t| e.and_then(|e| t.energy().and_then(|te| e.checked_add(&te))));
+      let (energy, window) = match (energy, available_energy(capacity, a, b)) {
+        (Some(energy), Some(window)) => (energy, window),
+        _ => continue
+      };
+      if energy > window {
+        return None;
+      }
+      for (i, t) in tasks.iter().enumerate() {
+        if !t.is_relevant() || t.ends_before(b) { continue; }
+        // The task `t` can not end before `b`: it ends after all the tasks of the window.
+        let overload = t.energy().and_then(|te| energy.checked_add(&te))
+          .and_then(|e| available_energy(capacity, min(a, t.est), b).map(|w| e > w));
+        if overload == Some(true) {
+          let rest = available_energy(capacity - t.demand, a, b)
+            .and_then(|w| energy.checked_sub(&w));
+          if let Some(rest) = rest {
+            if rest > B::zero() {
+              if let Some(est) = div_ceil(rest, t.demand).and_then(|d| a.checked_add(&d)) {
+                ests[i] = max(ests[i], est);
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+  if tasks.iter().zip(ests.iter()).any(|(t, &est)| est > t.lst) {
+    None
+  }
+  else {
+    Some(ests)
+  }
+}
+
+impl<V, B> Cumulative<V, B> where
+  B: PrimInt
+{
+  fn tasks<Store, Domain>(&self, store: &Store) -> Vec<Task<B>> where
+    V: StoreRead<Store, Value=Domain>,
+    Domain: Bounded<Bound=B>
+  {
+    self.starts.iter().enumerate()
+      .map(|(i, s)| {
+        let s = s.read(store);
+        Task {
+          est: s.lower(),
+          lst: s.upper(),
+          duration: self.durations[i],
+          demand: self.demands[i]
+        }
+      })
+      .collect()
+  }
+
+  // Filters the earliest start times with `filter`, and the latest start times by applying it on the mirrored tasks. A latest start time overflowing `B` is not filtered.
+  fn filter<F>(&self, tasks: &Vec<Task<B>>, filter: F) -> Option<Vec<(B, B)>> where
+    F: Fn(&Vec<Task<B>>, B) -> Option<Vec<B>>
+  {
+    let ests = match filter(tasks, self.capacity) {
+      Some(ests) => ests,
+      None => return None
+    };
+    let mirrored = tasks.iter().map(|t| t.mirror()).collect();
+    let lsts = match filter(&mirrored, self.capacity) {
+      Some(ests) => ests.into_iter().zip(tasks.iter())
+        .map(|(est, t)| B::zero().checked_sub(&est)
+          .and_then(|lct| lct.checked_sub(&t.duration))
+          .map_or(t.lst, |lst| min(lst, t.lst)))
+        .collect::<Vec<_>>(),
+      None => return None
+    };
+    Some(ests.into_iter().zip(lsts.into_iter()).collect())
+  }
+}
+
+impl<Store, Domain, V, B> Subsumption<Store> for Cumulative<V, B> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let tasks = self.tasks(store);
+    let overloaded = profile(&tasks)
+      .map_or(true, |segments| segments.iter().any(|&(_, _, h)| h > self.capacity));
+    if overloaded {
+      False
+    }
+    else if tasks.iter().all(|t| t.est == t.lst) {
+      True
+    }
+    else {
+      Unknown
+    }
+  }
+}
+
+impl<Store, Domain, V, B> Propagator<Store> for Cumulative<V, B> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let tasks = self.tasks(store);
+    let mut bounds = match self.filter(&tasks, time_table_est) {
+      Some(bounds) => bounds,
+      None => return false
+    };
+    if self.filtering == CumulativeFiltering::EdgeFinding {
+      match self.filter(&tasks, edge_finding_est) {
+        Some(ef_bounds) => {
+          for (b, ef) in bounds.iter_mut().zip(ef_bounds.into_iter()) {
+            *b = (max(b.0, ef.0), min(b.1, ef.1));
+          }
+        }
+        None => return false
+      }
+    }
+    for (s, (est, lst)) in self.starts.iter().zip(bounds.into_iter()) {
+      let dom = s.read(store);
+      if dom.lower() != est || dom.upper() != lst {
+        if !s.update(store, dom.shrink_left(est).shrink_right(lst)) {
+          return false;
+        }
+      }
+    }
+    true
+  }
+}
+
+impl<V, B> PropagatorDependencies<FDEvent> for Cumulative<V, B> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.starts.iter().flat_map(|s| s.dependencies(FDEvent::Bound)).collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagation::store::Store;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::cmp::*;
+  use variable::test::*;
+  use term::*;
+  use term::ops::*;
+  use search::search_tree_visitor::*;
+  use search::space::*;
+  use search::propagation::*;
+  use search::engine::branch_and_bound::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use propagators::test::*;
+  use gcollections::VectorStack;
+  use gcollections::ops::*;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  #[test]
+  fn time_table_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom0_2 = (0,2).to_interval();
+    let dom1_2 = (1,2).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let dom0_4 = (0,4).to_interval();
+    let zero = (0,0).to_interval();
+    let two = (2,2).to_interval();
+
+    // Durations [3, 3], demands [2, 2], capacity 3: the tasks can not overlap.
+    cumulative_test_one(1, CumulativeFiltering::TimeTable, vec![3,3], vec![2,2], 3,
+      vec![zero, dom0_10], Unknown, Unknown, vec![(1, Bound)], true);
+    // The compulsory part of the first task is [2..3) and the second task must start after it.
+    cumulative_test_one(2, CumulativeFiltering::TimeTable, vec![3,3], vec![2,2], 3,
+      vec![dom0_2, dom0_4], Unknown, Unknown, vec![(1, Bound)], true);
+    cumulative_test_one(3, CumulativeFiltering::TimeTable, vec![3,3], vec![2,2], 3,
+      vec![dom1_2, dom0_3], Unknown, Unknown, vec![], false);
+    cumulative_test_one(4, CumulativeFiltering::TimeTable, vec![3,3], vec![2,2], 4,
+      vec![zero, two], True, True, vec![], true);
+    cumulative_test_one(5, CumulativeFiltering::TimeTable, vec![3,3], vec![2,2], 3,
+      vec![zero, two], False, False, vec![], false);
+  }
+
+  #[test]
+  fn edge_finding_test() {
+    // Three tasks of duration 2 and demand 1 on a capacity 1 in the window [0..5) leave [4..) for the last one.
+    let dom0_3 = (0,3).to_interval();
+    let dom0_10 = (0,10).to_interval();
+    cumulative_test_one(1, CumulativeFiltering::TimeTable, vec![2,2,2], vec![1,1,1], 1,
+      vec![dom0_3, dom0_3, dom0_10], Unknown, Unknown, vec![], true);
+    cumulative_test_one(2, CumulativeFiltering::EdgeFinding, vec![2,2,2], vec![1,1,1], 1,
+      vec![dom0_3, dom0_3, dom0_10], Unknown, Unknown, vec![(2, Bound)], true);
+    // Energy 6 in the window [0..5) is too much.
+    cumulative_test_one(3, CumulativeFiltering::EdgeFinding, vec![2,2,2], vec![1,1,1], 1,
+      vec![dom0_3, dom0_3, dom0_3], Unknown, Unknown, vec![], false);
+    // The energy of a task overflows `i32`, the windows are not used for pruning.
+    cumulative_test_one(4, CumulativeFiltering::EdgeFinding, vec![50000,50000], vec![50000,50000], 60000,
+      vec![(0,100000).to_interval(), (0,200000).to_interval()], Unknown, Unknown, vec![], true);
+  }
+
+  #[test]
+  fn extreme_domains_test() {
+    let (min, max) = (i32::min_value(), i32::max_value());
+    // The latest completion times overflow `i32`, nothing is pruned.
+    cumulative_test_one(1, CumulativeFiltering::EdgeFinding, vec![3,3], vec![2,2], 3,
+      vec![(max-10, max).to_interval(), (max-10, max).to_interval()], Unknown, Unknown, vec![], true);
+    cumulative_test_one(2, CumulativeFiltering::EdgeFinding, vec![3,3], vec![2,2], 3,
+      vec![(min, min+10).to_interval(), (min, min+10).to_interval()], Unknown, Unknown, vec![], true);
+    // The first task runs in [max-3..max), the second one must end before it.
+    cumulative_test_one(3, CumulativeFiltering::TimeTable, vec![3,3], vec![2,2], 3,
+      vec![(max-3, max-3).to_interval(), (max-8, max-2).to_interval()], Unknown, Unknown, vec![(1, Bound)], true);
+  }
+
+  #[test]
+  #[should_panic]
+  fn demand_exceeds_capacity() {
+    Cumulative::new(vec![0], vec![1], vec![4], 3);
+  }
+
+  fn cumulative_test_one(test_num: u32, filtering: CumulativeFiltering, durations: Vec<i32>,
+    demands: Vec<i32>, capacity: i32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars| Cumulative::with_filtering(vars, durations, demands, capacity, filtering),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  // Resource-constrained project scheduling: 6 tasks with precedences share a resource of capacity 4. The makespan is minimized with a branch and bound.
+  fn rcpsp(filtering: CumulativeFiltering) -> Option<i32> {
+    let durations = vec![3, 2, 4, 2, 3, 1];
+    let demands = vec![2, 3, 2, 2, 1, 3];
+    // (i, j): the task j starts after the end of the task i.
+    let precedences = vec![(0, 2), (1, 3), (2, 4), (3, 4), (3, 5)];
+    let horizon = durations.iter().sum::<i32>();
+    let mut space = FDSpace::empty();
+    let starts: Vec<_> = durations.iter()
+      .map(|_| space.vstore.alloc((0, horizon).to_interval()))
+      .collect();
+    let makespan = space.vstore.alloc((0, horizon).to_interval());
+    for (i, &s) in starts.iter().enumerate() {
+      space.cstore.alloc(x_leq_y(Addition::new(s, durations[i]), makespan));
+    }
+    for &(i, j) in &precedences {
+      space.cstore.alloc(x_leq_y(Addition::new(starts[i], durations[i]), starts[j]));
+    }
+    space.cstore.alloc(Cumulative::with_filtering(starts.clone(), durations.clone(), demands.clone(), 4, filtering));
+
+    let mut search: BranchAndBound<_, VectorStack<_>, _, _, _> = BranchAndBound::minimize(makespan,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    search.start(&space);
+    let (frozen, status) = search.enter(space);
+    if status != Status::Satisfiable {
+      return None;
+    }
+    assert!(search.is_optimal());
+    let makespan = search.best_value();
+    // The resource is never overloaded in the best schedule.
+    let space = frozen.restore(search.take_best_solution().unwrap());
+    let starts: Vec<i32> = starts.iter().map(|s| s.read(&space.vstore).lower()).collect();
+    for t in 0..horizon {
+      let usage: i32 = (0..starts.len())
+        .filter(|&i| starts[i] <= t && t < starts[i] + durations[i])
+        .map(|i| demands[i])
+        .sum();
+      assert!(usage <= 4);
+    }
+    makespan
+  }
+
+  #[test]
+  fn rcpsp_example() {
+    assert_eq!(rcpsp(CumulativeFiltering::TimeTable), Some(12));
+    assert_eq!(rcpsp(CumulativeFiltering::EdgeFinding), Some(12));
+  }
+}
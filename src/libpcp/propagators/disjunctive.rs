@@ -0,0 +1,561 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Disjunctive (unary resource) constraint: the tasks `i` start at `s_i` and last `d_i` units of time, no two tasks can overlap.
+//!
+//! The filtering is based on the Θ-trees and Θ-Λ-trees of Vilím (2004, 2007), each algorithm runs in O(n log n):
+//!
+//! * *Overload checking* and *edge-finding*: if a task `i` can not end before a set of tasks `Ω` ends, it must end after all the tasks of `Ω`.
+//! * *Detectable precedences*: if `ect_i > lst_j`, the task `j` precedes the task `i`.
+//! * *Not-first/not-last*: if a task `i` can not start before all the tasks of `Ω`, it starts after at least one of them.
+//!
+//! The algorithms only update the earliest start times, the latest completion times are updated by applying them on the mirrored tasks.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagation::events::*;
+use propagation::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+#[derive(Clone)]
+pub struct Disjunctive<V, B>
+{
+  starts: Vec<V>,
+  durations: Vec<B>
+}
+
+impl<V, B> PropagatorKind for Disjunctive<V, B> {}
+
+impl<V, B> Disjunctive<V, B> where
+  B: PrimInt
+{
+  pub fn new(starts: Vec<V>, durations: Vec<B>) -> Disjunctive<V, B> {
+    assert!(starts.len() == durations.len(),
+      "Every task of a disjunctive constraint must have a start and a duration.");
+    assert!(durations.iter().all(|&d| d >= B::zero()),
+      "Durations must be positive.");
+    Disjunctive {
+      starts: starts,
+      durations: durations
+    }
+  }
+}
+
+impl<V, B> Debug for Disjunctive<V, B> where
+  V: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("disjunctive(starts: {:?}, durations: {:?})",
+      self.starts, self.durations))
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Task<B> {
+  est: B,
+  lst: B,
+  duration: B
+}
+
+// The completion times are saturated at the largest value of `B`. The earliest completion times computed by the trees are then never larger than a saturated latest completion time, which behaves as an infinite bound.
+impl<B: PrimInt> Task<B> {
+  fn ect(&self) -> B { self.est.saturating_add(self.duration) }
+  fn lct(&self) -> B { self.lst.saturating_add(self.duration) }
+
+  // Task in the reversed time, where the start `s` becomes `-(s + d)`. The bounds overflowing `B` are relaxed to the limits of `B`.
+  fn mirror(&self) -> Task<B> {
+    Task {
+      est: self.lst.checked_add(&self.duration)
+        .and_then(|lct| B::zero().checked_sub(&lct))
+        .unwrap_or(B::min_value()),
+      lst: B::zero().checked_sub(&self.ect()).unwrap_or(B::max_value()),
+      duration: self.duration
+    }
+  }
+}
+
+fn mirror<B: PrimInt>(tasks: &Vec<Task<B>>) -> Vec<Task<B>> {
+  tasks.iter().map(|t| t.mirror()).collect()
+}
+
+fn sorted_by<B, F>(tasks: &Vec<Task<B>>, key: F) -> Vec<usize> where
+  B: PrimInt,
+  F: Fn(&Task<B>) -> B
+{
+  let mut order: Vec<usize> = (0..tasks.len()).collect();
+  order.sort_by_key(|&i| key(&tasks[i]));
+  order
+}
+
+// `ect + duration` where `B::min_value()` stands for the earliest completion time of the empty set, saturated at the largest value of `B`.
+fn shift<B: PrimInt>(ect: B, duration: B) -> B {
+  if ect == B::min_value() { ect } else { ect.saturating_add(duration) }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ThetaLambdaNode<B> {
+  sum_duration: B,
+  ect: B,
+  sum_duration_bar: B,
+  ect_bar: B,
+  resp_duration_bar: Option<usize>,
+  resp_ect_bar: Option<usize>
+}
+
+impl<B: PrimInt> ThetaLambdaNode<B> {
+  fn empty() -> Self {
+    ThetaLambdaNode {
+      sum_duration: B::zero(),
+      ect: B::min_value(),
+      sum_duration_bar: B::zero(),
+      ect_bar: B::min_value(),
+      resp_duration_bar: None,
+      resp_ect_bar: None
+    }
+  }
+
+  fn combine(left: &Self, right: &Self) -> Self {
+    let sum_duration_bar_left = left.sum_duration_bar.saturating_add(right.sum_duration);
+    let sum_duration_bar_right = left.sum_duration.saturating_add(right.sum_duration_bar);
+    let (sum_duration_bar, resp_duration_bar) =
+      if sum_duration_bar_left >= sum_duration_bar_right { (sum_duration_bar_left, left.resp_duration_bar) }
+      else { (sum_duration_bar_right, right.resp_duration_bar) };
+    let ect_bar_left = shift(left.ect_bar, right.sum_duration);
+    let ect_bar_middle = shift(left.ect, right.sum_duration_bar);
+    let (mut ect_bar, mut resp_ect_bar) = (right.ect_bar, right.resp_ect_bar);
+    if ect_bar_middle > ect_bar {
+      ect_bar = ect_bar_middle;
+      resp_ect_bar = right.resp_duration_bar;
+    }
+    if ect_bar_left > ect_bar {
+      ect_bar = ect_bar_left;
+      resp_ect_bar = left.resp_ect_bar;
+    }
+    ThetaLambdaNode {
+      sum_duration: left.sum_duration.saturating_add(right.sum_duration),
+      ect: max(right.ect, shift(left.ect, right.sum_duration)),
+      sum_duration_bar: sum_duration_bar,
+      ect_bar: ect_bar,
+      resp_duration_bar: resp_duration_bar,
+      resp_ect_bar: resp_ect_bar
+    }
+  }
+}
+
+// Balanced binary tree where the tasks are the leaves sorted by earliest start times. The tasks in the set `Θ` are white and those in `Λ` are gray. The root gives the earliest completion time of `Θ` and of `Θ` with at most one task of `Λ`.
+struct ThetaLambdaTree<'a, B: 'a> {
+  tasks: &'a Vec<Task<B>>,
+  nodes: Vec<ThetaLambdaNode<B>>,
+  leaves: Vec<usize>
+}
+
+impl<'a, B: PrimInt> ThetaLambdaTree<'a, B> {
+  fn new(tasks: &'a Vec<Task<B>>) -> Self {
+    let size = tasks.len().next_power_of_two();
+    let mut leaves = vec![0; tasks.len()];
+    for (rank, i) in sorted_by(tasks, |t| t.est).into_iter().enumerate() {
+      leaves[i] = size + rank;
+    }
+    ThetaLambdaTree {
+      tasks: tasks,
+      nodes: vec![ThetaLambdaNode::empty(); 2 * size],
+      leaves: leaves
+    }
+  }
+
+  fn update(&mut self, i: usize, leaf: ThetaLambdaNode<B>) {
+    let mut node = self.leaves[i];
+    self.nodes[node] = leaf;
+    while node > 1 {
+      node = node / 2;
+      self.nodes[node] = ThetaLambdaNode::combine(&self.nodes[2 * node], &self.nodes[2 * node + 1]);
+    }
+  }
+
+  fn insert(&mut self, i: usize) {
+    let t = self.tasks[i];
+    self.update(i, ThetaLambdaNode {
+      sum_duration: t.duration,
+      ect: t.ect(),
+      sum_duration_bar: t.duration,
+      ect_bar: t.ect(),
+      resp_duration_bar: None,
+      resp_ect_bar: None
+    });
+  }
+
+  fn gray(&mut self, i: usize) {
+    let t = self.tasks[i];
+    self.update(i, ThetaLambdaNode {
+      sum_duration: B::zero(),
+      ect: B::min_value(),
+      sum_duration_bar: t.duration,
+      ect_bar: t.ect(),
+      resp_duration_bar: Some(i),
+      resp_ect_bar: Some(i)
+    });
+  }
+
+  fn remove(&mut self, i: usize) {
+    self.update(i, ThetaLambdaNode::empty());
+  }
+
+  fn ect(&self) -> B { self.nodes[1].ect }
+  fn ect_bar(&self) -> B { self.nodes[1].ect_bar }
+  fn resp_ect_bar(&self) -> Option<usize> { self.nodes[1].resp_ect_bar }
+}
+
+// Edge-finding, it also checks that the resource is not overloaded.
+fn edge_finding<B: PrimInt>(tasks: &Vec<Task<B>>, ests: &mut Vec<B>) -> bool {
+  let mut tree = ThetaLambdaTree::new(tasks);
+  for i in 0..tasks.len() {
+    tree.insert(i);
+  }
+  let mut order = sorted_by(tasks, |t| t.lct());
+  order.reverse();
+  for (k, &j) in order.iter().enumerate() {
+    if tree.ect() > tasks[j].lct() {
+      return false;
+    }
+    tree.gray(j);
+    if let Some(&next) = order.get(k + 1) {
+      while tree.ect_bar() > tasks[next].lct() {
+        match tree.resp_ect_bar() {
+          Some(i) => {
+            ests[i] = max(ests[i], tree.ect());
+            tree.remove(i);
+          }
+          // The resource is overloaded by the white tasks.
+          None => return false
+        }
+      }
+    }
+  }
+  true
+}
+
+fn detectable_precedences<B: PrimInt>(tasks: &Vec<Task<B>>, ests: &mut Vec<B>) {
+  let mut tree = ThetaLambdaTree::new(tasks);
+  let by_lst = sorted_by(tasks, |t| t.lst);
+  let mut inserted = vec![false; tasks.len()];
+  let mut next = 0;
+  for i in sorted_by(tasks, |t| t.ect()) {
+    while next < by_lst.len() && tasks[i].ect() > tasks[by_lst[next]].lst {
+      tree.insert(by_lst[next]);
+      inserted[by_lst[next]] = true;
+      next += 1;
+    }
+    if inserted[i] {
+      tree.remove(i);
+      ests[i] = max(ests[i], tree.ect());
+      tree.insert(i);
+    }
+    else {
+      ests[i] = max(ests[i], tree.ect());
+    }
+  }
+}
+
+fn not_last<B: PrimInt>(tasks: &Vec<Task<B>>, lcts: &mut Vec<B>) {
+  let mut tree = ThetaLambdaTree::new(tasks);
+  let by_lst = sorted_by(tasks, |t| t.lst);
+  let mut inserted = vec![false; tasks.len()];
+  let mut next = 0;
+  for i in sorted_by(tasks, |t| t.lct()) {
+    while next < by_lst.len() && tasks[i].lct() > tasks[by_lst[next]].lst {
+      tree.insert(by_lst[next]);
+      inserted[by_lst[next]] = true;
+      next += 1;
+    }
+    if inserted[i] { tree.remove(i); }
+    if tree.ect() > tasks[i].lst {
+      // `i` ends before the latest start time of the other tasks in `Θ`.
+      let j = by_lst[..next].iter().rev().find(|&&j| j != i).unwrap();
+      lcts[i] = min(lcts[i], tasks[*j].lst);
+    }
+    if inserted[i] { tree.insert(i); }
+  }
+}
+
+// New earliest start times of the tasks, `None` if the resource is overloaded.
+fn earliest_starts<B: PrimInt>(tasks: &Vec<Task<B>>) -> Option<Vec<B>> {
+  let mut ests: Vec<B> = tasks.iter().map(|t| t.est).collect();
+  if !edge_finding(tasks, &mut ests) {
+    return None;
+  }
+  detectable_precedences(tasks, &mut ests);
+  // Not-first is not-last in the reversed time.
+  let mirrored = mirror(tasks);
+  let mut lcts: Vec<B> = mirrored.iter().map(|t| t.lct()).collect();
+  not_last(&mirrored, &mut lcts);
+  // Only the filtered latest completion times are mirrored back, the others may be saturated.
+  for ((est, lct), t) in ests.iter_mut().zip(lcts.into_iter()).zip(mirrored.iter()) {
+    if lct < t.lct() {
+      if let Some(new_est) = B::zero().checked_sub(&lct) {
+        *est = max(*est, new_est);
+      }
+    }
+  }
+  Some(ests)
+}
+
+impl<V, B> Disjunctive<V, B> where
+  B: PrimInt
+{
+  // Tasks of non-zero duration with their indexes.
+  fn tasks<Store, Domain>(&self, store: &Store) -> (Vec<usize>, Vec<Task<B>>) where
+    V: StoreRead<Store, Value=Domain>,
+    Domain: Bounded<Bound=B>
+  {
+    let mut indexes = vec![];
+    let mut tasks = vec![];
+    for (i, s) in self.starts.iter().enumerate() {
+      if self.durations[i] > B::zero() {
+        let s = s.read(store);
+        indexes.push(i);
+        tasks.push(Task {
+          est: s.lower(),
+          lst: s.upper(),
+          duration: self.durations[i]
+        });
+      }
+    }
+    (indexes, tasks)
+  }
+}
+
+impl<Store, Domain, V, B> Subsumption<Store> for Disjunctive<V, B> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let (_, tasks) = self.tasks(store);
+    let mut ests: Vec<B> = tasks.iter().map(|t| t.est).collect();
+    if !edge_finding(&tasks, &mut ests) {
+      False
+    }
+    else if tasks.iter().all(|t| t.est == t.lst) {
+      True
+    }
+    else {
+      Unknown
+    }
+  }
+}
+
+impl<Store, Domain, V, B> Propagator<Store> for Disjunctive<V, B> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let (indexes, tasks) = self.tasks(store);
+    let ests = match earliest_starts(&tasks) {
+      Some(ests) => ests,
+      None => return false
+    };
+    // A latest start time overflowing `B` is not filtered.
+    let lsts = match earliest_starts(&mirror(&tasks)) {
+      Some(ests) => ests.into_iter().zip(tasks.iter())
+        .map(|(est, t)| B::zero().checked_sub(&est)
+          .and_then(|lct| lct.checked_sub(&t.duration))
+          .map_or(t.lst, |lst| min(lst, t.lst)))
+        .collect::<Vec<_>>(),
+      None => return false
+    };
+    for (k, &i) in indexes.iter().enumerate() {
+      if ests[k] != tasks[k].est || lsts[k] != tasks[k].lst {
+        let s = &self.starts[i];
+        let dom = s.read(store);
+        if !s.update(store, dom.shrink_left(ests[k]).shrink_right(lsts[k])) {
+          return false;
+        }
+      }
+    }
+    true
+  }
+}
+
+impl<V, B> PropagatorDependencies<FDEvent> for Disjunctive<V, B> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.starts.iter().flat_map(|s| s.dependencies(FDEvent::Bound)).collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagation::store::Store;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::cmp::*;
+  use propagators::cumulative::*;
+  use variable::test::*;
+  use term::*;
+  use term::ops::*;
+  use search::search_tree_visitor::*;
+  use search::space::*;
+  use search::propagation::*;
+  use search::engine::branch_and_bound::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use propagators::test::*;
+  use gcollections::VectorStack;
+  use gcollections::ops::*;
+  use test::Bencher;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  #[test]
+  fn disjunctive_test() {
+    let dom0_1 = (0,1).to_interval();
+    let dom0_2 = (0,2).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let dom0_10 = (0,10).to_interval();
+    let zero = (0,0).to_interval();
+    let two = (2,2).to_interval();
+
+    // Overload: three tasks of duration 2 in [0..5).
+    disjunctive_test_one(1, vec![2,2,2], vec![dom0_3, dom0_3, dom0_3], False, False, vec![], false);
+    // Edge-finding: the third task can not end before the first two.
+    disjunctive_test_one(2, vec![2,2,3], vec![dom0_2, dom0_2, dom0_10], Unknown, Unknown, vec![(2, Bound)], true);
+    // Detectable precedence: the first task must precede the second one.
+    disjunctive_test_one(3, vec![3,2], vec![dom0_1, dom0_10], Unknown, Unknown, vec![(1, Bound)], true);
+    disjunctive_test_one(4, vec![2,2], vec![zero, two], True, True, vec![], true);
+    disjunctive_test_one(5, vec![3,2], vec![zero, two], False, False, vec![], false);
+    // Tasks of zero duration are ignored.
+    disjunctive_test_one(6, vec![0,2], vec![dom0_1, zero], True, True, vec![], true);
+  }
+
+  #[test]
+  fn extreme_domains_test() {
+    let (min, max) = (i32::min_value(), i32::max_value());
+    // The completion times overflow `i32`, nothing is pruned.
+    disjunctive_test_one(1, vec![3,3], vec![(max-10, max).to_interval(), (max-10, max).to_interval()],
+      Unknown, Unknown, vec![], true);
+    disjunctive_test_one(2, vec![3,3], vec![(min, min+10).to_interval(), (min, min+10).to_interval()],
+      Unknown, Unknown, vec![], true);
+    // The first task runs in [max-3..max), the second one must end before it.
+    disjunctive_test_one(3, vec![3,3], vec![(max-3, max-3).to_interval(), (max-8, max-2).to_interval()],
+      Unknown, Unknown, vec![(1, Bound)], true);
+  }
+
+  fn disjunctive_test_one(test_num: u32, durations: Vec<i32>, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| Disjunctive::new(vars, durations),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  // The propagation never removes a start time of a schedule and is exact when every start time is fixed.
+  #[test]
+  fn exhaustive_disjunctive() {
+    for durations in vec![vec![1,2,3], vec![2,2,2], vec![3,1,2], vec![1,4,1]] {
+      for doms in interval_tuples(&intervals(0, 4), 3) {
+        let result = fixpoint(|vars| Disjunctive::new(vars, durations.clone()), &doms);
+        let expected = solutions_hull(&doms, |w| (0..w.len()).all(|i| (0..i).all(|j|
+          w[i] >= w[j] + durations[j] || w[j] >= w[i] + durations[i])));
+        check_soundness(&doms, &result, &expected);
+      }
+    }
+  }
+
+  // Job-shop: each job is a sequence of `(machine, duration)` operations and each machine processes one operation at a time.
+  fn jobshop(jobs: &Vec<Vec<(usize, i32)>>, dedicated: bool) -> Option<i32> {
+    let horizon = jobs.iter().flat_map(|job| job.iter().map(|&(_, d)| d)).sum::<i32>();
+    let num_machines = jobs.iter().flat_map(|job| job.iter().map(|&(m, _)| m + 1)).max().unwrap();
+    let mut space = FDSpace::empty();
+    let makespan = space.vstore.alloc((0, horizon).to_interval());
+    let mut machines = vec![(vec![], vec![]); num_machines];
+    for job in jobs {
+      let starts: Vec<_> = job.iter().map(|_| space.vstore.alloc((0, horizon).to_interval())).collect();
+      for (k, &(machine, duration)) in job.iter().enumerate() {
+        let end = Addition::new(starts[k], duration);
+        if k + 1 < job.len() {
+          space.cstore.alloc(x_leq_y(end, starts[k + 1]));
+        }
+        else {
+          space.cstore.alloc(x_leq_y(end, makespan));
+        }
+        machines[machine].0.push(starts[k]);
+        machines[machine].1.push(duration);
+      }
+    }
+    for (starts, durations) in machines {
+      if dedicated {
+        space.cstore.alloc(Disjunctive::new(starts, durations));
+      }
+      else {
+        let demands = vec![1; starts.len()];
+        space.cstore.alloc(Cumulative::new(starts, durations, demands, 1));
+      }
+    }
+
+    let mut search: BranchAndBound<_, VectorStack<_>, _, _, _> = BranchAndBound::minimize(makespan,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, Status::Satisfiable);
+    assert!(search.is_optimal());
+    search.best_value()
+  }
+
+  fn small_jobshop() -> Vec<Vec<(usize, i32)>> {
+    vec![vec![(0, 3), (1, 2), (2, 2)],
+         vec![(0, 2), (2, 1), (1, 4)],
+         vec![(1, 4), (2, 3), (0, 1)]]
+  }
+
+  // Fisher and Thompson (1963) 6x6 instance, the optimal makespan is 55.
+  fn ft06() -> Vec<Vec<(usize, i32)>> {
+    vec![vec![(2, 1), (0, 3), (1, 6), (3, 7), (5, 3), (4, 6)],
+         vec![(1, 8), (2, 5), (4, 10), (5, 10), (0, 10), (3, 4)],
+         vec![(2, 5), (3, 4), (5, 8), (0, 9), (1, 1), (4, 7)],
+         vec![(1, 5), (0, 5), (2, 5), (3, 3), (4, 8), (5, 9)],
+         vec![(2, 9), (1, 3), (4, 5), (5, 4), (0, 3), (3, 1)],
+         vec![(1, 3), (3, 3), (5, 9), (0, 10), (4, 4), (2, 1)]]
+  }
+
+  #[test]
+  fn example_jobshop() {
+    assert_eq!(jobshop(&small_jobshop(), true), Some(11));
+    assert_eq!(jobshop(&small_jobshop(), false), Some(11));
+  }
+
+  #[bench]
+  fn bench_ft06_disjunctive(b: &mut Bencher) {
+    b.iter(|| {
+      assert_eq!(jobshop(&ft06(), true), Some(55))
+    });
+  }
+}
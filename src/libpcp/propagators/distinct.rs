@@ -12,21 +12,38 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! All different constraint: the variables must take pairwise distinct values.
+//!
+//! The domain consistency of Régin (based on a maximum matching) is deliberately not provided. The variables have interval domains which can only keep the hull of the values supported by a matching, and this hull is exactly what the `Bounds` consistency computes with the Hall intervals.
+
 use kernel::*;
 use kernel::Trilean::*;
 use propagators::PropagatorKind;
+use propagators::bounds::{read_bounds, write_bounds};
 use propagators::cmp::x_neq_y::*;
 use propagation::events::*;
 use propagation::*;
 use term::ops::*;
 use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::min;
 use std::fmt::{Formatter, Debug, Error};
 
+/// Filtering algorithm of `Distinct`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DistinctConsistency {
+  /// Decomposition into `x != y` for every pair of variables, it only prunes on assignment.
+  Pairwise,
+  /// Bounds consistency with Hall intervals (López-Ortiz et al., 2003).
+  Bounds
+}
+
 #[derive(Clone)]
 pub struct Distinct<V>
 {
   props: Vec<XNeqY<V,V>>,
-  vars: Vec<V>
+  vars: Vec<V>,
+  consistency: DistinctConsistency
 }
 
 impl<V> PropagatorKind for Distinct<V> {}
@@ -35,16 +52,23 @@ impl<V> Distinct<V> where
   V: Clone
 {
   pub fn new(vars: Vec<V>) -> Distinct<V> {
+    Distinct::with_consistency(vars, DistinctConsistency::Pairwise)
+  }
+
+  pub fn with_consistency(vars: Vec<V>, consistency: DistinctConsistency) -> Distinct<V> {
     let mut props = vec![];
-    for i in 0..vars.len()-1 {
-      for j in i+1..vars.len() {
-        let i_neq_j = XNeqY::new(vars[i].clone(), vars[j].clone());
-        props.push(i_neq_j);
+    if consistency == DistinctConsistency::Pairwise {
+      for i in 0..vars.len()-1 {
+        for j in i+1..vars.len() {
+          let i_neq_j = XNeqY::new(vars[i].clone(), vars[j].clone());
+          props.push(i_neq_j);
+        }
       }
     }
     Distinct {
       props: props,
-      vars: vars
+      vars: vars,
+      consistency: consistency
     }
   }
 }
@@ -61,36 +85,54 @@ impl<V> Debug for Distinct<V> where
   }
 }
 
-impl<Store, Domain, V> Subsumption<Store> for Distinct<V> where
+impl<Store, Domain, V, B> Subsumption<Store> for Distinct<V> where
   V: StoreRead<Store, Value=Domain> + Clone,
-  Domain: Bounded + Disjoint
+  Domain: Bounded<Bound=B> + Disjoint,
+  B: PrimInt
 {
   fn is_subsumed(&self, store: &Store) -> Trilean {
-    let mut all_entailed = true;
-    for p in &self.props {
-      match p.is_subsumed(store) {
-        False => return False,
-        Unknown => all_entailed = false,
-        _ => ()
+    match self.consistency {
+      DistinctConsistency::Pairwise => {
+        let mut all_entailed = true;
+        for p in &self.props {
+          match p.is_subsumed(store) {
+            False => return False,
+            Unknown => all_entailed = false,
+            _ => ()
+          }
+        }
+        if all_entailed { True }
+        else { Unknown }
       }
+      DistinctConsistency::Bounds => bounds_subsumption(read_bounds(&self.vars, store))
     }
-    if all_entailed { True }
-    else { Unknown }
   }
 }
 
-impl<Store, Domain, V> Propagator<Store> for Distinct<V> where
+impl<Store, Domain, V, B> Propagator<Store> for Distinct<V> where
   V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
-  Domain: Bounded + Cardinality,
-  Domain: Difference<<Domain as Bounded>::Bound, Output=Domain>
+  Domain: Bounded<Bound=B> + Cardinality + ShrinkLeft<B> + ShrinkRight<B>,
+  Domain: Difference<<Domain as Bounded>::Bound, Output=Domain>,
+  B: PrimInt
 {
   fn propagate(&mut self, store: &mut Store) -> bool {
-    for p in &mut self.props {
-      if !p.propagate(store) {
-        return false;
+    match self.consistency {
+      DistinctConsistency::Pairwise => {
+        for p in &mut self.props {
+          if !p.propagate(store) {
+            return false;
+          }
+        }
+        true
+      }
+      DistinctConsistency::Bounds => {
+        let bounds = read_bounds(&self.vars, store);
+        match bounds_consistency(bounds) {
+          Some(bounds) => write_bounds(&self.vars, store, bounds),
+          None => false
+        }
       }
     }
-    true
   }
 }
 
@@ -98,8 +140,191 @@ impl<V> PropagatorDependencies<FDEvent> for Distinct<V> where
   V: ViewDependencies<FDEvent> + Clone
 {
   fn dependencies(&self) -> Vec<(usize, FDEvent)> {
-    self.vars.iter().flat_map(|v| v.dependencies(FDEvent::Inner)).collect()
+    let event = match self.consistency {
+      DistinctConsistency::Pairwise => FDEvent::Inner,
+      _ => FDEvent::Bound
+    };
+    self.vars.iter().flat_map(|v| v.dependencies(event)).collect()
+  }
+}
+
+// Entailed if the intervals are pairwise disjoint and disentailed if two variables are assigned to the same value.
+fn bounds_subsumption<B: PrimInt>(mut ivs: Vec<(B, B)>) -> Trilean {
+  ivs.sort();
+  if ivs.windows(2).all(|w| w[0].1 < w[1].0) {
+    return True;
+  }
+  let fixed: Vec<B> = ivs.iter()
+    .filter(|iv| iv.0 == iv.1)
+    .map(|iv| iv.0)
+    .collect();
+  if fixed.windows(2).any(|w| w[0] == w[1]) { False }
+  else { Unknown }
+}
+
+// Bounds consistency of the intervals `ivs`, returns `None` on failure. A Hall interval contains at most `n` values, so the gaps between the bounds larger than `n + 2` are shrunk to `n + 2` without changing the Hall intervals. The algorithm then runs on small integers and cannot overflow, even if the domains reach the limits of `B`.
+fn bounds_consistency<B: PrimInt>(ivs: Vec<(B, B)>) -> Option<Vec<(B, B)>> {
+  let n = ivs.len();
+  let mut points: Vec<B> = ivs.iter().flat_map(|&(l, u)| vec![l, u]).collect();
+  points.sort();
+  points.dedup();
+  let max_gap = n as i64 + 2;
+  let mut coords = vec![0i64; points.len()];
+  for k in 1..points.len() {
+    let gap = points[k].checked_sub(&points[k-1])
+      .and_then(|g| g.to_i64())
+      .map_or(max_gap, |g| min(g, max_gap));
+    coords[k] = coords[k-1] + gap;
+  }
+  let coord = |b: B| coords[points.binary_search(&b).unwrap()];
+  // The new bounds are either bounds of the domains or next to them.
+  let value = |c: i64| match coords.binary_search(&c) {
+    Ok(k) => points[k],
+    Err(k) if k > 0 && c == coords[k-1] + 1 => points[k-1] + B::one(),
+    Err(k) => points[k] - B::one()
+  };
+  let ivs = ivs.into_iter().map(|(l, u)| (coord(l), coord(u))).collect();
+  hall_intervals(ivs).map(|ivs| ivs.into_iter().map(|(l, u)| (value(l), value(u))).collect())
+}
+
+// Bounds consistency algorithm of López-Ortiz, Quimper, Tromp and van Beek, "A fast and simple algorithm for bounds consistency of the alldifferent constraint" (IJCAI 2003). The intervals are inclusive, the function returns `None` on failure.
+fn hall_intervals<B: PrimInt>(mut ivs: Vec<(B, B)>) -> Option<Vec<(B, B)>> {
+  let n = ivs.len();
+  if n == 0 {
+    return Some(ivs);
+  }
+  let one = B::one();
+  let two = one + one;
+  let mut minsorted: Vec<usize> = (0..n).collect();
+  let mut maxsorted: Vec<usize> = (0..n).collect();
+  minsorted.sort_by(|&a, &b| ivs[a].0.cmp(&ivs[b].0));
+  maxsorted.sort_by(|&a, &b| ivs[a].1.cmp(&ivs[b].1));
+
+  // Sorted union of the lower bounds and the upper bounds plus one, `bounds[0]` and `bounds[nb+1]` are sentinels.
+  let mut minrank = vec![0; n];
+  let mut maxrank = vec![0; n];
+  let mut bounds = vec![];
+  let mut last = ivs[minsorted[0]].0 - two;
+  bounds.push(last);
+  let mut nb = 0;
+  let (mut i, mut j) = (0, 0);
+  let mut lb = ivs[minsorted[0]].0;
+  let mut ub = ivs[maxsorted[0]].1 + one;
+  loop {
+    if i < n && lb <= ub {
+      if lb != last {
+        nb += 1;
+        last = lb;
+        bounds.push(lb);
+      }
+      minrank[minsorted[i]] = nb;
+      i += 1;
+      if i < n { lb = ivs[minsorted[i]].0; }
+    }
+    else {
+      if ub != last {
+        nb += 1;
+        last = ub;
+        bounds.push(ub);
+      }
+      maxrank[maxsorted[j]] = nb;
+      j += 1;
+      if j == n { break; }
+      ub = ivs[maxsorted[j]].1 + one;
+    }
+  }
+  let sentinel = bounds[nb] + two;
+  bounds.push(sentinel);
+
+  let mut t = vec![0; nb + 2];
+  let mut h = vec![0; nb + 2];
+  let mut d = vec![B::zero(); nb + 2];
+
+  // Lower bounds.
+  for i in 1..nb+2 {
+    t[i] = i - 1;
+    h[i] = i - 1;
+    d[i] = bounds[i] - bounds[i-1];
+  }
+  for &v in &maxsorted {
+    let x = minrank[v];
+    let y = maxrank[v];
+    let mut z = path_max(&t, x + 1);
+    let j = t[z];
+    d[z] = d[z] - one;
+    if d[z] == B::zero() {
+      t[z] = z + 1;
+      z = path_max(&t, t[z]);
+      t[z] = j;
+    }
+    path_set(&mut t, x + 1, z, z);
+    if d[z] < bounds[z] - bounds[y] {
+      return None;
+    }
+    if h[x] > x {
+      let w = path_max(&h, h[x]);
+      ivs[v].0 = bounds[w];
+      path_set(&mut h, x, w, w);
+    }
+    if d[z] == bounds[z] - bounds[y] {
+      let hy = h[y];
+      path_set(&mut h, hy, j - 1, y);
+      h[y] = j - 1;
+    }
+  }
+
+  // Upper bounds.
+  for i in 0..nb+1 {
+    t[i] = i + 1;
+    h[i] = i + 1;
+    d[i] = bounds[i+1] - bounds[i];
+  }
+  for &v in minsorted.iter().rev() {
+    let x = maxrank[v];
+    let y = minrank[v];
+    let mut z = path_min(&t, x - 1);
+    let j = t[z];
+    d[z] = d[z] - one;
+    if d[z] == B::zero() {
+      t[z] = z - 1;
+      z = path_min(&t, t[z]);
+      t[z] = j;
+    }
+    path_set(&mut t, x - 1, z, z);
+    if d[z] < bounds[y] - bounds[z] {
+      return None;
+    }
+    if h[x] < x {
+      let w = path_min(&h, h[x]);
+      ivs[v].1 = bounds[w] - one;
+      path_set(&mut h, x, w, w);
+    }
+    if d[z] == bounds[y] - bounds[z] {
+      let hy = h[y];
+      path_set(&mut h, hy, j + 1, y);
+      h[y] = j + 1;
+    }
   }
+  Some(ivs)
+}
+
+fn path_set(t: &mut Vec<usize>, start: usize, end: usize, to: usize) {
+  let mut l = start;
+  while l != end {
+    let k = l;
+    l = t[k];
+    t[k] = to;
+  }
+}
+
+fn path_min(t: &Vec<usize>, mut i: usize) -> usize {
+  while t[i] < i { i = t[i]; }
+  i
+}
+
+fn path_max(t: &Vec<usize>, mut i: usize) -> usize {
+  while t[i] > i { i = t[i]; }
+  i
 }
 
 #[cfg(test)]
@@ -130,6 +355,51 @@ mod test {
     distinct_test_one(7, vec![dom0_3], True, True, vec![], true);
   }
 
+  #[test]
+  fn bounds_distinct_test() {
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let dom0_1 = (0,1).to_interval();
+    let dom0_2 = (0,2).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let dom1_2 = (1,2).to_interval();
+    let dom1_3 = (1,3).to_interval();
+
+    distinct_bounds_test_one(1, vec![zero,one,dom0_3], Unknown, True, vec![(2, Bound)], true);
+    distinct_bounds_test_one(2, vec![zero,one,dom0_1], Unknown, Unknown, vec![], false);
+    // [1..2] is a Hall interval of {x,y}, so z in [3..3].
+    distinct_bounds_test_one(3, vec![dom1_2,dom1_2,dom1_3], Unknown, Unknown, vec![(2, Assignment)], true);
+    // Three variables can not take distinct values in [0..1].
+    distinct_bounds_test_one(4, vec![dom0_1,dom0_1,dom0_1], Unknown, Unknown, vec![], false);
+    distinct_bounds_test_one(5, vec![dom0_2,dom0_2,dom0_2], Unknown, Unknown, vec![], true);
+    distinct_bounds_test_one(6, vec![dom0_1,dom0_1,dom0_3,dom0_3], Unknown, Unknown, vec![(2, Bound),(3, Bound)], true);
+  }
+
+  #[test]
+  fn exhaustive_consistency() {
+    for ivs in interval_tuples(&intervals(0, 3), 4) {
+      let expected = solutions_hull(&ivs, |w| (0..w.len()).all(|i| (0..i).all(|j| w[i] != w[j])));
+      assert_eq!(bounds_consistency(ivs.clone()), expected, "bounds consistency of {:?}", ivs);
+    }
+  }
+
+  #[test]
+  fn extreme_domains_consistency() {
+    let (min, max) = (i32::min_value(), i32::max_value());
+    let ivs = vec![(min, min + 1), (min, min + 1), (min, max), (max - 1, max), (max, max)];
+    let expected = Some(vec![(min, min + 1), (min, min + 1), (min + 2, max - 2), (max - 1, max - 1), (max, max)]);
+    assert_eq!(bounds_consistency(ivs), expected);
+    assert_eq!(bounds_consistency(vec![(max, max), (max - 1, max), (max - 1, max)]), None);
+  }
+
+  fn distinct_bounds_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| Distinct::with_consistency(vars, DistinctConsistency::Bounds),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
   fn distinct_test_one(test_num: u32, doms: Vec<Interval<i32>>,
     before: Trilean, after: Trilean,
     delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
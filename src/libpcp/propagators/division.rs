@@ -0,0 +1,61 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Integer divisions rounded towards negative or positive infinity, the division of `PrimInt` truncates towards zero. They are `None` if `b` is zero or if the quotient overflows (`MIN / -1`).
+
+use num::PrimInt;
+
+/// `a / b` rounded towards negative infinity.
+pub fn div_floor<B: PrimInt>(a: B, b: B) -> Option<B> {
+  a.checked_div(&b).map(|q| {
+    if q * b != a && ((a < B::zero()) != (b < B::zero())) { q - B::one() }
+    else { q }
+  })
+}
+
+/// `a / b` rounded towards positive infinity.
+pub fn div_ceil<B: PrimInt>(a: B, b: B) -> Option<B> {
+  a.checked_div(&b).map(|q| {
+    if q * b != a && ((a < B::zero()) == (b < B::zero())) { q + B::one() }
+    else { q }
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn division_rounding() {
+    assert_eq!(div_floor(7, 2), Some(3));
+    assert_eq!(div_floor(-7, 2), Some(-4));
+    assert_eq!(div_floor(7, -2), Some(-4));
+    assert_eq!(div_floor(-7, -2), Some(3));
+    assert_eq!(div_floor(6, -2), Some(-3));
+    assert_eq!(div_ceil(7, 2), Some(4));
+    assert_eq!(div_ceil(-7, 2), Some(-3));
+    assert_eq!(div_ceil(7, -2), Some(-3));
+    assert_eq!(div_ceil(-7, -2), Some(4));
+    assert_eq!(div_ceil(-6, 2), Some(-3));
+  }
+
+  #[test]
+  fn division_overflow() {
+    assert_eq!(div_floor(i32::min_value(), -1), None);
+    assert_eq!(div_ceil(i32::min_value(), -1), None);
+    assert_eq!(div_floor(i32::min_value(), 1), Some(i32::min_value()));
+    assert_eq!(div_ceil(i32::max_value(), -1), Some(-i32::max_value()));
+    assert_eq!(div_floor(1, 0), None);
+  }
+}
@@ -0,0 +1,408 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Element constraints `y = array[x]` where the index `x` is a variable, the indices start at 0. `Element` indexes an array of constants and `VarElement` an array of variables (or views).
+//!
+//! Both propagators are bounds consistent: the bounds of `x` are moved to the first and last indices whose element can be equal to `y`, and `y` is reduced to the hull of these elements.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::cmp::XEqY;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+// Indices of the array in the domain of `x`, empty if there are none.
+fn index_range<Domain, BX>(x: &Domain, len: usize) -> (usize, usize) where
+  Domain: Bounded<Bound=BX>,
+  BX: PrimInt
+{
+  let lb = if x.lower() < BX::zero() { 0 } else { x.lower().to_usize().unwrap_or(len) };
+  let ub = if x.upper() < BX::zero() { 0 } else { min(x.upper().to_usize().unwrap_or(len), len.saturating_sub(1)) };
+  if x.upper() < BX::zero() || lb >= len { (1, 0) }
+  else { (lb, ub) }
+}
+
+fn update_index<Store, Domain, X, BX>(x: &X, store: &mut Store, first: usize, last: usize) -> bool where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=BX> + ShrinkLeft<BX> + ShrinkRight<BX>,
+  BX: PrimInt
+{
+  let dom = x.read(store);
+  x.update(store, dom
+    .shrink_left(BX::from(first).unwrap())
+    .shrink_right(BX::from(last).unwrap()))
+}
+
+#[derive(Clone)]
+pub struct Element<X, Y, B>
+{
+  array: Vec<B>,
+  x: X,
+  y: Y
+}
+
+impl<X, Y, B> PropagatorKind for Element<X, Y, B> {}
+
+impl<X, Y, B> Element<X, Y, B> {
+  /// Constraint `y = array[x]`.
+  pub fn new(array: Vec<B>, x: X, y: Y) -> Element<X, Y, B> {
+    Element {
+      array: array,
+      x: x,
+      y: y
+    }
+  }
+}
+
+impl<X, Y, B> Debug for Element<X, Y, B> where
+  X: Debug,
+  Y: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} = {:?}[{:?}]", self.y, self.array, self.x))
+  }
+}
+
+impl<Store, DomX, DomY, BX, X, Y, B> Subsumption<Store> for Element<X, Y, B> where
+  X: StoreRead<Store, Value=DomX>,
+  Y: StoreRead<Store, Value=DomY>,
+  DomX: Bounded<Bound=BX>,
+  DomY: Bounded<Bound=B>,
+  BX: PrimInt,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let x = self.x.read(store);
+    let y = self.y.read(store);
+    let (first, last) = index_range(&x, self.array.len());
+    if first > last {
+      return False;
+    }
+    let elements = &self.array[first..last+1];
+    if elements.iter().all(|&e| e < y.lower() || e > y.upper()) {
+      False
+    }
+    else if x.lower() >= BX::zero() && x.upper() <= BX::from(last).unwrap()
+      && y.lower() == y.upper() && elements.iter().all(|&e| e == y.lower())
+    {
+      True
+    }
+    else {
+      Unknown
+    }
+  }
+}
+
+impl<Store, DomX, DomY, BX, X, Y, B> Propagator<Store> for Element<X, Y, B> where
+  X: StoreRead<Store, Value=DomX> + StoreMonotonicUpdate<Store, DomX>,
+  Y: StoreRead<Store, Value=DomY> + StoreMonotonicUpdate<Store, DomY>,
+  DomX: Bounded<Bound=BX> + ShrinkLeft<BX> + ShrinkRight<BX>,
+  DomY: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  BX: PrimInt,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let x = self.x.read(store);
+    let y = self.y.read(store);
+    let (first, last) = index_range(&x, self.array.len());
+    let supported = |i: &usize| self.array[*i] >= y.lower() && self.array[*i] <= y.upper();
+    let first = (first..last+1).find(&supported);
+    let last = (first.unwrap_or(1)..last+1).rev().find(&supported);
+    match (first, last) {
+      (Some(first), Some(last)) => {
+        let elements = &self.array[first..last+1];
+        let lb = elements.iter().cloned().filter(|&e| e >= y.lower()).min().unwrap();
+        let ub = elements.iter().cloned().filter(|&e| e <= y.upper()).max().unwrap();
+        update_index(&self.x, store, first, last) &&
+        self.y.update(store, y.shrink_left(lb).shrink_right(ub))
+      }
+      _ => false
+    }
+  }
+}
+
+impl<X, Y, B> PropagatorDependencies<FDEvent> for Element<X, Y, B> where
+  X: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.x.dependencies(FDEvent::Bound);
+    deps.append(&mut self.y.dependencies(FDEvent::Bound));
+    deps
+  }
+}
+
+#[derive(Clone)]
+pub struct VarElement<V, X, Y>
+{
+  array: Vec<V>,
+  x: X,
+  y: Y
+}
+
+impl<V, X, Y> PropagatorKind for VarElement<V, X, Y> {}
+
+impl<V, X, Y> VarElement<V, X, Y> {
+  /// Constraint `y = array[x]`, `x` and `y` must not appear in `array`.
+  pub fn new(array: Vec<V>, x: X, y: Y) -> VarElement<V, X, Y> {
+    VarElement {
+      array: array,
+      x: x,
+      y: y
+    }
+  }
+}
+
+impl<V, X, Y> Debug for VarElement<V, X, Y> where
+  V: Debug,
+  X: Debug,
+  Y: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} = {:?}[{:?}]", self.y, self.array, self.x))
+  }
+}
+
+impl<Store, DomX, Domain, BX, B, V, X, Y> Subsumption<Store> for VarElement<V, X, Y> where
+  V: StoreRead<Store, Value=Domain>,
+  X: StoreRead<Store, Value=DomX>,
+  Y: StoreRead<Store, Value=Domain>,
+  DomX: Bounded<Bound=BX>,
+  Domain: Bounded<Bound=B>,
+  BX: PrimInt,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let x = self.x.read(store);
+    let y = self.y.read(store);
+    let (first, last) = index_range(&x, self.array.len());
+    if first > last {
+      return False;
+    }
+    let elements: Vec<_> = self.array[first..last+1].iter()
+      .map(|v| v.read(store))
+      .collect();
+    if elements.iter().all(|e| e.upper() < y.lower() || e.lower() > y.upper()) {
+      False
+    }
+    else if x.lower() >= BX::zero() && x.upper() <= BX::from(last).unwrap()
+      && y.lower() == y.upper()
+      && elements.iter().all(|e| e.lower() == y.lower() && e.upper() == y.lower())
+    {
+      True
+    }
+    else {
+      Unknown
+    }
+  }
+}
+
+impl<Store, DomX, Domain, BX, B, V, X, Y> Propagator<Store> for VarElement<V, X, Y> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  X: StoreRead<Store, Value=DomX> + StoreMonotonicUpdate<Store, DomX>,
+  Y: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  DomX: Bounded<Bound=BX> + ShrinkLeft<BX> + ShrinkRight<BX>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  BX: PrimInt,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let x = self.x.read(store);
+    let y = self.y.read(store);
+    let (first, last) = index_range(&x, self.array.len());
+    let mut support = None;
+    let mut hull: Option<(B, B)> = None;
+    if first <= last {
+      for i in first..last+1 {
+        let e = self.array[i].read(store);
+        if e.upper() >= y.lower() && e.lower() <= y.upper() {
+          support = Some(match support {
+            None => (i, i),
+            Some((first, _)) => (first, i)
+          });
+          hull = Some(match hull {
+            None => (e.lower(), e.upper()),
+            Some((lb, ub)) => (min(lb, e.lower()), max(ub, e.upper()))
+          });
+        }
+      }
+    }
+    match (support, hull) {
+      (Some((first, last)), Some((lb, ub))) => {
+        if !update_index(&self.x, store, first, last) ||
+           !self.y.update(store, y.shrink_left(lb).shrink_right(ub)) {
+          return false;
+        }
+        // When the index is fixed, `y = array[x]` is an equality between two variables.
+        if first == last {
+          let y = self.y.read(store);
+          let e = self.array[first].read(store);
+          self.array[first].update(store, e.shrink_left(y.lower()).shrink_right(y.upper()))
+        }
+        else { true }
+      }
+      _ => false
+    }
+  }
+}
+
+impl<V, X, Y> PropagatorDependencies<FDEvent> for VarElement<V, X, Y> where
+  V: ViewDependencies<FDEvent>,
+  X: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.x.dependencies(FDEvent::Bound);
+    deps.append(&mut self.y.dependencies(FDEvent::Bound));
+    for v in &self.array {
+      deps.append(&mut v.dependencies(FDEvent::Bound));
+    }
+    deps
+  }
+}
+
+/// Index of an array in `y = array[index]`, it is used by the `pcp!` macro to generate the right constraint whether the index is an `usize` value or a view.
+pub trait ElementIndex<V, Y> {
+  type Constraint;
+  fn element(self, array: &Vec<V>, y: Y) -> Self::Constraint;
+}
+
+impl<V, Y> ElementIndex<V, Y> for usize where
+  V: Clone
+{
+  type Constraint = XEqY<V, Y>;
+  fn element(self, array: &Vec<V>, y: Y) -> XEqY<V, Y> {
+    XEqY::new(array[self].clone(), y)
+  }
+}
+
+impl<V, Y, X> ElementIndex<V, Y> for X where
+  V: Clone,
+  X: ViewDependencies<FDEvent>
+{
+  type Constraint = VarElement<V, X, Y>;
+  fn element(self, array: &Vec<V>, y: Y) -> VarElement<V, X, Y> {
+    VarElement::new(array.clone(), self, y)
+  }
+}
+
+/// Constraint `y = array[index]`, the index is either an `usize` value or a view. The array is only copied when the index is a view.
+pub fn element<I, V, Y>(array: &Vec<V>, index: I, y: Y) -> I::Constraint where
+  I: ElementIndex<V, Y>
+{
+  index.element(array, y)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use interval::interval::*;
+  use term::addition::Addition;
+  use propagators::test::*;
+
+  #[test]
+  fn element_test() {
+    let table = vec![5, 2, 8, 2, 7];
+    let dom0_4 = (0,4).to_interval();
+    let dom_neg2_10 = (-2,10).to_interval();
+    let dom1_3 = (1,3).to_interval();
+    let dom2_3 = (2,3).to_interval();
+    let dom3_6 = (3,6).to_interval();
+    let dom9_9 = (9,9).to_interval();
+    let two = (2,2).to_interval();
+    let three = (3,3).to_interval();
+
+    element_test_one(1, table.clone(), dom0_4, dom_neg2_10, Unknown, Unknown, vec![(1, Bound)], true);
+    element_test_one(2, table.clone(), dom_neg2_10, dom0_4, Unknown, Unknown, vec![(0, Bound), (1, Assignment)], true);
+    element_test_one(3, table.clone(), dom0_4, two, Unknown, Unknown, vec![(0, Bound)], true);
+    element_test_one(4, table.clone(), dom2_3, dom3_6, False, False, vec![], false);
+    element_test_one(5, table.clone(), dom0_4, dom3_6, Unknown, True, vec![(0, Assignment), (1, Assignment)], true);
+    element_test_one(6, table.clone(), three, dom0_4, Unknown, True, vec![(1, Assignment)], true);
+    element_test_one(7, table.clone(), dom1_3, two, Unknown, Unknown, vec![], true);
+    element_test_one(8, table.clone(), dom0_4, dom9_9, False, False, vec![], false);
+  }
+
+  fn element_test_one(test_num: u32, table: Vec<i32>, x: Interval<i32>, y: Interval<i32>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    binary_propagator_test(test_num, |x, y| Element::new(table, x, y), x, y,
+      before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn var_element_test() {
+    let dom0_1 = (0,1).to_interval();
+    let dom0_2 = (0,2).to_interval();
+    let dom0_10 = (0,10).to_interval();
+    let dom1_3 = (1,3).to_interval();
+    let dom4_6 = (4,6).to_interval();
+    let dom5_9 = (5,9).to_interval();
+    let dom7_8 = (7,8).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+
+    // Variables: [x, y, a0, a1, a2] for `y = [a0, a1, a2][x]`.
+    var_element_test_one(1, vec![dom0_10, dom0_10, dom1_3, dom4_6, dom7_8],
+      Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    var_element_test_one(2, vec![dom0_2, dom5_9, dom1_3, dom4_6, dom7_8],
+      Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    var_element_test_one(3, vec![one, dom5_9, dom1_3, dom4_6, dom7_8],
+      Unknown, Unknown, vec![(1, Bound), (3, Bound)], true);
+    var_element_test_one(4, vec![dom0_1, dom7_8, dom1_3, dom4_6, dom7_8],
+      False, False, vec![], false);
+    var_element_test_one(5, vec![two, dom0_10, dom1_3, dom4_6, two],
+      Unknown, True, vec![(1, Assignment)], true);
+  }
+
+  #[test]
+  fn element_index() {
+    let dom0_1 = (0,1).to_interval();
+    let dom1_3 = (1,3).to_interval();
+    let dom4_6 = (4,6).to_interval();
+    let dom0_10 = (0,10).to_interval();
+    // With a constant index, `y = [a0, a1][1]` is `y = a1`.
+    nary_propagator_test(1,
+      |vars| element(&vars[1..3].to_vec(), 1usize, vars[0].clone()),
+      vec![dom0_10, dom1_3, dom4_6], Unknown, Unknown, vec![(0, Bound)], true);
+    nary_propagator_test(2,
+      |vars| element(&vars[2..4].to_vec(), vars[0].clone(), vars[1].clone()),
+      vec![dom0_1, dom0_10, dom1_3, dom4_6], Unknown, Unknown, vec![(1, Bound)], true);
+    // The index `x - 1` is a view, `x` in [1..3] is reduced to [1..2].
+    nary_propagator_test(3,
+      |vars| element(&vars[2..4].to_vec(), Addition::new(vars[0].clone(), -1), vars[1].clone()),
+      vec![dom1_3, dom0_10, dom1_3, dom4_6], Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+  }
+
+  fn var_element_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars| VarElement::new(vars[2..].to_vec(), vars[0].clone(), vars[1].clone()),
+      doms, before, after, delta_expected, propagate_success);
+  }
+}
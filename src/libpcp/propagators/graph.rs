@@ -0,0 +1,249 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Graph algorithms shared by the propagators, the graphs are given by the successors of each node.
+
+use num::PrimInt;
+use std::cmp::min;
+
+/// Tarjan's algorithm, returns the index of the component of each node. The depth-first search is iterative so large graphs do not overflow the stack.
+pub fn strongly_connected_components(succ: &Vec<Vec<usize>>) -> Vec<usize> {
+  let nodes = succ.len();
+  let mut index: Vec<Option<usize>> = vec![None; nodes];
+  let mut low = vec![0; nodes];
+  let mut on_stack = vec![false; nodes];
+  let mut stack = vec![];
+  let mut component = vec![0; nodes];
+  let mut next_index = 0;
+  let mut next_component = 0;
+  // The depth-first search stack, each node is paired with the position of its next successor to visit.
+  let mut visits: Vec<(usize, usize)> = vec![];
+  for root in 0..nodes {
+    if index[root].is_some() {
+      continue;
+    }
+    visits.push((root, 0));
+    while let Some(&(a, i)) = visits.last() {
+      if i == 0 && index[a].is_none() {
+        index[a] = Some(next_index);
+        low[a] = next_index;
+        next_index += 1;
+        stack.push(a);
+        on_stack[a] = true;
+      }
+      if i < succ[a].len() {
+        visits.last_mut().unwrap().1 += 1;
+        let b = succ[a][i];
+        match index[b] {
+          None => visits.push((b, 0)),
+          Some(index) if on_stack[b] => low[a] = min(low[a], index),
+          _ => ()
+        }
+      }
+      else {
+        visits.pop();
+        if Some(low[a]) == index[a] {
+          loop {
+            let b = stack.pop().unwrap();
+            on_stack[b] = false;
+            component[b] = next_component;
+            if a == b { break; }
+          }
+          next_component += 1;
+        }
+        if let Some(&(parent, _)) = visits.last() {
+          low[parent] = min(low[parent], low[a]);
+        }
+      }
+    }
+  }
+  component
+}
+
+// Flow between the variables `0..n` and the segments of values `0..m`, every variable is matched to one segment.
+struct Flow {
+  domains: Vec<(usize, usize)>,
+  var_match: Vec<Option<usize>>,
+  val_vars: Vec<Vec<usize>>,
+  low: Vec<usize>,
+  up: Vec<usize>
+}
+
+impl Flow {
+  fn assign(&mut self, x: usize, v: usize) {
+    if let Some(w) = self.var_match[x] {
+      self.val_vars[w].retain(|&y| y != x);
+    }
+    self.var_match[x] = Some(v);
+    self.val_vars[v].push(x);
+  }
+
+  // Increases the flow of the value `v` by one, the flow of the other values is unchanged.
+  fn augment_value(&mut self, v: usize, visited_vars: &mut Vec<bool>, visited_vals: &mut Vec<bool>) -> bool {
+    visited_vals[v] = true;
+    for x in 0..self.domains.len() {
+      let (l, u) = self.domains[x];
+      if visited_vars[x] || v < l || v > u || self.var_match[x] == Some(v) {
+        continue;
+      }
+      visited_vars[x] = true;
+      let reassigned = match self.var_match[x] {
+        None => true,
+        Some(w) => !visited_vals[w] && self.augment_value(w, visited_vars, visited_vals)
+      };
+      if reassigned {
+        self.assign(x, v);
+        return true;
+      }
+    }
+    false
+  }
+
+  // Matches the free variable `x`, the flow of the values can only increase.
+  fn augment_var(&mut self, x: usize, visited: &mut Vec<bool>) -> bool {
+    let (l, u) = self.domains[x];
+    for v in l..u+1 {
+      if visited[v] { continue; }
+      visited[v] = true;
+      if self.val_vars[v].len() < self.up[v] {
+        self.assign(x, v);
+        return true;
+      }
+      for y in self.val_vars[v].clone() {
+        if self.augment_var(y, visited) {
+          self.assign(x, v);
+          return true;
+        }
+      }
+    }
+    false
+  }
+}
+
+/// Computes a flow between the variables and the values, where the value `cards[i].0` receives between `cards[i].1` and `cards[i].2` variables and any other value at most `capacity` variables. A feasible flow is computed by first satisfying the lower cardinalities and then matching the remaining variables. A value is supported by a variable if the edge between them belongs to the flow or to a cycle of the residual graph (Régin, 1996). Returns the hull of the supported values of each variable, or `None` if there is no feasible flow.
+///
+/// The values are grouped into segments delimited by the bounds of the domains and the values of `cards`: the values of a segment belong to the same domains and have the same cardinalities, hence they are interchangeable. A segment receives at most `n` variables so the size of the graph only depends on `n` and `cards`, whatever the width of the domains.
+pub fn flow_consistency<B: PrimInt>(ivs: Vec<(B, B)>, cards: &[(B, usize, usize)], capacity: usize) -> Option<Vec<(B, B)>> {
+  let n = ivs.len();
+  if n == 0 {
+    return if cards.iter().all(|c| c.1 == 0) { Some(ivs) } else { None };
+  }
+  let lb = ivs.iter().map(|iv| iv.0).min().unwrap();
+  let ub = ivs.iter().map(|iv| iv.1).max().unwrap();
+  let mut starts = vec![];
+  for &(l, u) in &ivs {
+    starts.push(l);
+    if u < ub { starts.push(u + B::one()); }
+  }
+  for &(value, _, _) in cards {
+    if lb <= value && value <= ub {
+      starts.push(value);
+      if value < ub { starts.push(value + B::one()); }
+    }
+  }
+  starts.sort();
+  starts.dedup();
+  let m = starts.len();
+  let first_value = |v: usize| starts[v];
+  let last_value = |v: usize| if v + 1 < m { starts[v + 1] - B::one() } else { ub };
+  let index_of = |b: B| match starts.binary_search(&b) { Ok(v) => v, Err(v) => v - 1 };
+  let size = |v: usize| last_value(v).checked_sub(&first_value(v))
+    .and_then(|s| s.to_usize())
+    .and_then(|s| s.checked_add(1))
+    .unwrap_or(n);
+  let mut low = vec![0; m];
+  let mut up: Vec<usize> = (0..m).map(|v| min(n, capacity.saturating_mul(size(v)))).collect();
+  for &(value, l, u) in cards {
+    if value < lb || value > ub {
+      if l > 0 { return None; }
+    }
+    else {
+      let v = index_of(value);
+      low[v] = l;
+      up[v] = u;
+    }
+  }
+  let mut flow = Flow {
+    domains: ivs.iter().map(|&(l, u)| (index_of(l), index_of(u))).collect(),
+    var_match: vec![None; n],
+    val_vars: vec![vec![]; m],
+    low: low,
+    up: up
+  };
+
+  for v in 0..m {
+    while flow.val_vars[v].len() < flow.low[v] {
+      if !flow.augment_value(v, &mut vec![false; n], &mut vec![false; m]) {
+        return None;
+      }
+    }
+  }
+  for x in 0..n {
+    if flow.var_match[x].is_none() && !flow.augment_var(x, &mut vec![false; m]) {
+      return None;
+    }
+  }
+
+  // Residual graph: the node `n+m` is the sink, a value can send flow to the sink if it is below its upper cardinality and receive flow from it if it is above its lower cardinality.
+  let sink = n + m;
+  let mut succ: Vec<Vec<usize>> = vec![vec![]; n + m + 1];
+  for x in 0..n {
+    let (l, u) = flow.domains[x];
+    for v in l..u+1 {
+      if flow.var_match[x] == Some(v) { succ[n + v].push(x); }
+      else { succ[x].push(n + v); }
+    }
+  }
+  for v in 0..m {
+    let f = flow.val_vars[v].len();
+    if f < flow.up[v] { succ[n + v].push(sink); }
+    if f > flow.low[v] { succ[sink].push(n + v); }
+  }
+  let component = strongly_connected_components(&succ);
+
+  let supported = |x: usize, v: usize| flow.var_match[x] == Some(v) || component[x] == component[n + v];
+  let mut res = vec![];
+  for x in 0..n {
+    let (l, u) = flow.domains[x];
+    let new_l = (l..u+1).find(|&v| supported(x, v)).unwrap();
+    let new_u = (l..u+1).rev().find(|&v| supported(x, v)).unwrap();
+    res.push((first_value(new_l), last_value(new_u)));
+  }
+  Some(res)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn components() {
+    // 0 -> 1 -> 2 -> 0 and 2 -> 3 -> 4 -> 3, 5 is isolated.
+    let succ = vec![vec![1], vec![2], vec![0, 3], vec![4], vec![3], vec![]];
+    let component = strongly_connected_components(&succ);
+    assert!(component[0] == component[1] && component[1] == component[2]);
+    assert_eq!(component[3], component[4]);
+    assert!(component[0] != component[3]);
+    assert!(component[5] != component[0] && component[5] != component[3]);
+  }
+
+  #[test]
+  fn long_path() {
+    // A cycle of one million nodes would overflow the stack of a recursive implementation.
+    let n = 1000000;
+    let succ: Vec<Vec<usize>> = (0..n).map(|a| vec![(a + 1) % n]).collect();
+    let component = strongly_connected_components(&succ);
+    assert!(component.iter().all(|&c| c == component[0]));
+  }
+}
@@ -0,0 +1,452 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lexicographic ordering between vectors of variables, mostly used to break the symmetries of matrix models.
+//!
+//! `LexLess` and `LexLessEq` enforce generalized arc consistency on `xs <lex ys` and `xs <=lex ys` (Frisch et al., "Global constraints for lexicographic orderings", CP 2002). The filtering only happens at the first position `i` where `xs[i]` and `ys[i]` are not fixed to the same value: `xs[i] <= ys[i]`, and `xs[i] < ys[i]` if the suffixes starting at `i+1` can not be ordered. `LexChain` orders a sequence of vectors by propagating the ordering of each pair of consecutive vectors until a fixed point is reached.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::bounds::{read_bounds, write_bounds};
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::cmp::{min, max};
+use std::fmt::{Formatter, Debug, Error};
+
+// Reduces the bounds of `xs` and `ys` such that `xs <lex ys` (or `xs <=lex ys` if not `strict`), `None` if the ordering is not satisfiable.
+fn lex_consistency<B: PrimInt>(mut xs: Vec<(B, B)>, mut ys: Vec<(B, B)>, strict: bool)
+  -> Option<(Vec<(B, B)>, Vec<(B, B)>)>
+{
+  assert_eq!(xs.len(), ys.len(), "Lexicographic ordering between vectors of different lengths.");
+  let n = xs.len();
+  // `feasible[i]` is true if `xs[i..]` can be ordered with `ys[i..]`, the smallest suffix of `xs` is compared to the greatest one of `ys`.
+  let mut feasible = vec![!strict; n + 1];
+  for i in (0..n).rev() {
+    feasible[i] =
+      if xs[i].0 < ys[i].1 { true }
+      else if xs[i].0 > ys[i].1 { false }
+      else { feasible[i + 1] };
+  }
+  if !feasible[0] {
+    return None;
+  }
+  for i in 0..n {
+    if xs[i].0 == xs[i].1 && xs[i] == ys[i] {
+      continue;
+    }
+    let gap = if feasible[i + 1] { B::zero() } else { B::one() };
+    xs[i].1 = min(xs[i].1, ys[i].1 - gap);
+    ys[i].0 = max(ys[i].0, xs[i].0 + gap);
+    // The ordering is decided at `i` unless `xs[i]` and `ys[i]` are now fixed to the same value.
+    if xs[i].0 != xs[i].1 || xs[i] != ys[i] {
+      break;
+    }
+  }
+  Some((xs, ys))
+}
+
+fn lex_subsumption<B: PrimInt>(xs: &Vec<(B, B)>, ys: &Vec<(B, B)>, strict: bool) -> Trilean {
+  // Compares the greatest vector of `xs` to the smallest one of `ys`.
+  let entailed = xs.iter().zip(ys.iter())
+    .find(|&(x, y)| x.1 != y.0)
+    .map_or(!strict, |(x, y)| x.1 < y.0);
+  if entailed { True }
+  else if lex_consistency(xs.clone(), ys.clone(), strict).is_none() { False }
+  else { Unknown }
+}
+
+fn lex_propagate<Store, Domain, B, X, Y>(xs: &Vec<X>, ys: &Vec<Y>, store: &mut Store, strict: bool) -> bool where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  let x_bounds = read_bounds(xs, store);
+  let y_bounds = read_bounds(ys, store);
+  match lex_consistency(x_bounds, y_bounds, strict) {
+    Some((x_bounds, y_bounds)) =>
+      write_bounds(xs, store, x_bounds) && write_bounds(ys, store, y_bounds),
+    None => false
+  }
+}
+
+/// `xs <lex ys`.
+#[derive(Clone)]
+pub struct LexLess<X, Y>
+{
+  xs: Vec<X>,
+  ys: Vec<Y>
+}
+
+impl<X, Y> PropagatorKind for LexLess<X, Y> {}
+
+impl<X, Y> LexLess<X, Y> {
+  pub fn new(xs: Vec<X>, ys: Vec<Y>) -> LexLess<X, Y> {
+    assert_eq!(xs.len(), ys.len(), "Lexicographic ordering between vectors of different lengths.");
+    LexLess { xs: xs, ys: ys }
+  }
+}
+
+impl<X, Y> Debug for LexLess<X, Y> where
+  X: Debug,
+  Y: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} <lex {:?}", self.xs, self.ys))
+  }
+}
+
+impl<Store, Domain, B, X, Y> Subsumption<Store> for LexLess<X, Y> where
+  X: StoreRead<Store, Value=Domain>,
+  Y: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    lex_subsumption(&read_bounds(&self.xs, store), &read_bounds(&self.ys, store), true)
+  }
+}
+
+impl<Store, Domain, B, X, Y> Propagator<Store> for LexLess<X, Y> where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    lex_propagate(&self.xs, &self.ys, store, true)
+  }
+}
+
+impl<X, Y> PropagatorDependencies<FDEvent> for LexLess<X, Y> where
+  X: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.xs.iter().flat_map(|x| x.dependencies(FDEvent::Bound))
+      .chain(self.ys.iter().flat_map(|y| y.dependencies(FDEvent::Bound)))
+      .collect()
+  }
+}
+
+/// `xs <=lex ys`.
+#[derive(Clone)]
+pub struct LexLessEq<X, Y>
+{
+  xs: Vec<X>,
+  ys: Vec<Y>
+}
+
+impl<X, Y> PropagatorKind for LexLessEq<X, Y> {}
+
+impl<X, Y> LexLessEq<X, Y> {
+  pub fn new(xs: Vec<X>, ys: Vec<Y>) -> LexLessEq<X, Y> {
+    assert_eq!(xs.len(), ys.len(), "Lexicographic ordering between vectors of different lengths.");
+    LexLessEq { xs: xs, ys: ys }
+  }
+}
+
+impl<X, Y> Debug for LexLessEq<X, Y> where
+  X: Debug,
+  Y: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} <=lex {:?}", self.xs, self.ys))
+  }
+}
+
+impl<Store, Domain, B, X, Y> Subsumption<Store> for LexLessEq<X, Y> where
+  X: StoreRead<Store, Value=Domain>,
+  Y: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    lex_subsumption(&read_bounds(&self.xs, store), &read_bounds(&self.ys, store), false)
+  }
+}
+
+impl<Store, Domain, B, X, Y> Propagator<Store> for LexLessEq<X, Y> where
+  X: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Y: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    lex_propagate(&self.xs, &self.ys, store, false)
+  }
+}
+
+impl<X, Y> PropagatorDependencies<FDEvent> for LexLessEq<X, Y> where
+  X: ViewDependencies<FDEvent>,
+  Y: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.xs.iter().flat_map(|x| x.dependencies(FDEvent::Bound))
+      .chain(self.ys.iter().flat_map(|y| y.dependencies(FDEvent::Bound)))
+      .collect()
+  }
+}
+
+/// `vectors[0] <lex vectors[1] <lex ...` (or `<=lex`).
+#[derive(Clone)]
+pub struct LexChain<V>
+{
+  vectors: Vec<Vec<V>>,
+  strict: bool
+}
+
+impl<V> PropagatorKind for LexChain<V> {}
+
+impl<V> LexChain<V> {
+  pub fn new(vectors: Vec<Vec<V>>, strict: bool) -> LexChain<V> {
+    assert!(vectors.windows(2).all(|w| w[0].len() == w[1].len()),
+      "Lexicographic ordering between vectors of different lengths.");
+    LexChain { vectors: vectors, strict: strict }
+  }
+
+  pub fn less(vectors: Vec<Vec<V>>) -> LexChain<V> {
+    LexChain::new(vectors, true)
+  }
+
+  pub fn less_eq(vectors: Vec<Vec<V>>) -> LexChain<V> {
+    LexChain::new(vectors, false)
+  }
+}
+
+impl<V> Debug for LexChain<V> where
+  V: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    let op = if self.strict { "<lex" } else { "<=lex" };
+    try!(formatter.write_fmt(format_args!("lex_chain(")));
+    for (i, v) in self.vectors.iter().enumerate() {
+      if i > 0 {
+        try!(formatter.write_fmt(format_args!(" {} ", op)));
+      }
+      try!(formatter.write_fmt(format_args!("{:?}", v)));
+    }
+    formatter.write_fmt(format_args!(")"))
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for LexChain<V> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let bounds: Vec<_> = self.vectors.iter().map(|v| read_bounds(v, store)).collect();
+    let mut all_entailed = true;
+    for w in bounds.windows(2) {
+      match lex_subsumption(&w[0], &w[1], self.strict) {
+        False => return False,
+        Unknown => all_entailed = false,
+        _ => ()
+      }
+    }
+    if all_entailed { True }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for LexChain<V> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let mut bounds: Vec<_> = self.vectors.iter().map(|v| read_bounds(v, store)).collect();
+    let mut changed = true;
+    while changed {
+      changed = false;
+      for i in 1..bounds.len() {
+        match lex_consistency(bounds[i-1].clone(), bounds[i].clone(), self.strict) {
+          Some((xs, ys)) => {
+            changed = changed || xs != bounds[i-1] || ys != bounds[i];
+            bounds[i-1] = xs;
+            bounds[i] = ys;
+          }
+          None => return false
+        }
+      }
+    }
+    self.vectors.iter().zip(bounds.into_iter())
+      .all(|(v, b)| write_bounds(v, store, b))
+  }
+}
+
+impl<V> PropagatorDependencies<FDEvent> for LexChain<V> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.vectors.iter()
+      .flat_map(|v| v.iter().flat_map(|x| x.dependencies(FDEvent::Bound)))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::events::FDEvent::*;
+  use propagation::store::Store;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::linear::*;
+  use propagators::test::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use term::*;
+  use search::space::*;
+  use search::solutions::*;
+  use search::propagation::*;
+  use search::engine::one_solution::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use gcollections::VectorStack;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  #[test]
+  fn lex_less_eq_test() {
+    let dom0_1 = (0,1).to_interval();
+    let dom0_2 = (0,2).to_interval();
+    let dom1_2 = (1,2).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+
+    lex_less_eq_test_one(1, vec![dom0_2, dom0_2], vec![dom0_2, dom0_2], Unknown, Unknown, vec![], true);
+    lex_less_eq_test_one(2, vec![dom1_2, dom0_2], vec![dom0_1, dom0_2], Unknown, Unknown, vec![(0, Assignment), (2, Assignment)], true);
+    lex_less_eq_test_one(3, vec![one, two], vec![one, dom0_2], Unknown, True, vec![(3, Assignment)], true);
+    lex_less_eq_test_one(4, vec![one, one], vec![one, one], True, True, vec![], true);
+    lex_less_eq_test_one(5, vec![two, dom0_2], vec![dom0_1, dom0_2], False, False, vec![], false);
+  }
+
+  fn lex_less_eq_test_one(test_num: u32, xs: Vec<Interval<i32>>, ys: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    let n = xs.len();
+    nary_propagator_test(test_num, |vars: Vec<FDVar>| LexLessEq::new(vars[..n].to_vec(), vars[n..].to_vec()),
+      xs.into_iter().chain(ys.into_iter()).collect(), before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn lex_less_test() {
+    let dom0_2 = (0,2).to_interval();
+    let dom1_2 = (1,2).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+
+    lex_less_test_one(1, vec![dom0_2, dom0_2], vec![dom0_2, dom0_2], Unknown, Unknown, vec![], true);
+    // `xs[1] = 2` can not be smaller than `ys[1]` so `ys[0]` must be greater than `xs[0]`.
+    lex_less_test_one(2, vec![one, two], vec![dom1_2, dom0_2], Unknown, True, vec![(2, Assignment)], true);
+    lex_less_test_one(3, vec![one, dom0_2], vec![one, dom0_2], Unknown, Unknown, vec![(1, Bound), (3, Bound)], true);
+    lex_less_test_one(4, vec![one, one], vec![one, one], False, False, vec![], false);
+  }
+
+  fn lex_less_test_one(test_num: u32, xs: Vec<Interval<i32>>, ys: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    let n = xs.len();
+    nary_propagator_test(test_num, |vars: Vec<FDVar>| LexLess::new(vars[..n].to_vec(), vars[n..].to_vec()),
+      xs.into_iter().chain(ys.into_iter()).collect(), before, after, delta_expected, propagate_success);
+  }
+
+  fn lex_le(xs: &[i32], ys: &[i32], strict: bool) -> bool {
+    if strict { xs < ys } else { xs <= ys }
+  }
+
+  #[test]
+  fn exhaustive_lex_consistency() {
+    for &(n, ub) in &[(2, 2), (3, 1)] {
+      for doms in interval_tuples(&intervals(0, ub), 2 * n) {
+        for &strict in &[true, false] {
+          let expected = solutions_hull(&doms, |t| lex_le(&t[..n], &t[n..], strict));
+          let result = lex_consistency(doms[..n].to_vec(), doms[n..].to_vec(), strict)
+            .map(|(mut xs, ys)| { xs.extend(ys); xs });
+          assert_eq!(result, expected, "{:?} (strict: {})", doms, strict);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn exhaustive_lex_chain() {
+    for doms in interval_tuples(&intervals(0, 1), 6) {
+      for &strict in &[true, false] {
+        let expected = solutions_hull(&doms, |t| lex_le(&t[0..2], &t[2..4], strict) && lex_le(&t[2..4], &t[4..6], strict));
+        let result = fixpoint(|vars| LexChain::new(vec![vars[0..2].to_vec(), vars[2..4].to_vec(), vars[4..6].to_vec()], strict), &doms);
+        check_soundness(&doms, &result, &expected);
+      }
+    }
+  }
+
+  fn count_solutions(space: FDSpace) -> usize {
+    let search: OneSolution<_, VectorStack<_>, FDSpace> =
+      OneSolution::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    Solutions::new(search, space).count()
+  }
+
+  fn nqueens(n: usize, break_symmetry: bool) -> FDSpace {
+    let mut space = nqueens_space(n);
+    if break_symmetry {
+      let queens: Vec<FDVar> = (0..n).map(Identity::new).collect();
+      // The reflection of a board on its vertical axis: `n + 1 - queens[i]`.
+      let reflection = queens.iter().map(|&q| Addition::new(Negation::new(q), n as i32 + 1)).collect();
+      space.cstore.alloc(LexLess::new(queens, reflection));
+    }
+    space
+  }
+
+  #[test]
+  fn nqueens_reflection() {
+    assert_eq!(count_solutions(nqueens(6, false)), 4);
+    assert_eq!(count_solutions(nqueens(6, true)), 2);
+    assert_eq!(count_solutions(nqueens(8, false)), 92);
+    assert_eq!(count_solutions(nqueens(8, true)), 46);
+  }
+
+  // The `n*n` permutation matrices, the rows are interchangeable.
+  fn permutation_matrices(n: usize, break_symmetry: bool) -> FDSpace {
+    let mut space = FDSpace::empty();
+    let rows: Vec<Vec<_>> = (0..n)
+      .map(|_| (0..n).map(|_| space.vstore.alloc((0,1).to_interval())).collect())
+      .collect();
+    for i in 0..n {
+      space.cstore.alloc(Linear::equal(vec![1; n], rows[i].clone(), 1));
+      space.cstore.alloc(Linear::equal(vec![1; n], rows.iter().map(|r| r[i]).collect(), 1));
+    }
+    if break_symmetry {
+      space.cstore.alloc(LexChain::less(rows));
+    }
+    space
+  }
+
+  #[test]
+  fn matrix_row_symmetry() {
+    assert_eq!(count_solutions(permutation_matrices(4, false)), 24);
+    assert_eq!(count_solutions(permutation_matrices(4, true)), 1);
+  }
+}
@@ -0,0 +1,374 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::division::{div_floor, div_ceil};
+use propagation::events::*;
+use propagation::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::fmt::{Formatter, Debug, Error};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinearRel {
+  LessEq,
+  Eq,
+  NotEq
+}
+
+/// Bounds consistent propagator of `a_1*x_1 + ... + a_n*x_n rel c` where `rel` is `<=`, `=` or `!=`.
+#[derive(Clone)]
+pub struct Linear<V, B>
+{
+  coefs: Vec<B>,
+  vars: Vec<V>,
+  rel: LinearRel,
+  c: B
+}
+
+impl<V, B> PropagatorKind for Linear<V, B> {}
+
+impl<V, B> Linear<V, B> {
+  pub fn new(coefs: Vec<B>, vars: Vec<V>, rel: LinearRel, c: B) -> Linear<V, B> {
+    assert_eq!(coefs.len(), vars.len(),
+      "A coefficient must be given for each variable of a linear constraint.");
+    Linear {
+      coefs: coefs,
+      vars: vars,
+      rel: rel,
+      c: c
+    }
+  }
+
+  pub fn less_eq(coefs: Vec<B>, vars: Vec<V>, c: B) -> Linear<V, B> {
+    Linear::new(coefs, vars, LinearRel::LessEq, c)
+  }
+
+  pub fn equal(coefs: Vec<B>, vars: Vec<V>, c: B) -> Linear<V, B> {
+    Linear::new(coefs, vars, LinearRel::Eq, c)
+  }
+
+  pub fn not_equal(coefs: Vec<B>, vars: Vec<V>, c: B) -> Linear<V, B> {
+    Linear::new(coefs, vars, LinearRel::NotEq, c)
+  }
+}
+
+impl<V, B> Debug for Linear<V, B> where
+  V: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    for (i, (a, x)) in self.coefs.iter().zip(self.vars.iter()).enumerate() {
+      if i > 0 {
+        try!(formatter.write_str(" + "));
+      }
+      try!(formatter.write_fmt(format_args!("{:?}*{:?}", a, x)));
+    }
+    let rel = match self.rel {
+      LinearRel::LessEq => "<=",
+      LinearRel::Eq => "=",
+      LinearRel::NotEq => "!="
+    };
+    formatter.write_fmt(format_args!(" {} {:?}", rel, self.c))
+  }
+}
+
+impl<V, B> Linear<V, B> where
+  B: PrimInt
+{
+  // Smallest and largest values of `a*x` where `x` is in `[lb..ub]`, `None` if the product overflows.
+  fn term_bounds(a: B, lb: B, ub: B) -> (Option<B>, Option<B>) {
+    if a >= B::zero() { (a.checked_mul(&lb), a.checked_mul(&ub)) }
+    else { (a.checked_mul(&ub), a.checked_mul(&lb)) }
+  }
+
+  // The bounds of the sum are `None` if they overflow, they are then considered infinite.
+  fn sum_bounds<Store, Domain>(&self, store: &Store) -> (Option<B>, Option<B>) where
+    V: StoreRead<Store, Value=Domain>,
+    Domain: Bounded<Bound=B>
+  {
+    let mut min = Some(B::zero());
+    let mut max = Some(B::zero());
+    for (a, x) in self.coefs.iter().zip(self.vars.iter()) {
+      let x = x.read(store);
+      let (l, u) = Linear::<V, B>::term_bounds(*a, x.lower(), x.upper());
+      min = min.and_then(|min| l.and_then(|l| min.checked_add(&l)));
+      max = max.and_then(|max| u.and_then(|u| max.checked_add(&u)));
+    }
+    (min, max)
+  }
+
+  // Filters the bounds for `sum(sign * a_i * x_i) <= sign * c`, `sign` being 1 or -1. Nothing is filtered if the bounds overflow.
+  fn propagate_less_eq<Store, Domain>(&self, store: &mut Store, sign: B) -> bool where
+    V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+    Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>
+  {
+    let c = match sign.checked_mul(&self.c) {
+      Some(c) => c,
+      None => return true
+    };
+    let mins: Option<Vec<B>> = self.coefs.iter().zip(self.vars.iter())
+      .map(|(a, x)| {
+        let x = x.read(store);
+        sign.checked_mul(a).and_then(|a| Linear::<V, B>::term_bounds(a, x.lower(), x.upper()).0)
+      })
+      .collect();
+    let mins = match mins {
+      Some(mins) => mins,
+      None => return true
+    };
+    let sum_min = match mins.iter().fold(Some(B::zero()), |sum, min| sum.and_then(|sum| sum.checked_add(min))) {
+      Some(sum_min) => sum_min,
+      None => return true
+    };
+    if sum_min > c {
+      return false;
+    }
+    for (i, (a, x)) in self.coefs.iter().zip(self.vars.iter()).enumerate() {
+      let a = sign * *a;
+      if a == B::zero() { continue; }
+      // a*x <= c - (sum_min - min_i)
+      let residual = match sum_min.checked_sub(&mins[i]).and_then(|others| c.checked_sub(&others)) {
+        Some(residual) => residual,
+        None => continue
+      };
+      let dom = x.read(store);
+      // The quotient only overflows for `MIN / -1`, `x` would then be greater than `MAX`.
+      let updated =
+        if a > B::zero() { div_floor(residual, a).map(|ub| dom.shrink_right(ub)) }
+        else { div_ceil(residual, a).map(|lb| dom.shrink_left(lb)) };
+      let updated = match updated {
+        Some(updated) => updated,
+        None => return false
+      };
+      if !x.update(store, updated) {
+        return false;
+      }
+    }
+    true
+  }
+
+  // When all the variables but one are assigned, the value making the sum equal to `c` is removed from the last one.
+  fn propagate_not_eq<Store, Domain>(&self, store: &mut Store) -> bool where
+    V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+    Domain: Bounded<Bound=B> + Difference<B, Output=Domain>
+  {
+    let mut unassigned = None;
+    let mut sum = B::zero();
+    for (i, (a, x)) in self.coefs.iter().zip(self.vars.iter()).enumerate() {
+      let dom = x.read(store);
+      if *a == B::zero() { continue; }
+      if dom.lower() == dom.upper() {
+        sum = match a.checked_mul(&dom.lower()).and_then(|ax| sum.checked_add(&ax)) {
+          Some(sum) => sum,
+          None => return true
+        };
+      }
+      else if unassigned.is_none() {
+        unassigned = Some(i);
+      }
+      else {
+        return true;
+      }
+    }
+    match unassigned {
+      None => sum != self.c,
+      Some(i) => {
+        let a = self.coefs[i];
+        let residual = match self.c.checked_sub(&sum) {
+          Some(residual) => residual,
+          None => return true
+        };
+        // `residual / a` overflows for `MIN / -1`, this value is not in the domain of `x`.
+        match residual.checked_div(&a) {
+          Some(v) if v * a == residual => {
+            let x = &self.vars[i];
+            let dom = x.read(store);
+            x.update(store, dom.difference(&v))
+          }
+          _ => true
+        }
+      }
+    }
+  }
+}
+
+impl<Store, Domain, V, B> Subsumption<Store> for Linear<V, B> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let (min, max) = self.sum_bounds(store);
+    let c = self.c;
+    // An overflowing bound is infinite and never compares equal to `c`.
+    let min_above = min.map_or(false, |min| min > c);
+    let max_below = max.map_or(false, |max| max < c);
+    let fixed_to_c = min == Some(c) && max == Some(c);
+    match self.rel {
+      LinearRel::LessEq =>
+        if max.map_or(false, |max| max <= c) { True }
+        else if min_above { False }
+        else { Unknown },
+      LinearRel::Eq =>
+        if fixed_to_c { True }
+        else if min_above || max_below { False }
+        else { Unknown },
+      LinearRel::NotEq =>
+        if min_above || max_below { True }
+        else if fixed_to_c { False }
+        else { Unknown }
+    }
+  }
+}
+
+impl<Store, Domain, V, B> Propagator<Store> for Linear<V, B> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B> + Difference<B, Output=Domain>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let one = B::one();
+    let minus_one = B::zero() - one;
+    match self.rel {
+      LinearRel::LessEq => self.propagate_less_eq(store, one),
+      LinearRel::Eq =>
+        self.propagate_less_eq(store, one) &&
+        self.propagate_less_eq(store, minus_one),
+      LinearRel::NotEq => self.propagate_not_eq(store)
+    }
+  }
+}
+
+impl<V, B> PropagatorDependencies<FDEvent> for Linear<V, B> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let event = match self.rel {
+      LinearRel::NotEq => FDEvent::Assignment,
+      _ => FDEvent::Bound
+    };
+    self.vars.iter().flat_map(|v| v.dependencies(event)).collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use interval::interval::*;
+  use propagators::test::*;
+
+  #[test]
+  fn linear_less_eq_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let dom2_5 = (2,5).to_interval();
+    let one = (1,1).to_interval();
+
+    // x + y <= 5
+    linear_test_one(1, vec![1,1], LinearRel::LessEq, 5, vec![dom0_10, dom0_10],
+      Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    linear_test_one(2, vec![1,1], LinearRel::LessEq, 5, vec![dom0_3, dom0_3],
+      Unknown, Unknown, vec![], true);
+    linear_test_one(3, vec![1,1], LinearRel::LessEq, 10, vec![dom0_3, dom2_5],
+      True, True, vec![], true);
+    linear_test_one(4, vec![1,1], LinearRel::LessEq, 1, vec![dom2_5, dom0_3],
+      False, False, vec![], false);
+    // 2x - 3y <= -4
+    linear_test_one(5, vec![2,-3], LinearRel::LessEq, -4, vec![dom0_10, dom0_3],
+      Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    // 3x + 2y <= 7 with y = 1
+    linear_test_one(6, vec![3,2], LinearRel::LessEq, 7, vec![dom0_10, one],
+      Unknown, True, vec![(0, Bound)], true);
+  }
+
+  #[test]
+  fn linear_eq_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let dom1_2 = (1,2).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+
+    // x + y + z = 5
+    linear_test_one(1, vec![1,1,1], LinearRel::Eq, 5, vec![dom0_10, dom0_10, dom0_10],
+      Unknown, Unknown, vec![(0, Bound), (1, Bound), (2, Bound)], true);
+    linear_test_one(2, vec![1,1,1], LinearRel::Eq, 5, vec![dom0_10, one, two],
+      Unknown, True, vec![(0, Assignment)], true);
+    linear_test_one(3, vec![1,1,1], LinearRel::Eq, 10, vec![dom0_3, dom0_3, dom0_3],
+      False, False, vec![], false);
+    // 2x - y = 1
+    linear_test_one(4, vec![2,-1], LinearRel::Eq, 1, vec![dom0_10, dom0_3],
+      Unknown, Unknown, vec![(0, Bound)], true);
+    linear_test_one(5, vec![2,-1], LinearRel::Eq, 1, vec![one, one],
+      True, True, vec![], true);
+    // 2x + 2y = 5 has no integer solution.
+    linear_test_one(6, vec![2,2], LinearRel::Eq, 5, vec![dom1_2, dom1_2],
+      Unknown, False, vec![], false);
+  }
+
+  #[test]
+  fn linear_not_eq_test() {
+    let dom0_10 = (0,10).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+
+    // x + 2y != 5
+    linear_test_one(1, vec![1,2], LinearRel::NotEq, 5, vec![dom0_10, dom0_10],
+      Unknown, Unknown, vec![], true);
+    linear_test_one(2, vec![1,2], LinearRel::NotEq, 5, vec![dom0_3, two],
+      Unknown, Unknown, vec![], true);
+    linear_test_one(3, vec![1,2], LinearRel::NotEq, 5, vec![dom0_10, one],
+      Unknown, Unknown, vec![], true);
+    linear_test_one(4, vec![1,2], LinearRel::NotEq, 5, vec![one, one],
+      True, True, vec![], true);
+    linear_test_one(5, vec![1,2], LinearRel::NotEq, 5, vec![one, two],
+      False, False, vec![], false);
+    linear_test_one(6, vec![1,2], LinearRel::NotEq, 4, vec![dom0_3, two],
+      Unknown, True, vec![(0, Bound)], true);
+    // 2y != 4 - 1 always holds.
+    linear_test_one(7, vec![1,2], LinearRel::NotEq, 4, vec![one, dom0_3],
+      Unknown, Unknown, vec![], true);
+  }
+
+  #[test]
+  fn linear_overflow_test() {
+    let dom0_3000 = (0,3000).to_interval();
+    let dom0_10 = (0,10).to_interval();
+
+    // The upper bound of `1000000 * x` overflows, the lower bounds are still used for filtering.
+    linear_test_one(1, vec![1000000, 1], LinearRel::LessEq, 5, vec![dom0_3000, dom0_10],
+      Unknown, True, vec![(0, Assignment), (1, Bound)], true);
+    // The lower bound of `-1000000 * x` overflows, only `1000000 * x - y <= -5` is filtered.
+    linear_test_one(2, vec![-1000000, 1], LinearRel::Eq, 5, vec![dom0_3000, dom0_10],
+      Unknown, Unknown, vec![(0, Assignment), (1, Bound)], true);
+  }
+
+  fn linear_test_one(test_num: u32, coefs: Vec<i32>, rel: LinearRel, c: i32,
+    doms: Vec<Interval<i32>>, before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| Linear::new(coefs, vars, rel, c),
+      doms, before, after, delta_expected, propagate_success);
+  }
+}
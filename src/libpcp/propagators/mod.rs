@@ -15,12 +15,46 @@
 //! Propagators are implementations of constraints, a single constraint can be realized by different propagators.
 //!
 //! We keep the propagator implementations generic over domains implementing specific operations (e.g. intersection or union). Propagators are also implemented to work on variable views, you can always obtain a view from a variable by using the `Identity` view.
+//!
+//! The domains are intervals, a value can only be removed from a domain by moving one of its bounds. The global constraints therefore reduce each domain to the hull of its supported values, which makes them bounds consistent.
 
+pub mod arith;
+pub mod bin_packing;
+pub mod boolean;
+pub mod cardinality;
+pub mod channel;
+pub mod circuit;
 pub mod cmp;
+pub mod cumulative;
+pub mod disjunctive;
 pub mod distinct;
+pub mod element;
+pub mod lex;
+pub mod linear;
+pub mod reified;
+pub mod regular;
+pub mod table;
+
+mod bounds;
+mod graph;
+pub(crate) mod division;
 
+pub use propagators::arith::*;
+pub use propagators::bin_packing::*;
+pub use propagators::boolean::*;
+pub use propagators::cardinality::*;
+pub use propagators::channel::*;
+pub use propagators::circuit::*;
 pub use propagators::cmp::*;
+pub use propagators::cumulative::*;
+pub use propagators::disjunctive::*;
 pub use propagators::distinct::*;
+pub use propagators::element::*;
+pub use propagators::lex::*;
+pub use propagators::linear::*;
+pub use propagators::reified::*;
+pub use propagators::regular::*;
+pub use propagators::table::*;
 
 use kernel::trilean::Trilean;
 use kernel::trilean::Trilean::*;
@@ -52,6 +86,7 @@ pub mod test {
   use propagation::events::*;
   use interval::interval::*;
   use term::identity::*;
+  use term::ops::*;
   use variable::store::test::consume_delta;
   use variable::test::*;
 
@@ -96,4 +131,90 @@ pub mod test {
     let propagator = make_prop(vars);
     subsumption_propagate(test_num, propagator, &mut store, before, after, delta_expected, propagate_success);
   }
+
+  /// Every interval included in `[lb..ub]`.
+  pub fn intervals(lb: i32, ub: i32) -> Vec<(i32, i32)> {
+    (lb..ub+1).flat_map(|l| (l..ub+1).map(move |u| (l, u))).collect()
+  }
+
+  /// Every vector of `n` intervals taken in `ivs`.
+  pub fn interval_tuples(ivs: &Vec<(i32, i32)>, n: usize) -> Vec<Vec<(i32, i32)>> {
+    let mut tuples = vec![vec![]];
+    for _ in 0..n {
+      tuples = tuples.into_iter()
+        .flat_map(|t: Vec<(i32, i32)>| ivs.iter().map(move |&iv| { let mut t = t.clone(); t.push(iv); t }))
+        .collect();
+    }
+    tuples
+  }
+
+  /// Extends `hull` such that it contains the values of `solution`.
+  pub fn extend_hull(hull: &mut Option<Vec<(i32, i32)>>, solution: &[i32]) {
+    *hull = Some(match hull.take() {
+      None => solution.iter().map(|&v| (v, v)).collect(),
+      Some(h) => h.into_iter().zip(solution.iter())
+        .map(|((l, u), &v)| (::std::cmp::min(l, v), ::std::cmp::max(u, v)))
+        .collect()
+    });
+  }
+
+  /// Hull of the values of each variable appearing in at least one solution of `relation`, `None` if there is no solution.
+  pub fn solutions_hull<R>(doms: &Vec<(i32, i32)>, relation: R) -> Option<Vec<(i32, i32)>> where
+   R: Fn(&[i32]) -> bool
+  {
+    let mut hull = None;
+    if doms.iter().any(|d| d.0 > d.1) {
+      return hull;
+    }
+    let mut current: Vec<i32> = doms.iter().map(|d| d.0).collect();
+    loop {
+      if relation(&current) {
+        extend_hull(&mut hull, &current);
+      }
+      // Next combination of values.
+      let mut i = 0;
+      while i < doms.len() && current[i] == doms[i].1 {
+        current[i] = doms[i].0;
+        i += 1;
+      }
+      if i == doms.len() { return hull; }
+      current[i] += 1;
+    }
+  }
+
+  /// Propagates until a fixed point and returns the bounds of the variables, `None` on failure.
+  pub fn fixpoint<P, F>(make_prop: F, doms: &Vec<(i32, i32)>) -> Option<Vec<(i32, i32)>> where
+   P: Propagator<FDStore>,
+   F: FnOnce(Vec<FDVar>) -> P
+  {
+    let mut store = FDStore::empty();
+    let vars: Vec<_> = doms.iter().map(|&(l, u)| store.alloc((l, u).to_interval())).collect();
+    let mut prop = make_prop(vars.clone());
+    let mut result = Some(doms.clone());
+    loop {
+      if !prop.propagate(&mut store) {
+        return None;
+      }
+      let current: Vec<_> = vars.iter().map(|x| { let d = x.read(&store); (d.lower(), d.upper()) }).collect();
+      if Some(current.clone()) == result { return result; }
+      result = Some(current);
+    }
+  }
+
+  /// Checks that the bounds `result` obtained by propagating `doms` contain the hull of the solutions `expected`, and that they are exact when every variable is fixed.
+  pub fn check_soundness(doms: &Vec<(i32, i32)>, result: &Option<Vec<(i32, i32)>>, expected: &Option<Vec<(i32, i32)>>) {
+    match (result, expected) {
+      (&Some(ref result), &Some(ref expected)) => {
+        assert!(result.iter().zip(expected.iter()).all(|(r, e)| r.0 <= e.0 && e.1 <= r.1),
+          "{:?}: {:?} removes a solution of {:?}", doms, result, expected);
+        if result.iter().all(|r| r.0 == r.1) {
+          assert_eq!(result, expected, "{:?}", doms);
+        }
+      }
+      (&Some(ref result), &None) => assert!(result.iter().any(|r| r.0 != r.1),
+        "{:?}: {:?} is not a solution", doms, result),
+      (&None, &Some(ref expected)) => panic!("{:?}: the solutions {:?} are removed", doms, expected),
+      (&None, &None) => ()
+    }
+  }
 }
@@ -0,0 +1,475 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `Regular` constraint forces a sequence of variables to form a word accepted by a deterministic finite automaton, or a path in a multi-valued decision diagram (MDD).
+//!
+//! Both are unfolded into a layered graph where the edges between the layers `i` and `i+1` are labelled by the values of the `i`-th variable (Pesant, "A regular language membership constraint for finite sequences of variables", CP 2004). An edge is kept if it belongs to a path from the root to an accepting node of the last layer, and the domain of each variable is reduced to the hull of the labels of its layer.
+//!
+//! The graph is stored in the propagator and updated incrementally: the bounds of the variables are compared to the ones of the previous propagation, the edges with a value outside of the new bounds are removed and the removal is propagated to the nodes without incoming or outgoing edges.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::bounds::read_bounds;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+use std::fmt::{Formatter, Debug, Error};
+
+/// Deterministic finite automaton with the states `0..num_states`, a transition `(from, value, to)` reads `value` in the state `from`.
+#[derive(Clone, Debug)]
+pub struct Automaton<B>
+{
+  num_states: usize,
+  initial: usize,
+  accepting: Vec<bool>,
+  transitions: Vec<Vec<(B, usize)>>
+}
+
+impl<B> Automaton<B> where
+  B: Eq + Clone
+{
+  pub fn new(num_states: usize, initial: usize, accepting: Vec<usize>,
+    transitions: Vec<(usize, B, usize)>) -> Automaton<B>
+  {
+    assert!(initial < num_states, "The initial state must be a state of the automaton.");
+    let mut is_accepting = vec![false; num_states];
+    for s in accepting {
+      assert!(s < num_states, "An accepting state must be a state of the automaton.");
+      is_accepting[s] = true;
+    }
+    let mut delta = vec![vec![]; num_states];
+    for (from, value, to) in transitions {
+      assert!(from < num_states && to < num_states, "A transition must link two states of the automaton.");
+      assert!(delta[from].iter().all(|t: &(B, usize)| t.0 != value),
+        "The automaton must be deterministic.");
+      delta[from].push((value, to));
+    }
+    Automaton {
+      num_states: num_states,
+      initial: initial,
+      accepting: is_accepting,
+      transitions: delta
+    }
+  }
+
+  pub fn accepts(&self, word: &[B]) -> bool {
+    let mut state = self.initial;
+    for value in word {
+      match self.transitions[state].iter().find(|t| t.0 == *value) {
+        Some(t) => state = t.1,
+        None => return false
+      }
+    }
+    self.accepting[state]
+  }
+
+  // Layered graph of the words of length `n`, the nodes of each layer are the states.
+  fn unfold(&self, n: usize) -> LayeredGraph<B> {
+    let edges: Vec<(usize, B, usize)> = (0..self.num_states)
+      .flat_map(|s| self.transitions[s].iter().map(move |&(ref v, t)| (s, v.clone(), t)))
+      .collect();
+    LayeredGraph::new(self.initial, vec![edges; n], &self.accepting)
+  }
+}
+
+/// Multi-valued decision diagram: `layers[i]` contains the edges `(from, value, to)` between the nodes of the layers `i` and `i+1`, the nodes are numbered from 0 in each layer. The root is the node 0 of the first layer and every node of the last layer is accepting.
+#[derive(Clone, Debug)]
+pub struct Mdd<B>
+{
+  layers: Vec<Vec<(usize, B, usize)>>
+}
+
+impl<B> Mdd<B> where
+  B: Eq + Hash + Clone
+{
+  pub fn new(layers: Vec<Vec<(usize, B, usize)>>) -> Mdd<B> {
+    Mdd { layers: layers }
+  }
+
+  /// MDD accepting exactly the tuples of length `arity`, the common prefixes of the tuples share their nodes.
+  pub fn from_tuples(arity: usize, tuples: Vec<Vec<B>>) -> Mdd<B> {
+    let mut layers = vec![vec![]; arity];
+    let mut children: Vec<HashMap<(usize, B), usize>> = vec![HashMap::new(); arity];
+    for tuple in tuples {
+      assert_eq!(tuple.len(), arity, "The tuples of a MDD must have the same arity.");
+      let mut node = 0;
+      for (i, value) in tuple.into_iter().enumerate() {
+        let next = children[i].len();
+        let layer = &mut layers[i];
+        // The last layer only contains the accepting node.
+        node = *children[i].entry((node, value.clone())).or_insert_with(|| {
+          let to = if i + 1 == arity { 0 } else { next };
+          layer.push((node, value, to));
+          to
+        });
+      }
+    }
+    Mdd::new(layers)
+  }
+
+  fn unfold(&self) -> LayeredGraph<B> {
+    let last = self.layers.last().map_or(1, |l| l.iter().map(|e| e.2 + 1).max().unwrap_or(0));
+    LayeredGraph::new(0, self.layers.clone(), &vec![true; last])
+  }
+}
+
+// Edges of the graph, shared by the copies of a propagator.
+struct Structure<B>
+{
+  edges: Vec<(usize, B, usize)>,
+  layer_edges: Vec<Vec<usize>>,
+  in_edges: Vec<Vec<usize>>,
+  out_edges: Vec<Vec<usize>>,
+  edge_layer: Vec<usize>
+}
+
+// The node 0 is the root, the edges not belonging to a path from the root to an accepting node are removed when the graph is built.
+#[derive(Clone)]
+struct LayeredGraph<B>
+{
+  structure: Rc<Structure<B>>,
+  alive: Vec<bool>,
+  in_degree: Vec<usize>,
+  out_degree: Vec<usize>
+}
+
+impl<B> LayeredGraph<B> where
+  B: Clone
+{
+  // `layers[i]` contains the edges from the nodes of the layer `i` to the ones of the layer `i+1`, the nodes are numbered in each layer.
+  fn new(root: usize, layers: Vec<Vec<(usize, B, usize)>>, accepting: &Vec<bool>) -> LayeredGraph<B> {
+    let n = layers.len();
+    let mut reachable: Vec<Vec<usize>> = vec![vec![root]];
+    for i in 0..n {
+      let mut next: Vec<usize> = layers[i].iter()
+        .filter(|e| reachable[i].contains(&e.0))
+        .map(|e| e.2)
+        .collect();
+      next.sort();
+      next.dedup();
+      reachable.push(next);
+    }
+    let mut coreachable: Vec<usize> = reachable[n].iter().cloned()
+      .filter(|&s| s < accepting.len() && accepting[s])
+      .collect();
+    let mut kept: Vec<Vec<(usize, B, usize)>> = vec![vec![]; n];
+    for i in (0..n).rev() {
+      kept[i] = layers[i].iter().cloned()
+        .filter(|e| reachable[i].contains(&e.0) && coreachable.contains(&e.2))
+        .collect();
+      coreachable = kept[i].iter().map(|e| e.0).collect();
+      coreachable.sort();
+      coreachable.dedup();
+    }
+
+    // Numbering of the nodes of all layers, the root is the node 0.
+    let mut ids: HashMap<(usize, usize), usize> = HashMap::new();
+    ids.insert((0, root), 0);
+    let mut edges = vec![];
+    let mut layer_edges = vec![vec![]; n];
+    let mut edge_layer = vec![];
+    for (i, layer) in kept.into_iter().enumerate() {
+      for (from, value, to) in layer {
+        let next_id = ids.len();
+        let from = *ids.entry((i, from)).or_insert(next_id);
+        let next_id = ids.len();
+        let to = *ids.entry((i + 1, to)).or_insert(next_id);
+        layer_edges[i].push(edges.len());
+        edge_layer.push(i);
+        edges.push((from, value, to));
+      }
+    }
+    let num_nodes = ids.len();
+    let mut in_edges = vec![vec![]; num_nodes];
+    let mut out_edges = vec![vec![]; num_nodes];
+    for (e, &(from, _, to)) in edges.iter().enumerate() {
+      out_edges[from].push(e);
+      in_edges[to].push(e);
+    }
+    LayeredGraph {
+      alive: vec![true; edges.len()],
+      in_degree: in_edges.iter().map(|e| e.len()).collect(),
+      out_degree: out_edges.iter().map(|e| e.len()).collect(),
+      structure: Rc::new(Structure {
+        edges: edges,
+        layer_edges: layer_edges,
+        in_edges: in_edges,
+        out_edges: out_edges,
+        edge_layer: edge_layer
+      })
+    }
+  }
+
+  fn is_empty(&self) -> bool {
+    self.out_degree[0] == 0
+  }
+
+  // Removes the `edges` and the edges that are not on a path from the root to the last layer anymore, `dirty[i]` is set if an edge of the layer `i` is removed.
+  fn remove_edges(&mut self, mut edges: Vec<usize>, dirty: &mut Vec<bool>) {
+    let structure = self.structure.clone();
+    while let Some(e) = edges.pop() {
+      if !self.alive[e] { continue; }
+      self.alive[e] = false;
+      dirty[structure.edge_layer[e]] = true;
+      let (from, _, to) = structure.edges[e];
+      self.out_degree[from] -= 1;
+      self.in_degree[to] -= 1;
+      if self.out_degree[from] == 0 {
+        edges.extend(structure.in_edges[from].iter().filter(|&&e| self.alive[e]));
+      }
+      if self.in_degree[to] == 0 {
+        edges.extend(structure.out_edges[to].iter().filter(|&&e| self.alive[e]));
+      }
+    }
+  }
+
+  fn alive_edges<'a>(&'a self, layer: usize) -> Box<Iterator<Item=&'a (usize, B, usize)> + 'a> {
+    Box::new(self.structure.layer_edges[layer].iter()
+      .filter(move |&&e| self.alive[e])
+      .map(move |&e| &self.structure.edges[e]))
+  }
+}
+
+#[derive(Clone)]
+pub struct Regular<V, B>
+{
+  vars: Vec<V>,
+  graph: LayeredGraph<B>,
+  // Bounds of the variables at the end of the last propagation.
+  bounds: Vec<Option<(B, B)>>
+}
+
+impl<V, B> PropagatorKind for Regular<V, B> {}
+
+impl<V, B> Regular<V, B> where
+  B: Eq + Hash + Clone
+{
+  /// The values of `vars` form a word accepted by `automaton`.
+  pub fn new(vars: Vec<V>, automaton: &Automaton<B>) -> Regular<V, B> {
+    let graph = automaton.unfold(vars.len());
+    Regular::with_graph(vars, graph)
+  }
+
+  /// The values of `vars` form a path of `mdd`.
+  pub fn with_mdd(vars: Vec<V>, mdd: &Mdd<B>) -> Regular<V, B> {
+    assert_eq!(vars.len(), mdd.layers.len(), "A MDD must have one layer per variable.");
+    let graph = mdd.unfold();
+    Regular::with_graph(vars, graph)
+  }
+
+  fn with_graph(vars: Vec<V>, graph: LayeredGraph<B>) -> Regular<V, B> {
+    assert!(!vars.is_empty(), "The regular constraint must have at least one variable.");
+    Regular {
+      bounds: vec![None; vars.len()],
+      vars: vars,
+      graph: graph
+    }
+  }
+}
+
+impl<V, B> Debug for Regular<V, B> where
+  V: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("regular({:?})", self.vars))
+  }
+}
+
+impl<Store, Domain, B, V> Subsumption<Store> for Regular<V, B> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let doms = read_bounds(&self.vars, store);
+    for (i, &(l, u)) in doms.iter().enumerate() {
+      if self.graph.alive_edges(i).all(|e| e.1 < l || e.1 > u) {
+        return False;
+      }
+    }
+    if doms.iter().any(|&(l, u)| l != u) {
+      return Unknown;
+    }
+    let mut node = 0;
+    for (i, &(v, _)) in doms.iter().enumerate() {
+      match self.graph.alive_edges(i).find(|e| e.0 == node && e.1 == v) {
+        Some(e) => node = e.2,
+        None => return False
+      }
+    }
+    True
+  }
+}
+
+impl<Store, Domain, B, V> Propagator<Store> for Regular<V, B> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let n = self.vars.len();
+    let doms = read_bounds(&self.vars, store);
+    let mut removed = vec![];
+    for i in 0..n {
+      let (l, u) = doms[i];
+      if self.bounds[i] != Some((l, u)) {
+        let structure = &self.graph.structure;
+        removed.extend(structure.layer_edges[i].iter()
+          .filter(|&&e| self.graph.alive[e])
+          .filter(|&&e| structure.edges[e].1 < l || structure.edges[e].1 > u));
+      }
+    }
+    let mut dirty = vec![false; n];
+    self.graph.remove_edges(removed, &mut dirty);
+    if self.graph.is_empty() {
+      return false;
+    }
+    for i in 0..n {
+      if dirty[i] || self.bounds[i] != Some(doms[i]) {
+        let lb = self.graph.alive_edges(i).map(|e| e.1).min().unwrap();
+        let ub = self.graph.alive_edges(i).map(|e| e.1).max().unwrap();
+        let x = &self.vars[i];
+        let d = x.read(store);
+        if !x.update(store, d.shrink_left(lb).shrink_right(ub)) {
+          return false;
+        }
+        let d = x.read(store);
+        self.bounds[i] = Some((d.lower(), d.upper()));
+      }
+    }
+    true
+  }
+}
+
+impl<V, B> PropagatorDependencies<FDEvent> for Regular<V, B> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    self.vars.iter().flat_map(|x| x.dependencies(FDEvent::Bound)).collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagators::test::*;
+  use variable::test::*;
+  use interval::interval::*;
+
+  // Shifts of a roster: 0 is a rest day, 1 a day shift and 2 a night shift. There are no more than 3 consecutive nights and a night block is followed by a rest day. The state `k` means that the last `k` shifts were nights.
+  fn roster() -> Automaton<i32> {
+    Automaton::new(4, 0, vec![0, 1, 2, 3], vec![
+      (0, 0, 0), (0, 1, 0), (0, 2, 1),
+      (1, 0, 0), (1, 2, 2),
+      (2, 0, 0), (2, 2, 3),
+      (3, 0, 0)
+    ])
+  }
+
+  #[test]
+  fn automaton_test() {
+    let a = roster();
+    assert!(a.accepts(&[1, 2, 2, 0, 1]));
+    assert!(a.accepts(&[2, 2, 2, 0]));
+    assert!(!a.accepts(&[2, 2, 2, 2]));
+    assert!(!a.accepts(&[2, 1]));
+  }
+
+  #[test]
+  #[should_panic]
+  fn non_deterministic_automaton() {
+    Automaton::new(2, 0, vec![1], vec![(0, 1, 0), (0, 1, 1)]);
+  }
+
+  fn regular_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num, |vars| Regular::new(vars, &roster()),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn regular_test() {
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+    let dom0_2 = (0,2).to_interval();
+    let dom1_2 = (1,2).to_interval();
+
+    regular_test_one(1, vec![dom0_2, dom0_2, dom0_2], Unknown, Unknown, vec![], true);
+    regular_test_one(2, vec![two, one, dom0_2], Unknown, False, vec![], false);
+    // A day shift can not follow a night.
+    regular_test_one(3, vec![two, dom1_2, dom0_2], Unknown, Unknown, vec![(1, Assignment)], true);
+    // After three nights comes a rest day.
+    regular_test_one(4, vec![two, two, two, dom0_2], Unknown, True, vec![(3, Assignment)], true);
+    // The first shift can not be a night since the second one is a day.
+    regular_test_one(5, vec![(0,5).to_interval(), one, dom1_2], Unknown, Unknown, vec![(0, Bound)], true);
+    regular_test_one(6, vec![zero, one, two], True, True, vec![], true);
+  }
+
+  // Propagates `prop` on the domains `ivs`, first from scratch and then incrementally after a propagation on wider domains, and compares the result with `expected`.
+  fn check_consistency<F>(make_prop: F, ivs: Vec<(i32, i32)>, expected: Option<Vec<(i32, i32)>>) where
+    F: Fn(Vec<FDVar>) -> Regular<FDVar, i32>
+  {
+    assert_eq!(fixpoint(&make_prop, &ivs), expected, "consistency of {:?}", ivs);
+
+    let mut store = StoreI32::empty();
+    let vars: Vec<_> = ivs.iter().map(|_| store.alloc((0,2).to_interval())).collect();
+    let mut prop = make_prop(vars.clone());
+    if prop.propagate(&mut store) {
+      let mut success = true;
+      for (x, &(l, u)) in vars.iter().zip(ivs.iter()) {
+        let d = x.read(&store);
+        success = success && x.update(&mut store, d.shrink_left(l).shrink_right(u));
+      }
+      let result = if success && prop.propagate(&mut store) {
+        Some(vars.iter().map(|x| { let d = x.read(&store); (d.lower(), d.upper()) }).collect())
+      } else { None };
+      assert_eq!(result, expected, "incremental consistency of {:?}", ivs);
+    }
+  }
+
+  #[test]
+  fn exhaustive_regular_consistency() {
+    let automaton = roster();
+    for ivs in interval_tuples(&intervals(0, 2), 4) {
+      let expected = solutions_hull(&ivs, |w| automaton.accepts(w));
+      check_consistency(|vars| Regular::new(vars, &automaton), ivs, expected);
+    }
+  }
+
+  #[test]
+  fn exhaustive_mdd_consistency() {
+    let tuples = vec![
+      vec![0, 0, 1, 2], vec![0, 2, 1, 2], vec![1, 1, 1, 1],
+      vec![2, 0, 0, 0], vec![2, 0, 2, 1], vec![0, 0, 2, 2]
+    ];
+    let mdd = Mdd::from_tuples(4, tuples.clone());
+    for ivs in interval_tuples(&intervals(0, 2), 4) {
+      let expected = solutions_hull(&ivs, |w| tuples.iter().any(|t| &t[..] == w));
+      check_consistency(|vars| Regular::with_mdd(vars, &mdd), ivs, expected);
+    }
+  }
+}
@@ -0,0 +1,274 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reification of a constraint `c` with a boolean (0/1) view `b`: `Reified` is the equivalence `b <-> c` and `Implication` is the half-reification `b -> c`.
+//!
+//! Any propagator implementing `Subsumption` and `Propagator` can be reified. The constraint is propagated once `b` is fixed to 1, and its entailment (resp. disentailment) fixes `b` to 1 (resp. 0). When `b` is fixed to 0, a generic constraint can only be checked, not propagated: for stronger filtering, post the complement of `c` with `Implication::new(bool_not(b), not_c)`.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagation::*;
+use propagation::events::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::fmt::{Formatter, Debug, Error};
+
+// Reduces the domain of `b` to `[0..1]` and returns it.
+fn boolean_dom<Store, Domain, V, B>(b: &V, store: &mut Store) -> Option<Domain> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  let dom = b.read(store);
+  if b.update(store, dom.shrink_left(B::zero()).shrink_right(B::one())) {
+    Some(b.read(store))
+  }
+  else { None }
+}
+
+fn fix<Store, Domain, V, B>(b: &V, store: &mut Store, value: B) -> bool where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  let dom = b.read(store);
+  b.update(store, dom.shrink_left(value).shrink_right(value))
+}
+
+#[derive(Clone)]
+pub struct Reified<V, P>
+{
+  b: V,
+  p: P
+}
+
+impl<V, P> PropagatorKind for Reified<V, P> {}
+
+impl<V, P> Reified<V, P> {
+  /// Constraint `b <-> p` where `b` is a 0/1 variable.
+  pub fn new(b: V, p: P) -> Reified<V, P> {
+    Reified { b: b, p: p }
+  }
+}
+
+impl<V, P> Debug for Reified<V, P> where
+  V: Debug,
+  P: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} <-> ({:?})", self.b, self.p))
+  }
+}
+
+impl<Store, Domain, B, V, P> Subsumption<Store> for Reified<V, P> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt,
+  P: Subsumption<Store>
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let b = self.b.read(store);
+    if b.lower() > B::one() || b.upper() < B::zero() { False }
+    else if b.lower() == B::one() { self.p.is_subsumed(store) }
+    else if b.upper() == B::zero() { !self.p.is_subsumed(store) }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, V, P> Propagator<Store> for Reified<V, P> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt,
+  P: Subsumption<Store> + Propagator<Store>
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    match boolean_dom(&self.b, store) {
+      None => false,
+      Some(ref b) if b.lower() == B::one() => self.p.propagate(store),
+      Some(_) => match self.p.is_subsumed(store) {
+        True => fix(&self.b, store, B::one()),
+        False => fix(&self.b, store, B::zero()),
+        Unknown => true
+      }
+    }
+  }
+}
+
+impl<V, P> PropagatorDependencies<FDEvent> for Reified<V, P> where
+  V: ViewDependencies<FDEvent>,
+  P: PropagatorDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.b.dependencies(FDEvent::Assignment);
+    deps.append(&mut self.p.dependencies());
+    deps
+  }
+}
+
+#[derive(Clone)]
+pub struct Implication<V, P>
+{
+  b: V,
+  p: P
+}
+
+impl<V, P> PropagatorKind for Implication<V, P> {}
+
+impl<V, P> Implication<V, P> {
+  /// Constraint `b -> p` where `b` is a 0/1 variable.
+  pub fn new(b: V, p: P) -> Implication<V, P> {
+    Implication { b: b, p: p }
+  }
+}
+
+impl<V, P> Debug for Implication<V, P> where
+  V: Debug,
+  P: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} -> ({:?})", self.b, self.p))
+  }
+}
+
+impl<Store, Domain, B, V, P> Subsumption<Store> for Implication<V, P> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt,
+  P: Subsumption<Store>
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let b = self.b.read(store);
+    if b.lower() > B::one() || b.upper() < B::zero() { False }
+    else if b.upper() == B::zero() { True }
+    else if b.lower() == B::one() { self.p.is_subsumed(store) }
+    else if self.p.is_subsumed(store) == True { True }
+    else { Unknown }
+  }
+}
+
+impl<Store, Domain, B, V, P> Propagator<Store> for Implication<V, P> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt,
+  P: Subsumption<Store> + Propagator<Store>
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    match boolean_dom(&self.b, store) {
+      None => false,
+      Some(ref b) if b.lower() == B::one() => self.p.propagate(store),
+      Some(ref b) if b.upper() == B::zero() => true,
+      Some(_) =>
+        if self.p.is_subsumed(store) == False { fix(&self.b, store, B::zero()) }
+        else { true }
+    }
+  }
+}
+
+impl<V, P> PropagatorDependencies<FDEvent> for Implication<V, P> where
+  V: ViewDependencies<FDEvent>,
+  P: PropagatorDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    let mut deps = self.b.dependencies(FDEvent::Assignment);
+    deps.append(&mut self.p.dependencies());
+    deps
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use propagators::cmp::*;
+  use propagators::test::*;
+  use term::*;
+  use variable::test::*;
+  use interval::interval::*;
+
+  #[test]
+  fn reified_x_less_y() {
+    let dom0_1 = (0,1).to_interval();
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let dom0_10 = (0,10).to_interval();
+    let dom5_15 = (5,15).to_interval();
+    let dom11_20 = (11,20).to_interval();
+    let dom0_5 = (0,5).to_interval();
+
+    reified_test_one(1, vec![dom0_1, dom0_10, dom5_15], Unknown, Unknown, vec![], true);
+    reified_test_one(2, vec![dom0_1, dom0_10, dom11_20], Unknown, True, vec![(0, Assignment)], true);
+    reified_test_one(3, vec![dom0_1, dom11_20, dom0_10], Unknown, True, vec![(0, Assignment)], true);
+    reified_test_one(4, vec![one, dom5_15, dom0_10], Unknown, Unknown, vec![(1, Bound), (2, Bound)], true);
+    reified_test_one(5, vec![zero, dom5_15, dom0_10], Unknown, Unknown, vec![], true);
+    reified_test_one(6, vec![zero, dom0_5, dom11_20], False, False, vec![], false);
+    reified_test_one(7, vec![one, dom11_20, dom0_10], False, False, vec![], false);
+    reified_test_one(8, vec![(0,5).to_interval(), dom0_10, dom11_20], Unknown, True, vec![(0, Assignment)], true);
+    reified_test_one(9, vec![(2,5).to_interval(), dom0_10, dom11_20], False, False, vec![], false);
+  }
+
+  fn reified_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars: Vec<FDVar>| Reified::new(vars[0].clone(), XLessY::new(vars[1].clone(), vars[2].clone())),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn implication_x_eq_c() {
+    let dom0_1 = (0,1).to_interval();
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let dom0_10 = (0,10).to_interval();
+    let dom4_10 = (4,10).to_interval();
+    let three = (3,3).to_interval();
+
+    implication_test_one(1, vec![dom0_1, dom0_10], Unknown, Unknown, vec![], true);
+    implication_test_one(2, vec![dom0_1, dom4_10], Unknown, True, vec![(0, Assignment)], true);
+    implication_test_one(3, vec![dom0_1, three], True, True, vec![], true);
+    implication_test_one(4, vec![one, dom0_10], Unknown, True, vec![(1, Assignment)], true);
+    implication_test_one(5, vec![zero, dom4_10], True, True, vec![], true);
+    implication_test_one(6, vec![one, dom4_10], False, False, vec![], false);
+  }
+
+  fn implication_test_one(test_num: u32, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars: Vec<FDVar>| Implication::new(vars[0].clone(), XEqY::new(vars[1].clone(), Constant::new(3))),
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  #[test]
+  fn reified_with_complement() {
+    // `b <-> (x < y)` with `b = 0` only checks that `x < y` is not entailed, `!b -> (x >= y)` propagates its complement.
+    let mut store = StoreI32::empty();
+    let b = store.alloc((0,0).to_interval());
+    let x = store.alloc((5,15).to_interval());
+    let y = store.alloc((6,10).to_interval());
+    let mut reified = Reified::new(b.clone(), XLessY::new(x.clone(), y.clone()));
+    let mut complement = Implication::new(bool_not(b), x_geq_y(x.clone(), y));
+    assert!(reified.propagate(&mut store));
+    assert_eq!(x.read(&store), (5,15).to_interval());
+    assert!(complement.propagate(&mut store));
+    assert_eq!(x.read(&store), (6,15).to_interval());
+  }
+}
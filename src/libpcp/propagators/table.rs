@@ -0,0 +1,403 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Extensional constraint given by a list of allowed (positive table) or forbidden (negative table) tuples, filtered with the compact-table algorithm (Demeulenaere et al., "Compact-Table: Efficiently Filtering Table Constraints with Reversible Sparse Bit-Sets", CP 2016) and its extension to negative tables (Verhaeghe et al., CP 2018).
+//!
+//! The tuples still valid in the current domains are kept in a bitset, updated with the values removed since the previous propagation. The bounds of the variables are moved to the nearest supported values.
+
+use kernel::*;
+use kernel::Trilean::*;
+use propagators::PropagatorKind;
+use propagators::bounds::read_bounds;
+use propagation::events::*;
+use propagation::*;
+use term::ops::*;
+use gcollections::ops::*;
+use num::PrimInt;
+use std::rc::Rc;
+use std::fmt::{Formatter, Debug, Error};
+
+type Bitset = Vec<u64>;
+
+fn bitset_full(n: usize) -> Bitset {
+  let mut bits = vec![!0u64; (n + 63) / 64];
+  if n % 64 != 0 {
+    let last = bits.len() - 1;
+    bits[last] = (1u64 << (n % 64)) - 1;
+  }
+  bits
+}
+
+fn bitset_is_empty(bits: &Bitset) -> bool {
+  bits.iter().all(|&w| w == 0)
+}
+
+fn bitset_count(bits: &Bitset) -> u64 {
+  bits.iter().map(|w| w.count_ones() as u64).sum()
+}
+
+fn intersect_count(a: &Bitset, b: &Bitset) -> u64 {
+  a.iter().zip(b.iter()).map(|(x, y)| (x & y).count_ones() as u64).sum()
+}
+
+fn intersects(a: &Bitset, b: &Bitset) -> bool {
+  a.iter().zip(b.iter()).any(|(x, y)| x & y != 0)
+}
+
+// Immutable data shared between the copies of the propagator.
+struct TableData<B> {
+  tuples: Vec<Vec<B>>,
+  // `values[i]` are the sorted values of the `i`th variable appearing in the tuples.
+  values: Vec<Vec<B>>,
+  // `supports[i][k]` are the tuples where the `i`th variable is equal to `values[i][k]`.
+  supports: Vec<Vec<Bitset>>
+}
+
+impl<B> TableData<B> where
+  B: PrimInt
+{
+  fn new(arity: usize, mut tuples: Vec<Vec<B>>) -> TableData<B> {
+    assert!(tuples.iter().all(|t| t.len() == arity),
+      "The tuples of a table must have the same length as the number of variables.");
+    tuples.sort();
+    tuples.dedup();
+    let mut values: Vec<Vec<B>> = (0..arity)
+      .map(|i| tuples.iter().map(|t| t[i]).collect())
+      .collect();
+    for vals in &mut values {
+      vals.sort();
+      vals.dedup();
+    }
+    let words = (tuples.len() + 63) / 64;
+    let mut supports: Vec<Vec<Bitset>> = values.iter()
+      .map(|vals| vec![vec![0u64; words]; vals.len()])
+      .collect();
+    for (t, tuple) in tuples.iter().enumerate() {
+      for i in 0..arity {
+        let k = values[i].binary_search(&tuple[i]).unwrap();
+        supports[i][k][t / 64] |= 1u64 << (t % 64);
+      }
+    }
+    TableData {
+      tuples: tuples,
+      values: values,
+      supports: supports
+    }
+  }
+
+  // Removes from `bits` the tuples containing a value of `x_i` which is in `old` but not in `[l..u]`.
+  fn remove_values(&self, bits: &mut Bitset, i: usize, old: Option<(B, B)>, l: B, u: B) {
+    for (k, &v) in self.values[i].iter().enumerate() {
+      let was_in = match old {
+        Some((old_l, old_u)) => v >= old_l && v <= old_u,
+        None => true
+      };
+      if was_in && (v < l || v > u) {
+        for (w, s) in bits.iter_mut().zip(self.supports[i][k].iter()) {
+          *w = *w & !s;
+        }
+      }
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct Table<V, B>
+{
+  vars: Vec<V>,
+  positive: bool,
+  data: Rc<TableData<B>>,
+  // Tuples whose values are all in the domains seen during the last propagation.
+  current: Bitset,
+  last_bounds: Vec<Option<(B, B)>>
+}
+
+impl<V, B> PropagatorKind for Table<V, B> {}
+
+impl<V, B> Table<V, B> where
+  B: PrimInt
+{
+  /// The tuple of values of `vars` must be one of `tuples`.
+  pub fn allowed(vars: Vec<V>, tuples: Vec<Vec<B>>) -> Table<V, B> {
+    Table::new(vars, tuples, true)
+  }
+
+  /// The tuple of values of `vars` must not be one of `tuples`.
+  pub fn forbidden(vars: Vec<V>, tuples: Vec<Vec<B>>) -> Table<V, B> {
+    Table::new(vars, tuples, false)
+  }
+
+  fn new(vars: Vec<V>, tuples: Vec<Vec<B>>, positive: bool) -> Table<V, B> {
+    let data = TableData::new(vars.len(), tuples);
+    let current = bitset_full(data.tuples.len());
+    let last_bounds = vec![None; vars.len()];
+    Table {
+      vars: vars,
+      positive: positive,
+      data: Rc::new(data),
+      current: current,
+      last_bounds: last_bounds
+    }
+  }
+
+  fn value_index(&self, i: usize, v: B) -> Option<usize> {
+    self.data.values[i].binary_search(&v).ok()
+  }
+
+  // A value is supported in a positive table if a valid tuple contains it.
+  fn positive_support(&self, i: usize, v: B) -> bool {
+    match self.value_index(i, v) {
+      Some(k) => intersects(&self.current, &self.data.supports[i][k]),
+      None => false
+    }
+  }
+
+  // A value is supported in a negative table if the valid tuples containing it do not cover all the combinations of values of the other variables.
+  fn negative_support(&self, i: usize, v: B, others: u64) -> bool {
+    match self.value_index(i, v) {
+      Some(k) => intersect_count(&self.current, &self.data.supports[i][k]) < others,
+      None => true
+    }
+  }
+
+  // First supported value of `x_i` in `[l..u]`. Only the values of the tuples are visited since the other values are never supported in a positive table and always supported in a negative one.
+  fn first_support(&self, i: usize, l: B, u: B, others: u64) -> Option<B> {
+    let values = &self.data.values[i];
+    let start = match values.binary_search(&l) { Ok(k) | Err(k) => k };
+    let mut table_values = values[start..].iter().cloned().take_while(|&v| v <= u);
+    if self.positive {
+      return table_values.find(|&v| self.positive_support(i, v));
+    }
+    let mut v = l;
+    for w in table_values {
+      if w != v || self.negative_support(i, w, others) {
+        return Some(v);
+      }
+      if v == u { return None; }
+      v = v + B::one();
+    }
+    Some(v)
+  }
+
+  // Last supported value of `x_i` in `[l..u]`, symmetric to `first_support`.
+  fn last_support(&self, i: usize, l: B, u: B, others: u64) -> Option<B> {
+    let values = &self.data.values[i];
+    let end = match values.binary_search(&u) { Ok(k) => k + 1, Err(k) => k };
+    let mut table_values = values[..end].iter().cloned().rev().take_while(|&v| v >= l);
+    if self.positive {
+      return table_values.find(|&v| self.positive_support(i, v));
+    }
+    let mut v = u;
+    for w in table_values {
+      if w != v || self.negative_support(i, w, others) {
+        return Some(v);
+      }
+      if v == l { return None; }
+      v = v - B::one();
+    }
+    Some(v)
+  }
+
+  // Tuples valid in `bounds`, obtained from the bitset of the last propagation if the domains only shrank since then.
+  fn valid_tuples(&self, bounds: &Vec<(B, B)>) -> Bitset {
+    let shrank = self.last_bounds.iter().zip(bounds.iter())
+      .all(|(old, &(l, u))| old.map_or(false, |(old_l, old_u)| old_l <= l && u <= old_u));
+    let (mut bits, last_bounds) =
+      if shrank { (self.current.clone(), self.last_bounds.clone()) }
+      else { (bitset_full(self.data.tuples.len()), vec![None; bounds.len()]) };
+    for (i, &(l, u)) in bounds.iter().enumerate() {
+      self.data.remove_values(&mut bits, i, last_bounds[i], l, u);
+    }
+    bits
+  }
+}
+
+// Number of values in `[l..u]`, saturated to `u64::MAX`.
+fn domain_size<B: PrimInt>(l: B, u: B) -> u64 {
+  u.checked_sub(&l)
+    .and_then(|s| s.to_u64())
+    .map_or(u64::max_value(), |s| s.saturating_add(1))
+}
+
+impl<V, B> Debug for Table<V, B> where
+  V: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    let kind = if self.positive { "allowed" } else { "forbidden" };
+    formatter.write_fmt(format_args!("table({:?} {} {:?})", self.vars, kind, self.data.tuples))
+  }
+}
+
+impl<Store, Domain, V, B> Subsumption<Store> for Table<V, B> where
+  V: StoreRead<Store, Value=Domain>,
+  Domain: Bounded<Bound=B>,
+  B: PrimInt
+{
+  fn is_subsumed(&self, store: &Store) -> Trilean {
+    let bounds = read_bounds(&self.vars, store);
+    let valid = bitset_count(&self.valid_tuples(&bounds));
+    let product = bounds.iter()
+      .fold(1u64, |p, &(l, u)| p.saturating_mul(domain_size(l, u)));
+    // Every combination of values is allowed (resp. forbidden) if all of them are in the valid tuples.
+    let all = valid == product;
+    match (self.positive, valid == 0, all) {
+      (true, true, _) => False,
+      (true, _, true) => True,
+      (false, true, _) => True,
+      (false, _, true) => False,
+      _ => Unknown
+    }
+  }
+}
+
+impl<Store, Domain, V, B> Propagator<Store> for Table<V, B> where
+  V: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>,
+  Domain: Bounded<Bound=B> + ShrinkLeft<B> + ShrinkRight<B>,
+  B: PrimInt
+{
+  fn propagate(&mut self, store: &mut Store) -> bool {
+    let bounds = read_bounds(&self.vars, store);
+    for i in 0..self.vars.len() {
+      let (l, u) = bounds[i];
+      let old = self.last_bounds[i];
+      if old != Some((l, u)) {
+        self.data.remove_values(&mut self.current, i, old, l, u);
+        self.last_bounds[i] = Some((l, u));
+      }
+    }
+    if self.positive && bitset_is_empty(&self.current) {
+      return false;
+    }
+    let sizes: Vec<u64> = bounds.iter().map(|&(l, u)| domain_size(l, u)).collect();
+    for i in 0..self.vars.len() {
+      let (l, u) = bounds[i];
+      // Number of combinations of values of the other variables.
+      let others = sizes.iter().enumerate()
+        .filter(|&(j, _)| j != i)
+        .fold(1u64, |p, (_, &s)| p.saturating_mul(s));
+      let new_l = match self.first_support(i, l, u, others) {
+        Some(new_l) => new_l,
+        None => return false
+      };
+      let new_u = self.last_support(i, new_l, u, others).unwrap_or(new_l);
+      if new_l != l || new_u != u {
+        let x = &self.vars[i];
+        let dom = x.read(store);
+        if !x.update(store, dom.shrink_left(new_l).shrink_right(new_u)) {
+          return false;
+        }
+      }
+    }
+    true
+  }
+}
+
+impl<V, B> PropagatorDependencies<FDEvent> for Table<V, B> where
+  V: ViewDependencies<FDEvent>
+{
+  fn dependencies(&self) -> Vec<(usize, FDEvent)> {
+    // Only the bounds of the domains are read.
+    self.vars.iter().flat_map(|v| v.dependencies(FDEvent::Bound)).collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use kernel::*;
+  use kernel::Trilean::*;
+  use propagation::events::*;
+  use propagation::events::FDEvent::*;
+  use interval::interval::*;
+  use propagators::test::*;
+
+  fn tuples() -> Vec<Vec<i32>> {
+    vec![vec![0,1,2], vec![1,1,3], vec![2,0,0], vec![3,2,1], vec![1,2,2]]
+  }
+
+  #[test]
+  fn positive_table_test() {
+    let dom0_3 = (0,3).to_interval();
+    let dom0_1 = (0,1).to_interval();
+    let dom1_2 = (1,2).to_interval();
+    let dom2_3 = (2,3).to_interval();
+    let dom5_6 = (5,6).to_interval();
+    let one = (1,1).to_interval();
+    let three = (3,3).to_interval();
+
+    table_test_one(1, true, vec![dom0_3, dom0_3, dom0_3], Unknown, Unknown, vec![(1, Bound)], true);
+    table_test_one(2, true, vec![dom0_3, dom1_2, dom2_3], Unknown, Unknown, vec![(0, Bound)], true);
+    table_test_one(3, true, vec![dom0_3, dom0_1, dom0_3], Unknown, Unknown, vec![(0, Bound)], true);
+    table_test_one(4, true, vec![three, dom0_3, dom0_3], Unknown, True, vec![(1, Assignment), (2, Assignment)], true);
+    table_test_one(5, true, vec![dom5_6, dom0_3, dom0_3], False, False, vec![], false);
+    table_test_one(6, true, vec![one, one, three], True, True, vec![], true);
+  }
+
+  #[test]
+  fn negative_table_test() {
+    let dom0_1 = (0,1).to_interval();
+    let dom0_3 = (0,3).to_interval();
+    let dom1_2 = (1,2).to_interval();
+    let zero = (0,0).to_interval();
+    let one = (1,1).to_interval();
+    let two = (2,2).to_interval();
+
+    table_test_one(1, false, vec![dom0_3, dom0_3, dom0_3], Unknown, Unknown, vec![], true);
+    // (1,1,3) is forbidden so z != 3.
+    table_test_one(2, false, vec![one, one, dom1_2], True, True, vec![], true);
+    table_test_one(3, false, vec![one, one, dom0_3], Unknown, True, vec![(2, Bound)], true);
+    table_test_one(4, false, vec![zero, one, two], False, False, vec![], false);
+    // (1,2,2) is forbidden so y = 1.
+    table_test_one(5, false, vec![one, dom1_2, two], Unknown, True, vec![(1, Assignment)], true);
+    table_test_one(6, false, vec![dom0_1, one, two], Unknown, True, vec![(0, Assignment)], true);
+  }
+
+  fn table_test_one(test_num: u32, positive: bool, doms: Vec<Interval<i32>>,
+    before: Trilean, after: Trilean,
+    delta_expected: Vec<(usize, FDEvent)>, propagate_success: bool)
+  {
+    nary_propagator_test(test_num,
+      |vars| if positive { Table::allowed(vars, tuples()) } else { Table::forbidden(vars, tuples()) },
+      doms, before, after, delta_expected, propagate_success);
+  }
+
+  // Only the values of the tuples are visited, so the bounds of the domains can be far apart or at the limits of the intervals.
+  #[test]
+  fn extreme_domains_test() {
+    let (min, max) = (i32::min_value() + 1, i32::max_value());
+    nary_propagator_test(1, |vars| Table::allowed(vars, vec![vec![-5, 7], vec![1000, 3]]),
+      vec![(min, max).to_interval(), (0, 10).to_interval()], Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    nary_propagator_test(2, |vars| Table::forbidden(vars, vec![vec![min], vec![min + 1]]),
+      vec![(min, max).to_interval()], Unknown, True, vec![(0, Bound)], true);
+    nary_propagator_test(3, |vars| Table::forbidden(vars, vec![vec![max - 1], vec![max]]),
+      vec![(max - 1, max).to_interval()], False, False, vec![], false);
+  }
+
+  #[test]
+  fn exhaustive_table() {
+    let tuples = tuples();
+    for &positive in &[true, false] {
+      for doms in interval_tuples(&intervals(0, 3), 3) {
+        let result = fixpoint(|vars|
+          if positive { Table::allowed(vars, tuples.clone()) }
+          else { Table::forbidden(vars, tuples.clone()) }, &doms);
+        let expected = solutions_hull(&doms, |w| tuples.iter().any(|t| &t[..] == w) == positive);
+        assert_eq!(result, expected,
+          "{} table with domains {:?}", if positive { "positive" } else { "negative" }, doms);
+      }
+    }
+  }
+}
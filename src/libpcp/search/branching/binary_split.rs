@@ -22,6 +22,7 @@ use propagators::cmp::*;
 use gcollections::ops::*;
 use num::traits::Num;
 use num::PrimInt;
+use std::fmt::Debug;
 
 pub struct BinarySplit;
 
@@ -35,7 +36,7 @@ impl<VStore, CStore, Domain, Bound> Distributor<Space<VStore, CStore>> for Binar
   CStore: Alloc<XLessEqC<Domain, Bound>>,
   CStore: Alloc<XGreaterC<Domain, Bound>>,
   Domain: Clone + Cardinality + Bounded<Bound=Bound> + 'static,
-  Bound: PrimInt + Num + PartialOrd + Clone + Bounded<Bound=Bound> + 'static
+  Bound: PrimInt + Num + PartialOrd + Clone + Bounded<Bound=Bound> + Debug + 'static
 {
   fn distribute(&mut self, space: Space<VStore, CStore>, var_idx: usize) ->
     (<Space<VStore, CStore> as Freeze>::FrozenState, Vec<Branch<Space<VStore, CStore>>>)
@@ -43,13 +44,17 @@ impl<VStore, CStore, Domain, Bound> Distributor<Space<VStore, CStore>> for Binar
     let dom = nth_dom(&space.vstore, var_idx);
     assert!(!dom.is_singleton() && !dom.is_empty(),
       "Can not distribute over assigned or failed variables.");
-    let mid = (dom.lower() + dom.upper()) / (Bound::one() + Bound::one());
+    // Rounds towards the lower bound so both branches shrink the domain, even when it contains negative values.
+    let mid = dom.lower() + (dom.upper() - dom.lower()) / (Bound::one() + Bound::one());
+    let descriptions = vec![
+      format!("_{} <= {:?}", var_idx, mid),
+      format!("_{} > {:?}", var_idx, mid)];
     let mid = Constant::new(mid);
     let x = Identity::<Domain>::new(var_idx);
     let x_less_mid = x_leq_y(x.clone(), mid.clone());
     let x_geq_mid = x_greater_y(x, mid);
 
-    Branch::distribute(space,
+    let (immutable_state, branches) = Branch::distribute(space,
       vec![
         Box::new(move |space: &mut Space<VStore, CStore>| {
           space.cstore.alloc(x_less_mid);
@@ -58,7 +63,12 @@ impl<VStore, CStore, Domain, Bound> Distributor<Space<VStore, CStore>> for Binar
           space.cstore.alloc(x_geq_mid);
         })
       ]
-    )
+    );
+    let branches = branches.into_iter()
+      .zip(descriptions.into_iter())
+      .map(|(branch, description)| branch.with_description(description))
+      .collect();
+    (immutable_state, branches)
   }
 }
 
@@ -133,6 +143,23 @@ mod test {
     );
   }
 
+  #[test]
+  fn binary_split_negative_distribution() {
+    let vars = vec![(-1,0),(-5,0),(-3,2)];
+    test_distributor(BinarySplit, 0,
+      vars.clone(),
+      vec![(-1,-1),(0,0)]
+    );
+    test_distributor(BinarySplit, 1,
+      vars.clone(),
+      vec![(-5,-3),(-2,0)]
+    );
+    test_distributor(BinarySplit, 2,
+      vars.clone(),
+      vec![(-3,-1),(0,2)]
+    );
+  }
+
   #[test]
   #[should_panic]
   fn binary_split_impossible_distribution() {
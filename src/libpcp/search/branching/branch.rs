@@ -21,12 +21,15 @@ use alloc::boxed::FnBox;
 // several branches can share the same data until calling `restore`.
 // We don't store the propagators but instead a closure that
 // add the propagator(s) to the new space, when available.
+// A branch can also carry a human-readable description of its
+// alternative (e.g. `x <= 5`), used for tracing the search tree.
 
 pub struct Branch<Space> where
   Space: Freeze
 {
   label: <Space::FrozenState as Snapshot>::Label,
-  alternative: Box<FnBox(&mut Space)>
+  alternative: Box<FnBox(&mut Space)>,
+  description: String
 }
 
 impl<Space> Branch<Space> where
@@ -37,12 +40,39 @@ impl<Space> Branch<Space> where
     let branches = alternatives.into_iter().map(|alt|
       Branch {
         label: immutable_space.label(),
-        alternative: alt
+        alternative: alt,
+        description: String::new()
       }
     ).collect();
     (immutable_space, branches)
   }
 
+  /// Executes `action` on the space restored by `commit`, before the alternative of this branch is applied.
+  pub fn on_commit<F>(self, action: F) -> Branch<Space> where
+    F: FnOnce(&mut Space) + 'static,
+    Space: 'static
+  {
+    let alternative = self.alternative;
+    Branch {
+      label: self.label,
+      alternative: Box::new(move |space: &mut Space| {
+        action(space);
+        alternative.call_once((space,));
+      }),
+      description: self.description
+    }
+  }
+
+  pub fn with_description(mut self, description: String) -> Branch<Space> {
+    self.description = description;
+    self
+  }
+
+  /// Description of the alternative, empty if the distributor did not provide one.
+  pub fn description(&self) -> &str {
+    &self.description
+  }
+
   pub fn commit(self, space_from: Space::FrozenState) -> Space {
     let mut new = space_from.restore(self.label);
     self.alternative.call_once((&mut new,));
@@ -0,0 +1,207 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use search::branching::branch::*;
+use gcollections::ops::multiset::*;
+use std::mem;
+use std::usize;
+
+/// Explores the whole search tree and records the label of every solution encountered. The search can be bounded to the first `n` solutions with `AllSolutions::n_solutions`.
+///
+/// If the search is stopped, for example by a `Limit` or the bound on the number of solutions, the status `EndOfSearch` is returned and the solutions found so far are still available.
+///
+/// Solutions are restored by calling `restore` on any frozen state (for example the one returned by `enter`) with a label obtained from `solutions` or `take_solutions`.
+pub struct AllSolutions<C, Q, Space> where
+ Space: Freeze
+{
+  child: C,
+  queue: Q,
+  exploring: bool,
+  limit: usize,
+  solutions: Vec<<Space::FrozenState as Snapshot>::Label>
+}
+
+impl<C, Q, Space> AllSolutions<C, Q, Space> where
+ Space: Freeze,
+ C: SearchTreeVisitor<Space>,
+ Q: Multiset<Branch<Space>>
+{
+  pub fn new(child: C) -> AllSolutions<C, Q, Space>
+  {
+    AllSolutions::n_solutions(usize::MAX, child)
+  }
+
+  /// Stops the search as soon as `limit` solutions have been found. In this case `enter` returns `EndOfSearch` unless the tree happens to be fully explored, and calling it again resumes the exploration until `limit` more solutions are found.
+  pub fn n_solutions(limit: usize, child: C) -> AllSolutions<C, Q, Space>
+  {
+    assert!(limit > 0, "The number of solutions to search for must be positive.");
+    AllSolutions {
+      child: child,
+      queue: Q::empty(),
+      exploring: false,
+      limit: limit,
+      solutions: vec![]
+    }
+  }
+
+  /// Labels of the solutions found so far, in the order of their discovery.
+  pub fn solutions(&self) -> &[<Space::FrozenState as Snapshot>::Label] {
+    &self.solutions
+  }
+
+  pub fn take_solutions(&mut self) -> Vec<<Space::FrozenState as Snapshot>::Label> {
+    mem::replace(&mut self.solutions, vec![])
+  }
+
+  fn push_branches(&mut self, branches: Vec<Branch<Space>>)
+  {
+    for branch in branches {
+      self.queue.insert(branch);
+    }
+  }
+
+  fn enter_child(&mut self, current: Space, status: Status<Space>) -> (Space::FrozenState, Status<Space>)
+  {
+    let (mut immutable_state, child_status) = self.child.enter(current);
+    let status = status.or(&child_status);
+    match child_status {
+      Unknown(branches) => self.push_branches(branches),
+      Satisfiable => self.solutions.push(immutable_state.label()),
+      _ => ()
+    }
+    (immutable_state, status)
+  }
+
+  // Only visit the root if we didn't visit it before (based on the queue emptiness).
+  fn enter_root(&mut self, root: Space, status: Status<Space>) -> (Space::FrozenState, Status<Space>)
+  {
+    if self.queue.is_empty() && !self.exploring {
+      self.exploring = true;
+      self.enter_child(root, status)
+    } else {
+      (root.freeze(), status)
+    }
+  }
+}
+
+impl<C, Q, Space> SearchTreeVisitor<Space> for AllSolutions<C, Q, Space> where
+ Space: Freeze,
+ C: SearchTreeVisitor<Space>,
+ Q: Multiset<Branch<Space>>
+{
+  fn start(&mut self, root: &Space) {
+    self.queue = Q::empty();
+    self.exploring = false;
+    self.solutions = vec![];
+    self.child.start(root);
+  }
+
+  fn enter(&mut self, root: Space) -> (Space::FrozenState, Status<Space>) {
+    let found_before = self.solutions.len();
+    let (mut immutable_state, mut status) = self.enter_root(root, Unsatisfiable);
+    while self.solutions.len() - found_before < self.limit
+      && status != EndOfSearch
+      && !self.queue.is_empty()
+    {
+      let branch = self.queue.extract().unwrap();
+      let child = branch.commit(immutable_state);
+      let (next_state, next_status) = self.enter_child(child, status);
+      immutable_state = next_state;
+      status = next_status;
+    }
+    // The limit of solutions was reached before the tree was fully explored.
+    if !self.queue.is_empty() {
+      status = EndOfSearch;
+    }
+    (immutable_state, status)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use variable::ops::*;
+  use search::space::*;
+  use search::propagation::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use gcollections::VectorStack;
+  use gcollections::ops::*;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+  type Engine<C> = AllSolutions<C, VectorStack<Branch<FDSpace>>, FDSpace>;
+
+  #[test]
+  fn example_nqueens_all_solutions() {
+    // Number of solutions of the n-queens problem for n = 1..8.
+    let expected = vec![1, 0, 0, 2, 10, 4, 40, 92];
+    for (i, &num_solutions) in expected.iter().enumerate() {
+      let status = if num_solutions > 0 { Satisfiable } else { Unsatisfiable };
+      nqueens(i + 1, usize::MAX, num_solutions, status);
+    }
+  }
+
+  #[test]
+  fn example_nqueens_n_solutions() {
+    nqueens(8, 1, 1, EndOfSearch);
+    nqueens(8, 10, 10, EndOfSearch);
+    nqueens(8, 100, 92, Satisfiable);
+    nqueens(3, 5, 0, Unsatisfiable);
+  }
+
+  #[test]
+  fn resume_n_solutions() {
+    let space = nqueens_space(6);
+    let mut search: Engine<_> = AllSolutions::n_solutions(3,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    search.start(&space);
+    let (mut frozen, status) = search.enter(space);
+    assert_eq!(status, EndOfSearch);
+    assert_eq!(search.solutions().len(), 3);
+    let label = frozen.label();
+    let (_, status) = search.enter(frozen.restore(label));
+    assert_eq!(status, Satisfiable);
+    assert_eq!(search.take_solutions().len(), 4);
+  }
+
+  fn nqueens(n: usize, limit: usize, expected: usize, expected_status: Status<FDSpace>) {
+    let space = nqueens_space(n);
+    let mut search: Engine<_> = AllSolutions::n_solutions(limit,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    search.start(&space);
+    let (mut frozen, status) = search.enter(space);
+    assert_eq!(status, expected_status);
+    let solutions = search.take_solutions();
+    assert_eq!(solutions.len(), expected);
+    // Every label must restore an assignment of the queens.
+    for solution in solutions {
+      let space = frozen.restore(solution);
+      assert!(space.vstore.iter().all(|dom| dom.is_singleton()));
+      frozen = space.freeze();
+    }
+  }
+}
@@ -0,0 +1,266 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use search::space::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use search::branching::branch::*;
+use propagators::cmp::*;
+use term::*;
+use term::ops::*;
+use gcollections::ops::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Direction {
+  Minimize,
+  Maximize
+}
+
+/// Optimizes the value of an objective view. Each time a solution is found, the branches remaining in the queue are constrained to be strictly better than this solution.
+///
+/// The search stops when the queue is exhausted or when a child returns `EndOfSearch`. The best solution is obtained with `take_best_solution` and it is proved optimal if `is_optimal` returns `true` (the search was neither pruned nor stopped).
+pub struct BranchAndBound<C, Q, Space, Obj, Bound> where
+ Space: Freeze
+{
+  child: C,
+  queue: Q,
+  objective: Obj,
+  direction: Direction,
+  best: Option<(<Space::FrozenState as Snapshot>::Label, Bound)>,
+  optimal: bool
+}
+
+impl<C, Q, Space, Obj, Bound> BranchAndBound<C, Q, Space, Obj, Bound> where
+ Space: Freeze
+{
+  pub fn minimize(objective: Obj, child: C) -> Self where
+   Q: Empty
+  {
+    BranchAndBound::new(objective, Direction::Minimize, child)
+  }
+
+  pub fn maximize(objective: Obj, child: C) -> Self where
+   Q: Empty
+  {
+    BranchAndBound::new(objective, Direction::Maximize, child)
+  }
+
+  fn new(objective: Obj, direction: Direction, child: C) -> Self where
+   Q: Empty
+  {
+    BranchAndBound {
+      child: child,
+      queue: Q::empty(),
+      objective: objective,
+      direction: direction,
+      best: None,
+      optimal: false
+    }
+  }
+
+  /// Value of the objective in the best solution found so far.
+  pub fn best_value(&self) -> Option<Bound> where
+   Bound: Clone
+  {
+    self.best.as_ref().map(|&(_, ref value)| value.clone())
+  }
+
+  /// Label of the best solution found, it can be restored from any frozen space.
+  pub fn take_best_solution(&mut self) -> Option<<Space::FrozenState as Snapshot>::Label> {
+    self.best.take().map(|(label, _)| label)
+  }
+
+  /// `true` if the whole search tree was explored without pruning and a solution was found.
+  pub fn is_optimal(&self) -> bool {
+    self.optimal
+  }
+}
+
+impl<C, Q, VStore, CStore, Obj, Domain, Bound> BranchAndBound<C, Q, Space<VStore, CStore>, Obj, Bound> where
+ VStore: Freeze,
+ CStore: Freeze,
+ CStore: Alloc<XLessY<Obj, Constant<Bound>>>,
+ CStore: Alloc<XGreaterY<Obj, Constant<Bound>>>,
+ C: SearchTreeVisitor<Space<VStore, CStore>>,
+ Q: Multiset<Branch<Space<VStore, CStore>>>,
+ Obj: StoreRead<VStore, Value=Domain> + Clone,
+ Domain: Bounded<Bound=Bound>,
+ Bound: Clone
+{
+  fn push_branches(&mut self, branches: Vec<Branch<Space<VStore, CStore>>>)
+  {
+    for branch in branches {
+      self.queue.insert(branch);
+    }
+  }
+
+  fn enter_child(&mut self, current: Space<VStore, CStore>, pruned: &mut bool, stopped: &mut bool)
+    -> <Space<VStore, CStore> as Freeze>::FrozenState
+  {
+    let (immutable_state, child_status) = self.child.enter(current);
+    match child_status {
+      Unknown(ref branches) if branches.is_empty() => { *pruned = true; immutable_state }
+      EndOfSearch => { *stopped = true; immutable_state }
+      Unknown(branches) => { self.push_branches(branches); immutable_state }
+      Satisfiable => self.update_best(immutable_state),
+      _ => immutable_state
+    }
+  }
+
+  // Record the solution `immutable_state` as the best one. The objective value is read in the solution which is restored and then frozen again.
+  fn update_best(&mut self, mut immutable_state: <Space<VStore, CStore> as Freeze>::FrozenState)
+    -> <Space<VStore, CStore> as Freeze>::FrozenState
+  {
+    let solution = immutable_state.label();
+    let label = immutable_state.label();
+    let space = immutable_state.restore(label);
+    let objective = self.objective.read(&space.vstore);
+    let value = match self.direction {
+      Direction::Minimize => objective.lower(),
+      Direction::Maximize => objective.upper()
+    };
+    self.best = Some((solution, value));
+    space.freeze()
+  }
+
+  // The space must improve on the best solution found so far.
+  fn post_bound(&self, space: &mut Space<VStore, CStore>) {
+    if let Some((_, ref value)) = self.best {
+      let bound = Constant::new(value.clone());
+      match self.direction {
+        Direction::Minimize => { space.cstore.alloc(XLessY::new(self.objective.clone(), bound)); }
+        Direction::Maximize => { space.cstore.alloc(x_greater_y(self.objective.clone(), bound)); }
+      }
+    }
+  }
+}
+
+impl<C, Q, VStore, CStore, Obj, Domain, Bound> SearchTreeVisitor<Space<VStore, CStore>>
+  for BranchAndBound<C, Q, Space<VStore, CStore>, Obj, Bound> where
+ VStore: Freeze,
+ CStore: Freeze,
+ CStore: Alloc<XLessY<Obj, Constant<Bound>>>,
+ CStore: Alloc<XGreaterY<Obj, Constant<Bound>>>,
+ C: SearchTreeVisitor<Space<VStore, CStore>>,
+ Q: Multiset<Branch<Space<VStore, CStore>>>,
+ Obj: StoreRead<VStore, Value=Domain> + Clone,
+ Domain: Bounded<Bound=Bound>,
+ Bound: Clone
+{
+  fn start(&mut self, root: &Space<VStore, CStore>) {
+    self.queue = Q::empty();
+    self.best = None;
+    self.optimal = false;
+    self.child.start(root);
+  }
+
+  fn enter(&mut self, root: Space<VStore, CStore>)
+    -> (<Space<VStore, CStore> as Freeze>::FrozenState, Status<Space<VStore, CStore>>)
+  {
+    let mut pruned = false;
+    let mut stopped = false;
+    let mut immutable_state = self.enter_child(root, &mut pruned, &mut stopped);
+    while !stopped && !self.queue.is_empty() {
+      let mut child = self.queue.extract().unwrap().commit(immutable_state);
+      self.post_bound(&mut child);
+      immutable_state = self.enter_child(child, &mut pruned, &mut stopped);
+    }
+    self.optimal = self.best.is_some() && !pruned && !stopped;
+    let status =
+      if stopped { EndOfSearch }
+      else if self.best.is_some() { Satisfiable }
+      else if pruned { Status::pruned() }
+      else { Unsatisfiable };
+    (immutable_state, status)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use search::propagation::*;
+  use search::limit::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use gcollections::VectorStack;
+
+  type Domain = DomainI32;
+  type FDVar = Identity<Domain>;
+
+  #[test]
+  fn optimize_first_queen() {
+    // The solutions of the 6-queens problem are [2,4,6,1,3,5], [3,6,2,5,1,4], [4,1,5,2,6,3] and [5,3,1,6,4,2].
+    optimize_queen(6, 0, true, Some(2));
+    optimize_queen(6, 0, false, Some(5));
+    optimize_queen(6, 2, true, Some(1));
+    optimize_queen(6, 2, false, Some(6));
+    optimize_queen(8, 0, false, Some(8));
+    optimize_queen(3, 0, true, None);
+  }
+
+  #[test]
+  fn optimize_view() {
+    let space = nqueens_space(6);
+    let objective = Addition::new(FDVar::new(1), 10);
+    let mut search: BranchAndBound<_, VectorStack<_>, _, _, _> = BranchAndBound::maximize(objective,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, Satisfiable);
+    assert_eq!(search.best_value(), Some(16));
+    assert!(search.is_optimal());
+  }
+
+  #[test]
+  fn stopped_search_is_not_optimal() {
+    let space = nqueens_space(8);
+    let mut search: BranchAndBound<_, VectorStack<_>, _, _, _> = BranchAndBound::minimize(FDVar::new(0),
+      Limit::node_limit(40, Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))));
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, EndOfSearch);
+    assert!(!search.is_optimal());
+  }
+
+  fn optimize_queen(n: usize, queen: usize, minimize: bool, expected: Option<i32>) {
+    let space = nqueens_space(n);
+    let objective = FDVar::new(queen);
+    let brancher = Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit));
+    let mut search: BranchAndBound<_, VectorStack<_>, _, _, _> =
+      if minimize { BranchAndBound::minimize(objective, brancher) }
+      else { BranchAndBound::maximize(objective, brancher) };
+    search.start(&space);
+    let (frozen, status) = search.enter(space);
+    assert_eq!(search.best_value(), expected);
+    match expected {
+      Some(value) => {
+        assert_eq!(status, Satisfiable);
+        assert!(search.is_optimal());
+        let space = frozen.restore(search.take_best_solution().unwrap());
+        assert_eq!(objective.read(&space.vstore), (value, value).to_interval());
+      }
+      None => {
+        assert_eq!(status, Unsatisfiable);
+        assert!(!search.is_optimal());
+        assert!(search.take_best_solution().is_none());
+      }
+    }
+  }
+}
@@ -0,0 +1,254 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Limited discrepancy search (LDS). The first alternative of a branching is considered as the choice of the heuristic, and taking any other alternative counts as a *discrepancy*. Nodes are explored in depth-first order as long as the number of discrepancies on their path does not exceed a bound. The iterative variant restarts from the root with a bound incremented by one until no branch is cut, so the leaves are visited in increasing order of discrepancy.
+
+use kernel::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use search::branching::branch::*;
+
+pub struct LDS<C, Space> where
+ Space: Freeze
+{
+  child: C,
+  stack: Vec<(Branch<Space>, usize)>,
+  root: Option<<Space::FrozenState as Snapshot>::Label>,
+  iterative: bool,
+  max_discrepancy: usize,
+  // Solutions with fewer discrepancies were already reported during the previous iterations.
+  min_reported: usize,
+  // A branch has been cut during the current iteration.
+  cut: bool
+}
+
+impl<C, Space> LDS<C, Space> where
+ Space: Freeze,
+ <Space::FrozenState as Snapshot>::Label: Clone,
+ C: SearchTreeVisitor<Space>
+{
+  /// Explores the nodes with at most `max_discrepancy` discrepancies. If a branch has been cut, the search returns `Status::pruned()` instead of `Unsatisfiable` once it is over.
+  pub fn new(max_discrepancy: usize, child: C) -> LDS<C, Space> {
+    LDS::init(false, max_discrepancy, child)
+  }
+
+  /// Iterative LDS, starting with a bound of 0 discrepancy. Every solution is reported once, during the iteration matching its number of discrepancies.
+  pub fn iterative(child: C) -> LDS<C, Space> {
+    LDS::init(true, 0, child)
+  }
+
+  fn init(iterative: bool, max_discrepancy: usize, child: C) -> LDS<C, Space> {
+    LDS {
+      child: child,
+      stack: vec![],
+      root: None,
+      iterative: iterative,
+      max_discrepancy: max_discrepancy,
+      min_reported: 0,
+      cut: false
+    }
+  }
+
+  /// Current bound on the number of discrepancies.
+  pub fn max_discrepancy(&self) -> usize {
+    self.max_discrepancy
+  }
+
+  // The branches are pushed in reverse order so the first alternative is explored first.
+  fn push_branches(&mut self, branches: Vec<Branch<Space>>, discrepancy: usize) {
+    for (i, branch) in branches.into_iter().enumerate().rev() {
+      let discrepancy = if i == 0 { discrepancy } else { discrepancy + 1 };
+      if discrepancy <= self.max_discrepancy {
+        self.stack.push((branch, discrepancy));
+      }
+      else {
+        self.cut = true;
+      }
+    }
+  }
+
+  fn enter_child(&mut self, current: Space, discrepancy: usize, status: &mut Status<Space>) -> Space::FrozenState {
+    let (immutable_state, child_status) = self.child.enter(current);
+    match child_status {
+      Unknown(ref branches) if branches.is_empty() => *status = Status::pruned(),
+      Unknown(branches) => self.push_branches(branches, discrepancy),
+      Satisfiable if discrepancy >= self.min_reported => *status = Satisfiable,
+      EndOfSearch => *status = EndOfSearch,
+      _ => ()
+    }
+    immutable_state
+  }
+
+  fn enter_root(&mut self, immutable_state: Space::FrozenState, status: &mut Status<Space>) -> Space::FrozenState {
+    self.stack.clear();
+    self.cut = false;
+    let root_label = self.root.clone().expect("The root must be labelled before an iteration.");
+    let root = immutable_state.restore(root_label);
+    self.enter_child(root, 0, status)
+  }
+}
+
+impl<C, Space> SearchTreeVisitor<Space> for LDS<C, Space> where
+ Space: Freeze,
+ <Space::FrozenState as Snapshot>::Label: Clone,
+ C: SearchTreeVisitor<Space>
+{
+  fn start(&mut self, root: &Space) {
+    self.stack.clear();
+    self.root = None;
+    self.cut = false;
+    if self.iterative {
+      self.max_discrepancy = 0;
+    }
+    self.min_reported = 0;
+    self.child.start(root);
+  }
+
+  fn enter(&mut self, current: Space) -> (Space::FrozenState, Status<Space>) {
+    let mut status = Unsatisfiable;
+    let mut immutable_state = current.freeze();
+    if self.root.is_none() {
+      self.root = Some(immutable_state.label());
+      immutable_state = self.enter_root(immutable_state, &mut status);
+    }
+    loop {
+      while status != Satisfiable && status != EndOfSearch {
+        match self.stack.pop() {
+          Some((branch, discrepancy)) => {
+            let child = branch.commit(immutable_state);
+            immutable_state = self.enter_child(child, discrepancy, &mut status);
+          }
+          None => break
+        }
+      }
+      if status == Satisfiable || status == EndOfSearch || !self.cut {
+        break;
+      }
+      else if self.iterative {
+        self.max_discrepancy += 1;
+        self.min_reported = self.max_discrepancy;
+        immutable_state = self.enter_root(immutable_state, &mut status);
+      }
+      else {
+        status = Status::pruned();
+        break;
+      }
+    }
+    (immutable_state, status)
+  }
+}
+
+impl<C, Space> ResumableSearch<Space> for LDS<C, Space> where
+ Space: Freeze,
+ <Space::FrozenState as Snapshot>::Label: Clone,
+ C: SearchTreeVisitor<Space>
+{}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::cmp::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use search::space::*;
+  use search::solutions::*;
+  use search::propagation::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use gcollections::ops::*;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  // `x = y` with x,y in [1..8]. The binary splits form a complete tree of depth 3 where the leaf `v` has as many discrepancies as there are right branches in its path, for example 1 for the leaf 5 and 2 for the leaf 4.
+  fn equal_space() -> FDSpace {
+    let mut space = FDSpace::empty();
+    let x = space.vstore.alloc((1, 8).to_interval());
+    let y = space.vstore.alloc((1, 8).to_interval());
+    space.cstore.alloc(XEqY::new(x, y));
+    space
+  }
+
+  fn first_values(engine: LDS<Propagation<Brancher<FirstSmallestVar, BinarySplit>>, FDSpace>) -> Vec<i32> {
+    Solutions::new(engine, equal_space())
+      .map(|solution| solution[0].lower())
+      .collect()
+  }
+
+  fn lds(max_discrepancy: usize) -> LDS<Propagation<Brancher<FirstSmallestVar, BinarySplit>>, FDSpace> {
+    LDS::new(max_discrepancy, Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)))
+  }
+
+  fn ilds() -> LDS<Propagation<Brancher<FirstSmallestVar, BinarySplit>>, FDSpace> {
+    LDS::iterative(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)))
+  }
+
+  #[test]
+  fn bounded_discrepancy() {
+    assert_eq!(first_values(lds(0)), vec![1]);
+    assert_eq!(first_values(lds(1)), vec![1,2,3,5]);
+    assert_eq!(first_values(lds(2)), vec![1,2,3,4,5,6,7]);
+    assert_eq!(first_values(lds(3)), vec![1,2,3,4,5,6,7,8]);
+    assert_eq!(first_values(lds(10)), vec![1,2,3,4,5,6,7,8]);
+  }
+
+  #[test]
+  fn incomplete_search_is_pruned() {
+    let mut space = equal_space();
+    let mut search = lds(1);
+    search.start(&space);
+    // The solutions 1, 2, 3 and 5 are found but the leaves 4, 6, 7 and 8 are cut.
+    for _ in 0..4 {
+      let (mut frozen, status) = search.enter(space);
+      assert_eq!(status, Satisfiable);
+      let label = frozen.label();
+      space = frozen.restore(label);
+    }
+    let (_, status) = search.enter(space);
+    assert_eq!(status, Status::pruned());
+  }
+
+  #[test]
+  fn iterative_discrepancy_order() {
+    // The leaf 5 (right then left twice) has fewer discrepancies than the leaf 4 (left then right twice) which comes first in depth-first order.
+    assert_eq!(first_values(ilds()), vec![1,2,3,5,4,6,7,8]);
+    let space = equal_space();
+    let mut search = ilds();
+    search.start(&space);
+    search.enter(space);
+    assert_eq!(search.max_discrepancy(), 0);
+  }
+
+  #[test]
+  fn iterative_nqueens() {
+    for n in 1..9 {
+      let expected = if n == 2 || n == 3 { Unsatisfiable } else { Satisfiable };
+      let space = nqueens_space(n);
+      let mut search = ilds();
+      search.start(&space);
+      let (_, status) = search.enter(space);
+      assert_eq!(status, expected);
+    }
+    // All the solutions are found exactly once.
+    assert_eq!(Solutions::new(ilds(), nqueens_space(6)).count(), 4);
+  }
+}
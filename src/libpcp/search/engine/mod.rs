@@ -13,3 +13,8 @@
 // limitations under the License.
 
 pub mod one_solution;
+pub mod all_solutions;
+pub mod branch_and_bound;
+pub mod restart;
+pub mod lds;
+pub mod parallel;
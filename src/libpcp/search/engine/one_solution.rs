@@ -55,6 +55,7 @@ impl<C, Q, Space> OneSolution<C, Q, Space> where
       Unknown(ref branches) if branches.is_empty() => *status = Status::pruned(),
       Unknown(branches) => self.push_branches(branches),
       Satisfiable => *status = Satisfiable,
+      EndOfSearch => *status = EndOfSearch,
       _ => ()
     }
     immutable_state
@@ -86,7 +87,7 @@ impl<C, Q, Space> SearchTreeVisitor<Space> for OneSolution<C, Q, Space> where
   fn enter(&mut self, root: Space) -> (Space::FrozenState, Status<Space>) {
     let mut status = Unsatisfiable;
     let mut immutable_state = self.enter_root(root, &mut status);
-    while status != Satisfiable && !self.queue.is_empty() {
+    while status != Satisfiable && status != EndOfSearch && !self.queue.is_empty() {
       let branch = self.queue.extract().unwrap();
       let child = branch.commit(immutable_state);
       immutable_state = self.enter_child(child, &mut status);
@@ -95,6 +96,12 @@ impl<C, Q, Space> SearchTreeVisitor<Space> for OneSolution<C, Q, Space> where
   }
 }
 
+impl<C, Q, Space> ResumableSearch<Space> for OneSolution<C, Q, Space> where
+ Space: Freeze,
+ C: SearchTreeVisitor<Space>,
+ Q: Multiset<Branch<Space>>
+{}
+
 #[cfg(test)]
 mod test {
   use super::*;
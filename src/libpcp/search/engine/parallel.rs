@@ -0,0 +1,444 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Parallel search with work stealing.
+//!
+//! Spaces, frozen states and labels are built on `Rc` and can not be sent to another thread. Each worker builds its own root with the model, and the nodes are shared between workers with the thread-safe labels of `SendSnapshot`: a published node is restored by another worker from its own root. The propagators are not transferred, they are restored in their root state and re-executed on the domains of the node.
+//!
+//! Each worker explores its subtree in depth-first order with the usual labels, and publishes its oldest open node when its public deque is empty. Idle workers steal the oldest published nodes of the other workers, which are usually the largest subtrees.
+//!
+//! A node is identified by its *path*: the sequence of the alternative indices taken from the root. The solutions are ordered by their paths (i.e. in depth-first order with the first alternative explored first), so the result does not depend on the scheduling of the threads.
+
+use kernel::*;
+use search::space::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use search::branching::branch::*;
+use variable::ops::*;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::collections::VecDeque;
+use std::thread;
+use std::panic;
+
+type Path = Vec<usize>;
+type SendLabel<VStore, CStore> = <FrozenSpace<VStore, CStore> as SendSnapshot>::SendLabel;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Mode {
+  OneSolution,
+  AllSolutions
+}
+
+struct Shared<Domain, Label> {
+  mode: Mode,
+  deques: Vec<Mutex<VecDeque<(Path, Label)>>>,
+  // Number of nodes created but not yet processed, the search is over when it reaches 0.
+  pending: AtomicUsize,
+  stopped: AtomicBool,
+  // Set when a worker panics, `pending` will never reach 0 so the other workers must stop.
+  poisoned: AtomicBool,
+  solutions: Mutex<Vec<(Path, Vec<Domain>)>>,
+  // Smallest path of a solution found so far, only used in `OneSolution` mode.
+  best: Mutex<Option<Path>>
+}
+
+impl<Domain, Label> Shared<Domain, Label> {
+  fn new(mode: Mode, num_workers: usize) -> Shared<Domain, Label> {
+    Shared {
+      mode: mode,
+      deques: (0..num_workers).map(|_| Mutex::new(VecDeque::new())).collect(),
+      pending: AtomicUsize::new(1),
+      stopped: AtomicBool::new(false),
+      poisoned: AtomicBool::new(false),
+      solutions: Mutex::new(vec![]),
+      best: Mutex::new(None)
+    }
+  }
+
+  fn is_interrupted(&self) -> bool {
+    self.stopped.load(Ordering::SeqCst) || self.poisoned.load(Ordering::SeqCst)
+  }
+
+  // The node can not lead to a solution smaller than the best one.
+  fn is_dominated(&self, path: &Path) -> bool {
+    match *self.best.lock().unwrap() {
+      Some(ref best) => path > best,
+      None => false
+    }
+  }
+
+  fn add_solution(&self, path: Path, solution: Vec<Domain>) {
+    if self.mode == Mode::OneSolution {
+      let mut best = self.best.lock().unwrap();
+      let improved = match *best {
+        Some(ref best) => path < *best,
+        None => true
+      };
+      if improved {
+        *best = Some(path.clone());
+      }
+    }
+    self.solutions.lock().unwrap().push((path, solution));
+  }
+
+  fn steal(&self, thief: usize) -> Option<(Path, Label)> {
+    let n = self.deques.len();
+    for i in 1..n {
+      let victim = (thief + i) % n;
+      if let Some(node) = self.deques[victim].lock().unwrap().pop_front() {
+        return Some(node);
+      }
+    }
+    None
+  }
+}
+
+// Poisons the shared state if the worker thread panics.
+struct PoisonGuard<Domain, Label> {
+  shared: Arc<Shared<Domain, Label>>
+}
+
+impl<Domain, Label> Drop for PoisonGuard<Domain, Label> {
+  fn drop(&mut self) {
+    if thread::panicking() {
+      self.shared.poisoned.store(true, Ordering::SeqCst);
+    }
+  }
+}
+
+enum Task<VStore, CStore> where
+ VStore: Freeze,
+ CStore: Freeze,
+ VStore::FrozenState: SendSnapshot,
+ CStore::FrozenState: SendSnapshot
+{
+  Local(Path, Branch<Space<VStore, CStore>>),
+  Stolen(Path, SendLabel<VStore, CStore>)
+}
+
+/// Parallel search engine, the tree is explored by `num_workers` threads. The root of the search is built by `model` and every worker explores the tree with a visitor built by `visitor_factory`, typically `Propagation` over a `Brancher`.
+///
+/// Since the propagators can not be sent to another thread, each worker builds its root with `model` which must always create the same problem. The frozen stores must implement `SendSnapshot`, which is the case with `CopyMemory`. A node is transferred by its domains after propagation, hence the constraints added by the branching must be entailed by the domains once propagated, as with `BinarySplit`.
+///
+/// If a visitor returns `EndOfSearch` (e.g. with a `Limit`), all the workers stop and the solutions found so far are returned. If a worker panics, the other workers are stopped and the panic is propagated once all the threads are joined.
+pub struct ParallelSearch<M, F> {
+  num_workers: usize,
+  model: Arc<M>,
+  visitor_factory: Arc<F>
+}
+
+impl<M, F, V, VStore, CStore, Domain> ParallelSearch<M, F> where
+ M: Fn() -> Space<VStore, CStore> + Send + Sync + 'static,
+ F: Fn() -> V + Send + Sync + 'static,
+ V: SearchTreeVisitor<Space<VStore, CStore>> + 'static,
+ VStore: Freeze + Iterable<Item=Domain> + 'static,
+ CStore: Freeze + Consistency<VStore> + 'static,
+ VStore::FrozenState: SendSnapshot,
+ CStore::FrozenState: SendSnapshot,
+ <FrozenSpace<VStore, CStore> as Snapshot>::Label: Clone,
+ SendLabel<VStore, CStore>: 'static,
+ Domain: Clone + Send + 'static
+{
+  pub fn new(num_workers: usize, model: M, visitor_factory: F) -> ParallelSearch<M, F> {
+    assert!(num_workers > 0, "The parallel search needs at least one worker.");
+    ParallelSearch {
+      num_workers: num_workers,
+      model: Arc::new(model),
+      visitor_factory: Arc::new(visitor_factory)
+    }
+  }
+
+  /// Returns the first solution in depth-first order, whatever the number of workers.
+  pub fn one_solution(&self) -> Option<Vec<Domain>> {
+    self.run(Mode::OneSolution).into_iter().next()
+  }
+
+  /// Returns all the solutions in depth-first order.
+  pub fn all_solutions(&self) -> Vec<Vec<Domain>> {
+    self.run(Mode::AllSolutions)
+  }
+
+  fn run(&self, mode: Mode) -> Vec<Vec<Domain>> {
+    let shared = Arc::new(Shared::new(mode, self.num_workers));
+    let workers: Vec<_> = (0..self.num_workers).map(|id| {
+      let shared = shared.clone();
+      let model = self.model.clone();
+      let visitor_factory = self.visitor_factory.clone();
+      thread::spawn(move || {
+        let _guard = PoisonGuard { shared: shared.clone() };
+        let worker = Worker::new(id, shared, &*model, &*visitor_factory);
+        worker.explore();
+      })
+    }).collect();
+    let results: Vec<_> = workers.into_iter().map(|worker| worker.join()).collect();
+    for result in results {
+      if let Err(cause) = result {
+        panic::resume_unwind(cause);
+      }
+    }
+    let mut solutions = shared.solutions.lock().unwrap()
+      .drain(..)
+      .collect::<Vec<_>>();
+    solutions.sort_by(|a, b| a.0.cmp(&b.0));
+    if mode == Mode::OneSolution {
+      solutions.truncate(1);
+    }
+    solutions.into_iter().map(|(_, solution)| solution).collect()
+  }
+}
+
+struct Worker<V, VStore, CStore, Domain> where
+ VStore: Freeze,
+ CStore: Freeze,
+ VStore::FrozenState: SendSnapshot,
+ CStore::FrozenState: SendSnapshot
+{
+  id: usize,
+  shared: Arc<Shared<Domain, SendLabel<VStore, CStore>>>,
+  visitor: V,
+  root_label: <FrozenSpace<VStore, CStore> as Snapshot>::Label,
+  immutable_state: Option<FrozenSpace<VStore, CStore>>,
+  local: VecDeque<(Path, Branch<Space<VStore, CStore>>)>
+}
+
+impl<V, VStore, CStore, Domain> Worker<V, VStore, CStore, Domain> where
+ V: SearchTreeVisitor<Space<VStore, CStore>>,
+ VStore: Freeze + Iterable<Item=Domain>,
+ CStore: Freeze + Consistency<VStore>,
+ VStore::FrozenState: SendSnapshot,
+ CStore::FrozenState: SendSnapshot,
+ <FrozenSpace<VStore, CStore> as Snapshot>::Label: Clone,
+ Domain: Clone
+{
+  fn new<M, F>(id: usize, shared: Arc<Shared<Domain, SendLabel<VStore, CStore>>>, model: &M, visitor_factory: &F)
+    -> Worker<V, VStore, CStore, Domain> where
+   M: Fn() -> Space<VStore, CStore>,
+   F: Fn() -> V
+  {
+    let root = model();
+    let mut visitor = visitor_factory();
+    visitor.start(&root);
+    let mut immutable_state = root.freeze();
+    let root_label = immutable_state.label();
+    // The root is the only node available at the beginning.
+    if id == 0 {
+      shared.deques[0].lock().unwrap().push_back((vec![], immutable_state.send_label()));
+    }
+    Worker {
+      id: id,
+      shared: shared,
+      visitor: visitor,
+      root_label: root_label,
+      immutable_state: Some(immutable_state),
+      local: VecDeque::new()
+    }
+  }
+
+  fn explore(mut self) {
+    while !self.shared.is_interrupted() {
+      match self.next_task() {
+        Some(task) => {
+          self.process(task);
+          self.shared.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+        None if self.shared.pending.load(Ordering::SeqCst) == 0 => break,
+        None => thread::yield_now()
+      }
+    }
+  }
+
+  // Local nodes first in depth-first order, then the published ones, and finally steal from the other workers.
+  fn next_task(&mut self) -> Option<Task<VStore, CStore>> {
+    if let Some((path, branch)) = self.local.pop_back() {
+      return Some(Task::Local(path, branch));
+    }
+    let own = self.shared.deques[self.id].lock().unwrap().pop_back();
+    own.or_else(|| self.shared.steal(self.id))
+      .map(|(path, label)| Task::Stolen(path, label))
+  }
+
+  fn process(&mut self, task: Task<VStore, CStore>) {
+    let (path, space) = match task {
+      Task::Local(path, branch) => {
+        if self.shared.is_dominated(&path) { return; }
+        let space = branch.commit(self.immutable_state.take().unwrap());
+        (path, space)
+      }
+      Task::Stolen(path, label) => {
+        if self.shared.is_dominated(&path) { return; }
+        let space = self.restore_stolen(label);
+        (path, space)
+      }
+    };
+    let (mut immutable_state, status) = self.visitor.enter(space);
+    match status {
+      Unknown(branches) => {
+        self.shared.pending.fetch_add(branches.len(), Ordering::SeqCst);
+        // Reverse order so the first alternative is explored first.
+        for (i, branch) in branches.into_iter().enumerate().rev() {
+          let mut child_path = path.clone();
+          child_path.push(i);
+          self.local.push_back((child_path, branch));
+        }
+      }
+      Satisfiable => {
+        let label = immutable_state.label();
+        let space = immutable_state.restore(label);
+        let solution = space.vstore.iter().cloned().collect();
+        immutable_state = space.freeze();
+        self.shared.add_solution(path, solution);
+      }
+      EndOfSearch => self.shared.stopped.store(true, Ordering::SeqCst),
+      Unsatisfiable => ()
+    }
+    self.immutable_state = Some(immutable_state);
+    self.publish();
+  }
+
+  // Shares the oldest local node if the public deque of this worker is empty. The branch is committed and propagated so its decision is entailed by the domains, which are the thread-safe label of the node.
+  fn publish(&mut self) {
+    if self.local.len() > 1 {
+      let mut deque = self.shared.deques[self.id].lock().unwrap();
+      if deque.is_empty() {
+        let (path, branch) = self.local.pop_front().unwrap();
+        let mut space = branch.commit(self.immutable_state.take().unwrap());
+        let consistent = space.consistency() != Trilean::False;
+        let mut immutable_state = space.freeze();
+        if consistent {
+          deque.push_back((path, immutable_state.send_label()));
+        }
+        else {
+          self.shared.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+        self.immutable_state = Some(immutable_state);
+      }
+    }
+  }
+
+  // Restores a node published by another worker from the root of this worker.
+  fn restore_stolen(&mut self, label: SendLabel<VStore, CStore>) -> Space<VStore, CStore> {
+    let immutable_state = self.immutable_state.take().unwrap();
+    let root = immutable_state.restore(self.root_label.clone());
+    root.freeze().restore_send(label)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use search::propagation::*;
+  use search::limit::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use gcollections::ops::*;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  fn parallel_queens(n: usize, num_workers: usize)
+    -> ParallelSearch<Box<Fn() -> FDSpace + Send + Sync>, fn() -> Propagation<Brancher<FirstSmallestVar, BinarySplit>>>
+  {
+    fn visitor() -> Propagation<Brancher<FirstSmallestVar, BinarySplit>> {
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))
+    }
+    ParallelSearch::new(num_workers, Box::new(move || nqueens_space(n)), visitor)
+  }
+
+  #[test]
+  fn all_solutions_nqueens() {
+    let expected = vec![1, 0, 0, 2, 10, 4, 40, 92];
+    for n in 1..9 {
+      for &workers in &[1, 4] {
+        let solutions = parallel_queens(n, workers).all_solutions();
+        assert_eq!(solutions.len(), expected[n-1]);
+        for solution in &solutions {
+          assert!(solution.iter().all(|queen| queen.is_singleton()));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn deterministic_solutions() {
+    let sequential = parallel_queens(8, 1).all_solutions();
+    for &workers in &[2, 3, 8] {
+      assert_eq!(parallel_queens(8, workers).all_solutions(), sequential);
+    }
+  }
+
+  #[test]
+  fn one_solution_nqueens() {
+    assert_eq!(parallel_queens(3, 4).one_solution(), None);
+    for n in 4..9 {
+      let first = parallel_queens(n, 1).all_solutions().into_iter().next();
+      for &workers in &[1, 4] {
+        assert_eq!(parallel_queens(n, workers).one_solution(), first);
+      }
+    }
+  }
+
+  // Panics on the first solution found by any of the workers, the other workers continue.
+  struct PanicOnSolution<C> {
+    child: C,
+    panicked: Arc<AtomicBool>
+  }
+
+  impl<C> SearchTreeVisitor<FDSpace> for PanicOnSolution<C> where
+   C: SearchTreeVisitor<FDSpace>
+  {
+    fn start(&mut self, root: &FDSpace) {
+      self.child.start(root);
+    }
+
+    fn enter(&mut self, current: FDSpace) -> (<FDSpace as Freeze>::FrozenState, Status<FDSpace>) {
+      let (immutable_state, status) = self.child.enter(current);
+      if status == Satisfiable && !self.panicked.swap(true, Ordering::SeqCst) {
+        panic!("The visitor failed on a solution.");
+      }
+      (immutable_state, status)
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "The visitor failed on a solution.")]
+  fn worker_panic_stops_the_search() {
+    let panicked = Arc::new(AtomicBool::new(false));
+    let visitor = move || PanicOnSolution {
+      child: Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)),
+      panicked: panicked.clone()
+    };
+    ParallelSearch::new(4, || nqueens_space(10), visitor).all_solutions();
+  }
+
+  #[test]
+  fn end_of_search_stops_the_workers() {
+    fn visitor() -> Limit<Propagation<Brancher<FirstSmallestVar, BinarySplit>>> {
+      Limit::node_limit(20, Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)))
+    }
+    for &workers in &[1, 4] {
+      let solutions = ParallelSearch::new(workers, || nqueens_space(8), visitor).all_solutions();
+      assert!(solutions.len() < 92);
+    }
+  }
+}
@@ -0,0 +1,304 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Restart-based search: the tree is explored in depth-first order until a number of failures is reached, the search is then restarted from the root with a larger failure limit. It is useful with randomized or learning heuristics that can take a different decision after a restart. The root is frozen once and restored at each restart, the model is never rebuilt.
+
+use kernel::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use search::branching::branch::*;
+use gcollections::ops::multiset::*;
+
+/// Sequence of failure limits used by `Restart`, the first limit is given by the first call to `next_limit`.
+pub trait RestartPolicy {
+  fn reset(&mut self);
+  fn next_limit(&mut self) -> usize;
+}
+
+/// Luby sequence `1, 1, 2, 1, 1, 2, 4, 1, 1, 2, ...` multiplied by `scale`.
+#[derive(Clone, Copy, Debug)]
+pub struct Luby {
+  scale: usize,
+  index: usize
+}
+
+impl Luby {
+  pub fn new(scale: usize) -> Luby {
+    assert!(scale > 0, "The scale of the Luby sequence must be strictly positive.");
+    Luby {
+      scale: scale,
+      index: 0
+    }
+  }
+
+  // `i`th term (starting from 1) of the Luby sequence.
+  fn luby(mut i: usize) -> usize {
+    loop {
+      // Smallest k such that i <= 2^k - 1.
+      let mut k = 1;
+      while (1 << k) - 1 < i {
+        k += 1;
+      }
+      if i == (1 << k) - 1 {
+        return 1 << (k - 1);
+      }
+      i = i - (1 << (k - 1)) + 1;
+    }
+  }
+}
+
+impl RestartPolicy for Luby {
+  fn reset(&mut self) {
+    self.index = 0;
+  }
+
+  fn next_limit(&mut self) -> usize {
+    self.index += 1;
+    self.scale * Luby::luby(self.index)
+  }
+}
+
+/// Geometric sequence `base, base * factor, base * factor^2, ...`, rounded down.
+#[derive(Clone, Copy, Debug)]
+pub struct Geometric {
+  base: usize,
+  factor: f64,
+  current: f64
+}
+
+impl Geometric {
+  pub fn new(base: usize, factor: f64) -> Geometric {
+    assert!(base > 0, "The base of the geometric sequence must be strictly positive.");
+    assert!(factor > 1.0, "The factor of the geometric sequence must be strictly greater than 1, otherwise the limit never grows and the search is not complete.");
+    Geometric {
+      base: base,
+      factor: factor,
+      current: base as f64
+    }
+  }
+}
+
+impl RestartPolicy for Geometric {
+  fn reset(&mut self) {
+    self.current = self.base as f64;
+  }
+
+  fn next_limit(&mut self) -> usize {
+    let limit = self.current;
+    self.current = self.current * self.factor;
+    if limit >= usize::max_value() as f64 {
+      usize::max_value()
+    } else {
+      limit as usize
+    }
+  }
+}
+
+/// Depth-first search restarted from the root each time the number of failures reaches the limit given by the policy `P`. It stops on the first solution, or when a run explores the whole tree without reaching its limit.
+///
+/// As in `OneSolution`, calling `enter` again resumes the current run. Since the search restarts from scratch, a solution found before a restart can be found again. For this reason `Restart` does not implement `ResumableSearch` and can not be iterated with `Solutions`, which would report such a solution twice.
+pub struct Restart<C, Q, P, Space> where
+ Space: Freeze
+{
+  child: C,
+  queue: Q,
+  policy: P,
+  root: Option<<Space::FrozenState as Snapshot>::Label>,
+  limit: usize,
+  failures: usize,
+  restarts: usize
+}
+
+impl<C, Q, P, Space> Restart<C, Q, P, Space> where
+ Space: Freeze,
+ <Space::FrozenState as Snapshot>::Label: Clone,
+ C: SearchTreeVisitor<Space>,
+ Q: Multiset<Branch<Space>>,
+ P: RestartPolicy
+{
+  pub fn new(policy: P, child: C) -> Restart<C, Q, P, Space> {
+    Restart {
+      child: child,
+      queue: Q::empty(),
+      policy: policy,
+      root: None,
+      limit: 0,
+      failures: 0,
+      restarts: 0
+    }
+  }
+
+  /// Number of restarts performed since the call to `start`.
+  pub fn num_restarts(&self) -> usize {
+    self.restarts
+  }
+
+  fn push_branches(&mut self, branches: Vec<Branch<Space>>) {
+    for branch in branches {
+      self.queue.insert(branch);
+    }
+  }
+
+  fn enter_child(&mut self, current: Space, status: &mut Status<Space>) -> Space::FrozenState {
+    let (immutable_state, child_status) = self.child.enter(current);
+    match child_status {
+      Unknown(ref branches) if branches.is_empty() => *status = Status::pruned(),
+      Unknown(branches) => self.push_branches(branches),
+      Satisfiable => *status = Satisfiable,
+      EndOfSearch => *status = EndOfSearch,
+      Unsatisfiable => self.failures += 1
+    }
+    immutable_state
+  }
+
+  // Starts a new run from the root with the next failure limit.
+  fn enter_root(&mut self, immutable_state: Space::FrozenState, status: &mut Status<Space>) -> Space::FrozenState {
+    self.queue = Q::empty();
+    self.failures = 0;
+    self.limit = self.policy.next_limit();
+    *status = Unsatisfiable;
+    let root_label = self.root.clone().expect("The root must be labelled before a run.");
+    let root = immutable_state.restore(root_label);
+    self.enter_child(root, status)
+  }
+}
+
+impl<C, Q, P, Space> SearchTreeVisitor<Space> for Restart<C, Q, P, Space> where
+ Space: Freeze,
+ <Space::FrozenState as Snapshot>::Label: Clone,
+ C: SearchTreeVisitor<Space>,
+ Q: Multiset<Branch<Space>>,
+ P: RestartPolicy
+{
+  fn start(&mut self, root: &Space) {
+    self.queue = Q::empty();
+    self.policy.reset();
+    self.root = None;
+    self.failures = 0;
+    self.restarts = 0;
+    self.child.start(root);
+  }
+
+  fn enter(&mut self, current: Space) -> (Space::FrozenState, Status<Space>) {
+    let mut status = Unsatisfiable;
+    let mut immutable_state = current.freeze();
+    if self.root.is_none() {
+      self.root = Some(immutable_state.label());
+      immutable_state = self.enter_root(immutable_state, &mut status);
+    }
+    loop {
+      while status != Satisfiable && status != EndOfSearch
+        && self.failures < self.limit && !self.queue.is_empty()
+      {
+        let branch = self.queue.extract().unwrap();
+        let child = branch.commit(immutable_state);
+        immutable_state = self.enter_child(child, &mut status);
+      }
+      // The run is interrupted by the failure limit with nodes left to explore.
+      if status != Satisfiable && status != EndOfSearch && !self.queue.is_empty() {
+        self.restarts += 1;
+        immutable_state = self.enter_root(immutable_state, &mut status);
+      }
+      else {
+        break;
+      }
+    }
+    (immutable_state, status)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use search::space::*;
+  use search::propagation::*;
+  use search::statistics::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use gcollections::VectorStack;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  fn sequence<P: RestartPolicy>(policy: &mut P, n: usize) -> Vec<usize> {
+    (0..n).map(|_| policy.next_limit()).collect()
+  }
+
+  #[test]
+  fn luby_sequence() {
+    let mut luby = Luby::new(1);
+    assert_eq!(sequence(&mut luby, 15), vec![1,1,2,1,1,2,4,1,1,2,1,1,2,4,8]);
+    luby.reset();
+    assert_eq!(sequence(&mut luby, 3), vec![1,1,2]);
+    let mut luby = Luby::new(32);
+    assert_eq!(sequence(&mut luby, 7), vec![32,32,64,32,32,64,128]);
+  }
+
+  #[test]
+  fn geometric_sequence() {
+    let mut geometric = Geometric::new(10, 1.5);
+    assert_eq!(sequence(&mut geometric, 5), vec![10,15,22,33,50]);
+    geometric.reset();
+    assert_eq!(sequence(&mut geometric, 2), vec![10,15]);
+  }
+
+  #[test]
+  #[should_panic]
+  fn constant_geometric_sequence() {
+    Geometric::new(4, 1.0);
+  }
+
+  #[test]
+  fn restart_nqueens() {
+    for n in 1..10 {
+      let expect = || if n == 2 || n == 3 { Unsatisfiable } else { Satisfiable };
+      nqueens(n, Luby::new(1), expect());
+      nqueens(n, Geometric::new(1, 2.0), expect());
+    }
+  }
+
+  #[test]
+  fn restarts_from_root() {
+    let space = nqueens_space(10);
+    let statistics = Statistics::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    let shared = statistics.shared_statistics();
+    let mut search: Restart<_, VectorStack<_>, _, FDSpace> = Restart::new(Luby::new(1), statistics);
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, Satisfiable);
+    assert!(search.num_restarts() > 0);
+    let statistics = shared.get();
+    // Every restart is triggered by a failure.
+    assert!(statistics.failures > search.num_restarts());
+    assert_eq!(statistics.solutions, 1);
+  }
+
+  fn nqueens<P: RestartPolicy>(n: usize, policy: P, expect: Status<FDSpace>) {
+    let space = nqueens_space(n);
+    let mut search: Restart<_, VectorStack<_>, _, FDSpace> =
+      Restart::new(policy, Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, expect);
+  }
+}
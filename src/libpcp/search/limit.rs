@@ -0,0 +1,257 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug)]
+enum Budget {
+  Nodes(usize),
+  Failures(usize),
+  Solutions(usize),
+  Time(Duration)
+}
+
+/// Stops the search when a budget is exhausted by returning `EndOfSearch` instead of entering the child. Limits can be combined by nesting several `Limit`.
+///
+/// The counters and the clock are reset when `start` is called.
+///
+/// A limited search cannot be resumed: the node on which the budget is exhausted is frozen and returned without its branches, and every subsequent call to `enter` returns `EndOfSearch` until `start` is called again.
+pub struct Limit<C> {
+  child: C,
+  budget: Budget,
+  nodes: usize,
+  failures: usize,
+  solutions: usize,
+  started_at: Instant
+}
+
+impl<C> Limit<C> {
+  /// At most `limit` nodes are entered.
+  pub fn node_limit(limit: usize, child: C) -> Limit<C> {
+    Limit::new(Budget::Nodes(limit), child)
+  }
+
+  /// The search is stopped after `limit` failed nodes.
+  pub fn failure_limit(limit: usize, child: C) -> Limit<C> {
+    Limit::new(Budget::Failures(limit), child)
+  }
+
+  /// The search is stopped after `limit` solutions.
+  pub fn solution_limit(limit: usize, child: C) -> Limit<C> {
+    Limit::new(Budget::Solutions(limit), child)
+  }
+
+  /// The search is stopped once `limit` is elapsed since the call to `start`.
+  pub fn time_limit(limit: Duration, child: C) -> Limit<C> {
+    Limit::new(Budget::Time(limit), child)
+  }
+
+  fn new(budget: Budget, child: C) -> Limit<C> {
+    Limit {
+      child: child,
+      budget: budget,
+      nodes: 0,
+      failures: 0,
+      solutions: 0,
+      started_at: Instant::now()
+    }
+  }
+
+  pub fn num_nodes(&self) -> usize {
+    self.nodes
+  }
+
+  pub fn num_failures(&self) -> usize {
+    self.failures
+  }
+
+  pub fn num_solutions(&self) -> usize {
+    self.solutions
+  }
+
+  fn is_exhausted(&self) -> bool {
+    match self.budget {
+      Budget::Nodes(limit) => self.nodes >= limit,
+      Budget::Failures(limit) => self.failures >= limit,
+      Budget::Solutions(limit) => self.solutions >= limit,
+      Budget::Time(limit) => self.started_at.elapsed() >= limit
+    }
+  }
+}
+
+impl<Space, C> SearchTreeVisitor<Space> for Limit<C> where
+ Space: Freeze,
+ C: SearchTreeVisitor<Space>
+{
+  fn start(&mut self, root: &Space) {
+    self.nodes = 0;
+    self.failures = 0;
+    self.solutions = 0;
+    self.started_at = Instant::now();
+    self.child.start(root);
+  }
+
+  fn enter(&mut self, current: Space) -> (Space::FrozenState, Status<Space>) {
+    // The node is not explored, hence the pending branches below it are lost.
+    if self.is_exhausted() {
+      return (current.freeze(), EndOfSearch);
+    }
+    self.nodes += 1;
+    let (immutable_state, status) = self.child.enter(current);
+    match status {
+      Satisfiable => self.solutions += 1,
+      Unsatisfiable => self.failures += 1,
+      _ => ()
+    }
+    (immutable_state, status)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use search::space::*;
+  use search::propagation::*;
+  use search::engine::one_solution::*;
+  use search::engine::all_solutions::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use gcollections::VectorStack;
+  use std::rc::Rc;
+  use std::cell::Cell;
+  use std::time::Duration;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  // Counts the nodes entered by the search below a `Limit`.
+  struct NodeCounter<C> {
+    child: C,
+    nodes: Rc<Cell<usize>>
+  }
+
+  impl<C> SearchTreeVisitor<FDSpace> for NodeCounter<C> where
+   C: SearchTreeVisitor<FDSpace>
+  {
+    fn start(&mut self, root: &FDSpace) {
+      self.nodes.set(0);
+      self.child.start(root);
+    }
+
+    fn enter(&mut self, current: FDSpace) -> (<FDSpace as Freeze>::FrozenState, Status<FDSpace>) {
+      self.nodes.set(self.nodes.get() + 1);
+      self.child.enter(current)
+    }
+  }
+
+  fn counted_search(nodes: Rc<Cell<usize>>) -> NodeCounter<Propagation<Brancher<FirstSmallestVar, BinarySplit>>> {
+    NodeCounter {
+      child: Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)),
+      nodes: nodes
+    }
+  }
+
+  #[test]
+  fn node_limit_nqueens() {
+    for &budget in &[1, 10, 100, 1000] {
+      let nodes = Rc::new(Cell::new(0));
+      let space = nqueens_space(10);
+      let mut search: AllSolutions<_, VectorStack<_>, FDSpace> =
+        AllSolutions::new(Limit::node_limit(budget, counted_search(nodes.clone())));
+      search.start(&space);
+      let (_, status) = search.enter(space);
+      assert_eq!(status, EndOfSearch);
+      assert_eq!(nodes.get(), budget);
+      assert!(search.solutions().len() < 724);
+    }
+  }
+
+  #[test]
+  fn limited_search_cannot_resume() {
+    let nodes = Rc::new(Cell::new(0));
+    let space = nqueens_space(8);
+    let mut search: OneSolution<_, VectorStack<_>, FDSpace> =
+      OneSolution::new(Limit::node_limit(3, counted_search(nodes.clone())));
+    search.start(&space);
+    let (mut frozen, status) = search.enter(space);
+    assert_eq!(status, EndOfSearch);
+    let label = frozen.label();
+    let (_, status) = search.enter(frozen.restore(label));
+    assert_eq!(status, EndOfSearch);
+    assert_eq!(nodes.get(), 3);
+  }
+
+  #[test]
+  fn node_limit_not_reached() {
+    let space = nqueens_space(6);
+    let mut search: AllSolutions<_, VectorStack<_>, FDSpace> =
+      AllSolutions::new(Limit::node_limit(100000,
+        Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))));
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, Satisfiable);
+    assert_eq!(search.solutions().len(), 4);
+  }
+
+  #[test]
+  fn failure_limit() {
+    // 3-queens is unsatisfiable, but the search is stopped before it is proved.
+    one_solution(Limit::failure_limit(1,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))), 3, EndOfSearch);
+    one_solution(Limit::failure_limit(1000,
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))), 3, Unsatisfiable);
+  }
+
+  #[test]
+  fn solution_limit() {
+    let space = nqueens_space(8);
+    let mut search: AllSolutions<_, VectorStack<_>, FDSpace> =
+      AllSolutions::new(Limit::solution_limit(5,
+        Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))));
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, EndOfSearch);
+    assert_eq!(search.solutions().len(), 5);
+  }
+
+  #[test]
+  fn time_limit() {
+    one_solution(Limit::time_limit(Duration::from_secs(0),
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))), 8, EndOfSearch);
+    one_solution(Limit::time_limit(Duration::from_secs(3600),
+      Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))), 8, Satisfiable);
+  }
+
+  fn one_solution<C>(child: C, n: usize, expect: Status<FDSpace>) where
+   C: SearchTreeVisitor<FDSpace>
+  {
+    let space = nqueens_space(n);
+    let mut search: OneSolution<_, VectorStack<_>, FDSpace> = OneSolution::new(child);
+    search.start(&space);
+    let (_, status) = search.enter(space);
+    assert_eq!(status, expect);
+  }
+}
@@ -20,10 +20,15 @@ pub mod space;
 pub mod branching;
 pub mod search_tree_visitor;
 pub mod propagation;
+pub mod limit;
+pub mod statistics;
+pub mod trace;
 pub mod engine;
+pub mod solutions;
 
 pub use search::space::*;
 pub use search::search_tree_visitor::*;
+pub use search::solutions::*;
 
 use propagation::CStoreFD;
 use variable::VStoreFD;
@@ -36,10 +41,54 @@ type VStore = VStoreFD;
 type CStore = CStoreFD<VStore>;
 pub type FDSpace = Space<VStore, CStore>;
 
-pub fn one_solution_engine() -> Box<SearchTreeVisitor<FDSpace>> {
+pub fn one_solution_engine() -> Box<ResumableSearch<FDSpace>> {
   let search =
     OneSolution::<_, VectorStack<_>, FDSpace>::new(
     Propagation::new(
     Brancher::new(FirstSmallestVar, BinarySplit)));
   Box::new(search)
 }
+
+#[cfg(test)]
+pub mod test {
+  use kernel::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::cmp::*;
+  use propagators::distinct::*;
+  use variable::test::*;
+  use term::*;
+  use search::space::*;
+  use interval::interval::*;
+  use gcollections::ops::*;
+
+  pub type VStore = StoreI32;
+  pub type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  pub type FDSpace = Space<VStore, CStore>;
+
+  /// The n-queens problem where the queen of the line `i` is the variable `i` and its column is in `[1..n]`.
+  pub fn nqueens_space(n: usize) -> FDSpace {
+    let mut space = FDSpace::empty();
+    let mut queens = vec![];
+    // 2 queens can't share the same line.
+    for _ in 0..n {
+      queens.push(space.vstore.alloc((1, n as i32).to_interval()));
+    }
+    for i in 0..n-1 {
+      for j in i + 1..n {
+        // 2 queens can't share the same diagonal.
+        let q1 = (i + 1) as i32;
+        let q2 = (j + 1) as i32;
+        // Xi + i != Xj + j
+        space.cstore.alloc(XNeqY::new(queens[i].clone(), Addition::new(queens[j].clone(), q2 - q1)));
+        // Xi - i != Xj - j
+        space.cstore.alloc(XNeqY::new(queens[i].clone(), Addition::new(queens[j].clone(), -q2 + q1)));
+      }
+    }
+    // 2 queens can't share the same column.
+    space.cstore.alloc(Distinct::new(queens));
+    space
+  }
+}
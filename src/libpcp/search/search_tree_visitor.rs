@@ -23,7 +23,9 @@ pub enum Status<Space> where
 {
   Satisfiable,
   Unsatisfiable,
-  Unknown(Vec<Branch<Space>>)
+  Unknown(Vec<Branch<Space>>),
+  /// The search was stopped before the tree was fully explored, for example by a `Limit`.
+  EndOfSearch
 }
 
 impl<Space> Status<Space> where
@@ -42,7 +44,8 @@ impl<Space> Debug for Status<Space> where
       &Satisfiable => "Satisfiable",
       &Unsatisfiable => "Unsatisfiable",
       &Unknown(ref branches) if branches.is_empty() => "Pruned",
-      &Unknown(_) => "Unknown"
+      &Unknown(_) => "Unknown",
+      &EndOfSearch => "EndOfSearch"
     };
     formatter.write_str(name)
   }
@@ -55,6 +58,7 @@ impl<Space> PartialEq for Status<Space> where
     match (self, other) {
       (&Satisfiable, &Satisfiable) => true,
       (&Unsatisfiable, &Unsatisfiable) => true,
+      (&EndOfSearch, &EndOfSearch) => true,
       (&Unknown(ref b1), &Unknown(ref b2)) if b1.is_empty() && b2.is_empty() => true,
       (&Unknown(_), &Unknown(_)) => panic!("Cannot compare unknown status."),
       (_, _) => false,
@@ -65,9 +69,10 @@ impl<Space> PartialEq for Status<Space> where
 impl<Space> Status<Space> where
   Space: Freeze
 {
-  // Promote `self` to `Pruned` or `Satisfiable` depending on `status`.
+  // Promote `self` to `Pruned` or `Satisfiable` depending on `status`. `EndOfSearch` is never overridden.
   pub fn or(self, status: &Status<Space>) -> Self {
     match (self, status) {
+      (EndOfSearch, _) | (_, &EndOfSearch) => EndOfSearch,
       (_, &Satisfiable) => Satisfiable,
       (Unsatisfiable, &Unknown(ref branches)) if branches.is_empty() => Status::pruned(),
       (s, _) => s,
@@ -81,3 +86,26 @@ pub trait SearchTreeVisitor<Space> where
   fn start(&mut self, _space: &Space) {}
   fn enter(&mut self, space: Space) -> (Space::FrozenState, Status<Space>);
 }
+
+impl<Space, V: ?Sized> SearchTreeVisitor<Space> for Box<V> where
+  Space: Freeze,
+  V: SearchTreeVisitor<Space>
+{
+  fn start(&mut self, space: &Space) {
+    (**self).start(space)
+  }
+
+  fn enter(&mut self, space: Space) -> (Space::FrozenState, Status<Space>) {
+    (**self).enter(space)
+  }
+}
+
+/// Marker of the search engines stopping on each solution. The exploration is resumed where it stopped when `enter` is called again with the restored solution space, which is what `Solutions` relies on.
+pub trait ResumableSearch<Space>: SearchTreeVisitor<Space> where
+  Space: Freeze
+{}
+
+impl<Space, V: ?Sized> ResumableSearch<Space> for Box<V> where
+  Space: Freeze,
+  V: ResumableSearch<Space>
+{}
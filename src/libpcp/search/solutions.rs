@@ -0,0 +1,162 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use search::space::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use variable::ops::*;
+
+/// Iterates over the solutions found by a search engine. The search is resumed lazily on each call to `next` which returns the domains of the variables in the solution.
+///
+/// The engine must be resumable (see `ResumableSearch`), such as `OneSolution` which stops on each solution and continues the exploration of its queue when `enter` is called again. An engine exploring the whole tree in one call would return its last visited node instead of a solution.
+///
+/// The iterator ends either because the search tree was fully explored or because the engine stopped before, for example due to a `Limit`. These cases are distinguished with `status`.
+pub struct Solutions<E, VStore, CStore> where
+ VStore: Freeze,
+ CStore: Freeze
+{
+  engine: E,
+  space: Option<Space<VStore, CStore>>,
+  status: Option<Status<Space<VStore, CStore>>>
+}
+
+impl<E, VStore, CStore> Solutions<E, VStore, CStore> where
+ VStore: Freeze,
+ CStore: Freeze,
+ E: ResumableSearch<Space<VStore, CStore>>
+{
+  pub fn new(mut engine: E, root: Space<VStore, CStore>) -> Solutions<E, VStore, CStore> {
+    engine.start(&root);
+    Solutions {
+      engine: engine,
+      space: Some(root),
+      status: None
+    }
+  }
+
+  /// The status returned by the engine on the last call to `next`, `None` if the search has not started yet. When the iterator is exhausted, it is `Unsatisfiable` if the search tree was fully explored and `EndOfSearch` if the search was stopped.
+  pub fn status(&self) -> Option<&Status<Space<VStore, CStore>>> {
+    self.status.as_ref()
+  }
+}
+
+impl<E, VStore, CStore, Domain> Iterator for Solutions<E, VStore, CStore> where
+ VStore: Freeze + Iterable<Item=Domain>,
+ CStore: Freeze,
+ E: ResumableSearch<Space<VStore, CStore>>,
+ Domain: Clone
+{
+  type Item = Vec<Domain>;
+
+  fn next(&mut self) -> Option<Vec<Domain>> {
+    let space = match self.space.take() {
+      Some(space) => space,
+      None => return None
+    };
+    let (mut immutable_state, status) = self.engine.enter(space);
+    let solution = match status {
+      Satisfiable => {
+        let label = immutable_state.label();
+        let space = immutable_state.restore(label);
+        let solution = space.vstore.iter().cloned().collect();
+        self.space = Some(space);
+        Some(solution)
+      }
+      _ => None
+    };
+    self.status = Some(status);
+    solution
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use search::propagation::*;
+  use search::limit::*;
+  use search::engine::one_solution::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use gcollections::VectorStack;
+  use gcollections::ops::*;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  fn nqueens_solutions(n: usize) -> Box<Iterator<Item=Vec<DomainI32>>> {
+    let search: OneSolution<_, VectorStack<_>, FDSpace> =
+      OneSolution::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    Box::new(Solutions::new(search, nqueens_space(n)))
+  }
+
+  #[test]
+  fn iterate_nqueens() {
+    assert_eq!(nqueens_solutions(1).count(), 1);
+    assert_eq!(nqueens_solutions(3).count(), 0);
+    assert_eq!(nqueens_solutions(6).count(), 4);
+    assert_eq!(nqueens_solutions(8).count(), 92);
+    assert_eq!(nqueens_solutions(8).take(5).count(), 5);
+  }
+
+  #[test]
+  fn solutions_content() {
+    let mut first_queens: Vec<i32> = nqueens_solutions(6)
+      .map(|solution| {
+        assert!(solution.iter().all(|dom| dom.is_singleton()));
+        solution[0].lower()
+      })
+      .collect();
+    first_queens.sort();
+    assert_eq!(first_queens, vec![2, 3, 4, 5]);
+
+    let solution = nqueens_solutions(6)
+      .filter(|solution| solution[0].lower() == 4)
+      .next()
+      .unwrap();
+    let values: Vec<i32> = solution.iter().map(|dom| dom.lower()).collect();
+    assert_eq!(values, vec![4, 1, 5, 2, 6, 3]);
+  }
+
+  #[test]
+  fn boxed_engine() {
+    let solutions = Solutions::new(::search::one_solution_engine(), nqueens_space(5));
+    assert_eq!(solutions.count(), 10);
+  }
+
+  #[test]
+  fn final_status() {
+    let search: OneSolution<_, VectorStack<_>, FDSpace> =
+      OneSolution::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    let mut solutions = Solutions::new(search, nqueens_space(6));
+    assert!(solutions.status().is_none());
+    assert_eq!(solutions.by_ref().count(), 4);
+    assert_eq!(solutions.status(), Some(&Unsatisfiable));
+
+    let search: OneSolution<_, VectorStack<_>, FDSpace> =
+      OneSolution::new(Limit::node_limit(5, Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit))));
+    let mut solutions = Solutions::new(search, nqueens_space(8));
+    assert_eq!(solutions.by_ref().count(), 0);
+    assert_eq!(solutions.status(), Some(&EndOfSearch));
+  }
+}
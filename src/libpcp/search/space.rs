@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use kernel::*;
+use propagation::ops::*;
 use gcollections::ops::*;
 
 pub struct Space<VStore, CStore> {
@@ -81,6 +82,16 @@ impl<VStore, CStore> FrozenSpace<VStore, CStore> where
   }
 }
 
+impl<VStore, CStore> NumPropagations for FrozenSpace<VStore, CStore> where
+ VStore: Freeze,
+ CStore: Freeze,
+ CStore::FrozenState: NumPropagations
+{
+  fn num_propagations(&self) -> usize {
+    self.frozen_cstore.num_propagations()
+  }
+}
+
 impl<VStore, CStore> Snapshot for FrozenSpace<VStore, CStore> where
  VStore: Freeze,
  CStore: Freeze
@@ -101,3 +112,25 @@ impl<VStore, CStore> Snapshot for FrozenSpace<VStore, CStore> where
     }
   }
 }
+
+impl<VStore, CStore> SendSnapshot for FrozenSpace<VStore, CStore> where
+ VStore: Freeze,
+ CStore: Freeze,
+ VStore::FrozenState: SendSnapshot,
+ CStore::FrozenState: SendSnapshot
+{
+  type SendLabel = (
+    <VStore::FrozenState as SendSnapshot>::SendLabel,
+    <CStore::FrozenState as SendSnapshot>::SendLabel);
+
+  fn send_label(&mut self) -> Self::SendLabel {
+    (self.frozen_vstore.send_label(), self.frozen_cstore.send_label())
+  }
+
+  fn restore_send(self, label: Self::SendLabel) -> Self::State {
+    Space {
+      vstore: self.frozen_vstore.restore_send(label.0),
+      cstore: self.frozen_cstore.restore_send(label.1)
+    }
+  }
+}
@@ -0,0 +1,195 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+use kernel::*;
+use propagation::ops::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use std::rc::Rc;
+use std::cell::Cell;
+use std::cmp::max;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SearchStatistics {
+  pub nodes: usize,
+  pub failures: usize,
+  pub solutions: usize,
+  /// Depth of the deepest node entered, the root has a depth of 0.
+  pub max_depth: usize,
+  /// Number of propagator executions reported by the constraint store of the nodes entered.
+  pub propagations: usize,
+  /// Time elapsed between the call to `start` and the exit of the last node entered.
+  pub elapsed: Duration
+}
+
+impl SearchStatistics {
+  pub fn new() -> SearchStatistics {
+    SearchStatistics {
+      nodes: 0,
+      failures: 0,
+      solutions: 0,
+      max_depth: 0,
+      propagations: 0,
+      elapsed: Duration::new(0, 0)
+    }
+  }
+}
+
+/// Collects statistics on the nodes entered by its child. Since the combinator is usually moved inside an engine, the statistics are shared through the handle returned by `shared_statistics`.
+pub struct Statistics<C> {
+  child: C,
+  statistics: Rc<Cell<SearchStatistics>>,
+  depth: Rc<Cell<usize>>,
+  started_at: Instant
+}
+
+impl<C> Statistics<C> {
+  pub fn new(child: C) -> Statistics<C> {
+    Statistics {
+      child: child,
+      statistics: Rc::new(Cell::new(SearchStatistics::new())),
+      depth: Rc::new(Cell::new(0)),
+      started_at: Instant::now()
+    }
+  }
+
+  pub fn statistics(&self) -> SearchStatistics {
+    self.statistics.get()
+  }
+
+  pub fn shared_statistics(&self) -> Rc<Cell<SearchStatistics>> {
+    self.statistics.clone()
+  }
+}
+
+impl<Space, C> SearchTreeVisitor<Space> for Statistics<C> where
+ Space: Freeze + 'static,
+ Space::FrozenState: NumPropagations,
+ C: SearchTreeVisitor<Space>
+{
+  fn start(&mut self, root: &Space) {
+    self.statistics.set(SearchStatistics::new());
+    self.depth.set(0);
+    self.started_at = Instant::now();
+    self.child.start(root);
+  }
+
+  fn enter(&mut self, current: Space) -> (Space::FrozenState, Status<Space>) {
+    let depth = self.depth.get();
+    let mut statistics = self.statistics.get();
+    statistics.nodes += 1;
+    statistics.max_depth = max(statistics.max_depth, depth);
+    let (immutable_state, status) = self.child.enter(current);
+    statistics.propagations += immutable_state.num_propagations();
+    let status = match status {
+      Satisfiable => { statistics.solutions += 1; Satisfiable }
+      Unsatisfiable => { statistics.failures += 1; Unsatisfiable }
+      // The depth of a child is set when its branch is committed, just before it is entered.
+      Unknown(branches) => Unknown(branches.into_iter()
+        .map(|branch| {
+          let child_depth = self.depth.clone();
+          branch.on_commit(move |_| child_depth.set(depth + 1))
+        })
+        .collect()),
+      status => status
+    };
+    statistics.elapsed = self.started_at.elapsed();
+    self.statistics.set(statistics);
+    (immutable_state, status)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::cmp::*;
+  use variable::test::*;
+  use search::test::nqueens_space;
+  use search::space::*;
+  use search::propagation::*;
+  use search::engine::one_solution::*;
+  use search::engine::all_solutions::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use gcollections::VectorStack;
+  use gcollections::ops::*;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  #[test]
+  fn all_solutions_statistics() {
+    for n in 1..8 {
+      let space = nqueens_space(n);
+      let statistics = Statistics::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+      let shared = statistics.shared_statistics();
+      let mut search: AllSolutions<_, VectorStack<_>, FDSpace> = AllSolutions::new(statistics);
+      search.start(&space);
+      search.enter(space);
+      let statistics = shared.get();
+      assert_eq!(statistics.solutions, search.solutions().len());
+      // The binary search tree is fully explored so it has one more leaf than internal nodes.
+      let leaves = statistics.failures + statistics.solutions;
+      assert_eq!(statistics.nodes, 2 * leaves - 1);
+      assert!(statistics.propagations >= statistics.nodes);
+      if n > 1 {
+        assert!(statistics.max_depth >= 1);
+        assert!(statistics.max_depth < statistics.nodes);
+      }
+    }
+  }
+
+  #[test]
+  fn one_solution_statistics() {
+    let space = nqueens_space(1);
+    let statistics = Statistics::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    let shared = statistics.shared_statistics();
+    let mut search: OneSolution<_, VectorStack<_>, FDSpace> = OneSolution::new(statistics);
+    search.start(&space);
+    search.enter(space);
+    let statistics = shared.get();
+    assert_eq!(statistics.nodes, 1);
+    assert_eq!(statistics.solutions, 1);
+    assert_eq!(statistics.failures, 0);
+    assert_eq!(statistics.max_depth, 0);
+    assert_eq!(statistics.propagations, 1);
+  }
+
+  #[test]
+  fn depth_of_binary_splits() {
+    // `x = y` with x,y in [1..4]: the domains are split into [1..2] and [3..4], and then into singletons.
+    let mut space = FDSpace::empty();
+    let x = space.vstore.alloc((1, 4).to_interval());
+    let y = space.vstore.alloc((1, 4).to_interval());
+    space.cstore.alloc(XEqY::new(x, y));
+    let statistics = Statistics::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    let shared = statistics.shared_statistics();
+    let mut search: AllSolutions<_, VectorStack<_>, FDSpace> = AllSolutions::new(statistics);
+    search.start(&space);
+    search.enter(space);
+    let statistics = shared.get();
+    assert_eq!(statistics.solutions, 4);
+    assert_eq!(statistics.nodes, 7);
+    assert_eq!(statistics.max_depth, 2);
+  }
+}
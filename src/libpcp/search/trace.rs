@@ -0,0 +1,347 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+
+//! Records the search tree explored by the child visitor so it can be inspected after the search. The tree can be exported to Graphviz DOT or to a line-oriented JSON trace with one node per line, similar to the format of CP-Profiler.
+
+use kernel::*;
+use search::search_tree_visitor::*;
+use search::search_tree_visitor::Status::*;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NodeStatus {
+  Solution,
+  Failure,
+  /// The node has been split in `n` children.
+  Branch(usize),
+  /// The node has been pruned without being explored (see `Status::pruned`).
+  Pruned,
+  /// The search has been stopped on this node (see `Status::EndOfSearch`).
+  Stopped
+}
+
+impl NodeStatus {
+  fn name(&self) -> &'static str {
+    match *self {
+      NodeStatus::Solution => "solution",
+      NodeStatus::Failure => "failure",
+      NodeStatus::Branch(_) => "branch",
+      NodeStatus::Pruned => "pruned",
+      NodeStatus::Stopped => "stopped"
+    }
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TraceNode {
+  /// Nodes are numbered in the order in which they are entered, the root has the id 0.
+  pub id: usize,
+  pub parent: Option<usize>,
+  /// Index of the alternative of the parent leading to this node.
+  pub alternative: usize,
+  pub status: NodeStatus,
+  /// Description of the branch leading to this node (e.g. the variable and value chosen by the distributor), empty for the root.
+  pub decision: String
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SearchTrace {
+  pub nodes: Vec<TraceNode>
+}
+
+impl SearchTrace {
+  pub fn new() -> SearchTrace {
+    SearchTrace {
+      nodes: vec![]
+    }
+  }
+
+  pub fn to_dot(&self) -> String {
+    let mut dot = vec![];
+    self.write_dot(&mut dot).unwrap();
+    String::from_utf8(dot).unwrap()
+  }
+
+  pub fn write_dot<W: Write>(&self, out: &mut W) -> io::Result<()> {
+    try!(writeln!(out, "digraph search_tree {{"));
+    for node in &self.nodes {
+      let (shape, color) = match node.status {
+        NodeStatus::Solution => ("diamond", "green"),
+        NodeStatus::Failure => ("square", "red"),
+        NodeStatus::Branch(_) => ("circle", "blue"),
+        NodeStatus::Pruned => ("triangle", "gray"),
+        NodeStatus::Stopped => ("triangle", "orange")
+      };
+      try!(writeln!(out, "  n{} [label=\"{}\", shape={}, color={}];",
+        node.id, node.id, shape, color));
+      if let Some(parent) = node.parent {
+        try!(writeln!(out, "  n{} -> n{} [label=\"{}\"];",
+          parent, node.id, escape_dot(&node.decision)));
+      }
+    }
+    writeln!(out, "}}")
+  }
+
+  pub fn to_json_lines(&self) -> String {
+    let mut json = vec![];
+    self.write_json_lines(&mut json).unwrap();
+    String::from_utf8(json).unwrap()
+  }
+
+  /// Each node is written as a JSON object on its own line, in the order in which they were entered.
+  pub fn write_json_lines<W: Write>(&self, out: &mut W) -> io::Result<()> {
+    for node in &self.nodes {
+      let parent = match node.parent {
+        Some(parent) => format!("{}", parent),
+        None => String::from("null")
+      };
+      let children = match node.status {
+        NodeStatus::Branch(n) => n,
+        _ => 0
+      };
+      try!(writeln!(out,
+        "{{\"id\":{},\"parent\":{},\"alternative\":{},\"status\":\"{}\",\"children\":{},\"label\":\"{}\"}}",
+        node.id, parent, node.alternative, node.status.name(), children,
+        escape_json(&node.decision)));
+    }
+    Ok(())
+  }
+}
+
+fn escape_dot(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_json(s: &str) -> String {
+  let mut escaped = String::with_capacity(s.len());
+  for c in s.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      '\r' => escaped.push_str("\\r"),
+      '\t' => escaped.push_str("\\t"),
+      c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+      c => escaped.push(c)
+    }
+  }
+  escaped
+}
+
+// Position in the tree of the next node to enter, set when its branch is committed.
+#[derive(Clone)]
+struct Origin {
+  parent: usize,
+  alternative: usize,
+  decision: String
+}
+
+/// Wraps any visitor and records the nodes it enters. The trace is shared through the handle returned by `shared_trace` since the combinator is usually moved inside an engine.
+pub struct Trace<C> {
+  child: C,
+  trace: Rc<RefCell<SearchTrace>>,
+  next_origin: Rc<RefCell<Option<Origin>>>
+}
+
+impl<C> Trace<C> {
+  pub fn new(child: C) -> Trace<C> {
+    Trace {
+      child: child,
+      trace: Rc::new(RefCell::new(SearchTrace::new())),
+      next_origin: Rc::new(RefCell::new(None))
+    }
+  }
+
+  pub fn trace(&self) -> SearchTrace {
+    self.trace.borrow().clone()
+  }
+
+  pub fn shared_trace(&self) -> Rc<RefCell<SearchTrace>> {
+    self.trace.clone()
+  }
+}
+
+impl<Space, C> SearchTreeVisitor<Space> for Trace<C> where
+ Space: Freeze + 'static,
+ C: SearchTreeVisitor<Space>
+{
+  fn start(&mut self, root: &Space) {
+    *self.trace.borrow_mut() = SearchTrace::new();
+    *self.next_origin.borrow_mut() = None;
+    self.child.start(root);
+  }
+
+  fn enter(&mut self, current: Space) -> (Space::FrozenState, Status<Space>) {
+    let id = self.trace.borrow().nodes.len();
+    let origin = self.next_origin.borrow_mut().take();
+    let (immutable_state, status) = self.child.enter(current);
+    let (node_status, status) = match status {
+      Satisfiable => (NodeStatus::Solution, Satisfiable),
+      Unsatisfiable => (NodeStatus::Failure, Unsatisfiable),
+      EndOfSearch => (NodeStatus::Stopped, EndOfSearch),
+      Unknown(ref branches) if branches.is_empty() => (NodeStatus::Pruned, Unknown(vec![])),
+      Unknown(branches) => {
+        let n = branches.len();
+        let branches = branches.into_iter().enumerate()
+          .map(|(alternative, branch)| {
+            let next_origin = self.next_origin.clone();
+            let origin = Origin {
+              parent: id,
+              alternative: alternative,
+              decision: String::from(branch.description())
+            };
+            branch.on_commit(move |_| *next_origin.borrow_mut() = Some(origin))
+          })
+          .collect();
+        (NodeStatus::Branch(n), Unknown(branches))
+      }
+    };
+    let node = match origin {
+      Some(origin) => TraceNode {
+        id: id,
+        parent: Some(origin.parent),
+        alternative: origin.alternative,
+        status: node_status,
+        decision: origin.decision
+      },
+      None => TraceNode {
+        id: id,
+        parent: None,
+        alternative: 0,
+        status: node_status,
+        decision: String::new()
+      }
+    };
+    self.trace.borrow_mut().nodes.push(node);
+    (immutable_state, status)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use propagation::store::Store;
+  use propagation::events::*;
+  use propagation::reactors::*;
+  use propagation::schedulers::*;
+  use propagators::cmp::*;
+  use variable::test::*;
+  use term::*;
+  use search::space::*;
+  use search::propagation::*;
+  use search::engine::one_solution::*;
+  use search::engine::all_solutions::*;
+  use search::branching::binary_split::*;
+  use search::branching::brancher::*;
+  use search::branching::first_smallest_var::*;
+  use interval::interval::*;
+  use gcollections::VectorStack;
+  use gcollections::ops::*;
+
+  type VStore = StoreI32;
+  type CStore = Store<VStore, FDEvent, IndexedDeps, RelaxedFifo>;
+  type FDSpace = Space<VStore, CStore>;
+
+  // `x = y` with x,y in [1..4] and `x != 2`, the search tree has 2 inner nodes and 3 solutions.
+  fn small_space() -> FDSpace {
+    let mut space = FDSpace::empty();
+    let x = space.vstore.alloc((1, 4).to_interval());
+    let y = space.vstore.alloc((1, 4).to_interval());
+    space.cstore.alloc(XEqY::new(x.clone(), y));
+    space.cstore.alloc(XNeqY::new(x, Constant::new(2)));
+    space
+  }
+
+  fn trace_all(space: FDSpace) -> SearchTrace {
+    let trace = Trace::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    let shared = trace.shared_trace();
+    let mut search: AllSolutions<_, VectorStack<_>, FDSpace> = AllSolutions::new(trace);
+    search.start(&space);
+    search.enter(space);
+    let trace = shared.borrow().clone();
+    trace
+  }
+
+  #[test]
+  fn tree_structure() {
+    let trace = trace_all(small_space());
+    let nodes = &trace.nodes;
+    assert_eq!(nodes[0].parent, None);
+    assert_eq!(nodes[0].status, NodeStatus::Branch(2));
+    for (i, node) in nodes.iter().enumerate() {
+      assert_eq!(node.id, i);
+      if let Some(parent) = node.parent {
+        assert!(parent < node.id);
+        assert_eq!(nodes[parent].status, NodeStatus::Branch(2));
+        assert!(node.alternative < 2);
+        assert!(!node.decision.is_empty());
+      }
+    }
+    let count = |status| nodes.iter().filter(|n| n.status == status).count();
+    assert_eq!(count(NodeStatus::Solution), 3);
+    assert_eq!(count(NodeStatus::Failure), 0);
+    assert_eq!(count(NodeStatus::Branch(2)), 2);
+    // Every branch node has exactly one child per alternative.
+    for node in nodes.iter().filter(|n| n.status == NodeStatus::Branch(2)) {
+      let mut alternatives: Vec<_> = nodes.iter()
+        .filter(|n| n.parent == Some(node.id))
+        .map(|n| n.alternative)
+        .collect();
+      alternatives.sort();
+      assert_eq!(alternatives, vec![0, 1]);
+    }
+    // The first alternative of the root splits the first variable on the middle value.
+    let first_child = nodes.iter().find(|n| n.parent == Some(0) && n.alternative == 0).unwrap();
+    assert_eq!(first_child.decision, "_0 <= 2");
+  }
+
+  #[test]
+  fn one_solution_trace() {
+    let space = small_space();
+    let trace = Trace::new(Propagation::new(Brancher::new(FirstSmallestVar, BinarySplit)));
+    let shared = trace.shared_trace();
+    let mut search: OneSolution<_, VectorStack<_>, FDSpace> = OneSolution::new(trace);
+    search.start(&space);
+    search.enter(space);
+    let trace = shared.borrow();
+    assert_eq!(trace.nodes.last().unwrap().status, NodeStatus::Solution);
+    assert_eq!(trace.nodes.iter().filter(|n| n.status == NodeStatus::Solution).count(), 1);
+  }
+
+  #[test]
+  fn dot_export() {
+    let trace = trace_all(small_space());
+    let dot = trace.to_dot();
+    assert!(dot.starts_with("digraph search_tree {\n"));
+    assert!(dot.ends_with("}\n"));
+    let first_child = trace.nodes.iter().find(|n| n.parent == Some(0) && n.alternative == 0).unwrap();
+    assert!(dot.contains(&format!("n0 -> n{} [label=\"_0 <= 2\"];", first_child.id)));
+    assert_eq!(dot.matches("->").count(), trace.nodes.len() - 1);
+  }
+
+  #[test]
+  fn json_lines_export() {
+    let trace = trace_all(small_space());
+    let json = trace.to_json_lines();
+    let lines: Vec<_> = json.lines().collect();
+    assert_eq!(lines.len(), trace.nodes.len());
+    assert_eq!(lines[0],
+      "{\"id\":0,\"parent\":null,\"alternative\":0,\"status\":\"branch\",\"children\":2,\"label\":\"\"}");
+    assert!(lines[1].starts_with("{\"id\":1,\"parent\":0,"));
+    assert_eq!(escape_json("a\"b\\c\n"), "a\\\"b\\\\c\\n");
+  }
+}
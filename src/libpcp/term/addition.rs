@@ -13,8 +13,10 @@
 // limitations under the License.
 
 use term::ops::*;
+use gcollections::ops::*;
 use term::ExprInference;
 use std::ops::*;
+use num::PrimInt;
 use std::fmt::{Formatter, Debug, Error};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -24,10 +26,10 @@ pub struct Addition<X, V>
   v: V
 }
 
-impl<X, V, R> ExprInference for Addition<X, V> where
-  X: Add<V, Output=R>
+impl<X, V> ExprInference for Addition<X, V> where
+  X: ExprInference
 {
-  type Output = R;
+  type Output = X::Output;
 }
 
 impl<X, V> Addition<X, V> {
@@ -48,46 +50,95 @@ impl<X, V> Debug for Addition<X, V> where
   }
 }
 
+/// Right operand of `Addition`: a constant `c` or the view `-y` of `x - y`.
+pub trait AdditionOperand<Store, Domain>
+{
+  /// Domain of `x + v` where `x` is the domain of the left operand.
+  fn plus(&self, store: &Store, x: Domain) -> Domain;
+  /// Domain of `value - v`.
+  fn minus(&self, store: &Store, value: Domain) -> Domain;
+  /// Restricts the operand to `value`, a constant is left unchanged.
+  fn update_operand(&self, store: &mut Store, value: Domain) -> bool;
+}
+
+pub trait OperandDependencies<Event>
+{
+  fn operand_dependencies(&self, event: Event) -> Vec<(usize, Event)>;
+}
+
+impl<B, Domain, Store> AdditionOperand<Store, Domain> for B where
+  Domain: Add<B, Output=Domain> + Sub<B, Output=Domain>,
+  B: PrimInt
+{
+  fn plus(&self, _store: &Store, x: Domain) -> Domain {
+    x + *self
+  }
+
+  fn minus(&self, _store: &Store, value: Domain) -> Domain {
+    value - *self
+  }
+
+  fn update_operand(&self, _store: &mut Store, _value: Domain) -> bool {
+    true
+  }
+}
+
+impl<B, Event> OperandDependencies<Event> for B where
+  B: PrimInt
+{
+  fn operand_dependencies(&self, _event: Event) -> Vec<(usize, Event)> {
+    vec![]
+  }
+}
+
 impl<X, V, Domain, Store> StoreMonotonicUpdate<Store, Domain> for Addition<X, V> where
-  Domain: Sub<V, Output=Domain>,
-  V: Clone,
-  X: StoreMonotonicUpdate<Store, Domain>
+  Domain: Clone + Sub<Output=Domain> + Intersection<Output=Domain>,
+  V: AdditionOperand<Store, Domain>,
+  X: StoreMonotonicUpdate<Store, Domain> + StoreRead<Store, Value=Domain>
 {
   fn update(&self, store: &mut Store, value: Domain) -> bool {
-    self.x.update(store, value - self.v.clone())
+    let x = self.x.read(store).intersection(&self.v.minus(store, value.clone()));
+    if !self.x.update(store, x) {
+      return false;
+    }
+    let x = self.x.read(store);
+    self.v.update_operand(store, value - x)
   }
 }
 
 impl<X, V, Domain, Store> StoreRead<Store> for Addition<X, V> where
-  Domain: Add<V, Output=Domain>,
-  V: Clone,
+  V: AdditionOperand<Store, Domain>,
   X: StoreRead<Store, Value=Domain>
 {
   type Value = Domain;
   fn read(&self, store: &Store) -> Domain {
-    self.x.read(store) + self.v.clone()
+    self.v.plus(store, self.x.read(store))
   }
 }
 
 impl<X, V, Event> ViewDependencies<Event> for Addition<X, V> where
-  X: ViewDependencies<Event>
+  X: ViewDependencies<Event>,
+  V: OperandDependencies<Event>,
+  Event: Clone
 {
   fn dependencies(&self, event: Event) -> Vec<(usize, Event)> {
-    self.x.dependencies(event)
+    let mut deps = self.x.dependencies(event.clone());
+    deps.extend(self.v.operand_dependencies(event));
+    deps
   }
 }
 
 #[cfg(test)]
 mod test {
   use super::*;
-  use gcollections::ops::*;
   use kernel::*;
   use kernel::trilean::Trilean::*;
   use propagation::events::FDEvent;
   use propagation::events::FDEvent::*;
   use variable::test::*;
   use propagators::test::*;
-  use propagators::cmp::XLessY;
+  use propagators::cmp::{XLessY, x_leq_y};
+  use term::negation::Negation;
   use interval::interval::*;
 
   type Domain = DomainI32;
@@ -119,4 +170,51 @@ mod test {
     let x_less_y_plus_c = XLessY::new(x, Addition::new(y, c));
     subsumption_propagate(id, x_less_y_plus_c, &mut store, before, after, expected, update_success);
   }
+
+  #[test]
+  fn x_leq_y_plus_c() {
+    let dom0_10 = (0,10).to_interval();
+    let dom5_5 = (5,5).to_interval();
+
+    // `x <= y + c` is rewritten into `x < (y + c) + 1`, the output domain of the view is inferred from `y`.
+    x_leq_y_plus_c_test_one(1, dom0_10, dom0_10, -5, Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    x_leq_y_plus_c_test_one(2, dom0_10, dom0_10, 10, True, True, vec![], true);
+    x_leq_y_plus_c_test_one(3, dom0_10, dom0_10, -11, False, False, vec![], false);
+    x_leq_y_plus_c_test_one(4, dom5_5, dom0_10, 0, Unknown, True, vec![(1, Bound)], true);
+  }
+
+  fn x_leq_y_plus_c_test_one(id: u32, x: Domain, y: Domain, c: i32,
+    before: Trilean, after: Trilean, expected: Vec<(usize, FDEvent)>, update_success: bool)
+  {
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc(x);
+    let y = store.alloc(y);
+    let x_leq_y_plus_c = x_leq_y(x, Addition::new(y, c));
+    subsumption_propagate(id, x_leq_y_plus_c, &mut store, before, after, expected, update_success);
+  }
+
+  #[test]
+  fn x_minus_y() {
+    let dom0_10 = (0,10).to_interval();
+    let dom5_8 = (5,8).to_interval();
+    let dom3_3 = (3,3).to_interval();
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc(dom0_10);
+    let y = store.alloc(dom5_8);
+    let z = store.alloc(dom3_3);
+    // `z < x - y` gives `x - y` in `4..5`, thus `x` in `9..10` and `y` in `5..6`.
+    let z_less_x_minus_y = XLessY::new(z, Addition::new(x, Negation::new(y)));
+    subsumption_propagate(1, z_less_x_minus_y, &mut store, Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    assert_eq!(x.read(&store), (9,10).to_interval());
+    assert_eq!(y.read(&store), (5,6).to_interval());
+
+    // `x - c` is `x + -c`.
+    let c = 2;
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc(dom0_10);
+    let z = store.alloc(dom3_3);
+    let z_less_x_minus_c = XLessY::new(z, Addition::new(x, -c));
+    subsumption_propagate(2, z_less_x_minus_c, &mut store, Unknown, True, vec![(0, Bound)], true);
+    assert_eq!(x.read(&store), (6,10).to_interval());
+  }
 }
@@ -16,9 +16,36 @@ pub mod expr_inference;
 pub mod identity;
 pub mod constant;
 pub mod addition;
+pub mod times;
+pub mod negation;
 pub mod ops;
 
 pub use term::expr_inference::ExprInference;
 pub use term::identity::Identity;
 pub use term::constant::Constant;
 pub use term::addition::Addition;
+pub use term::times::Times;
+pub use term::negation::Negation;
+
+use gcollections::ops::*;
+use num::PrimInt;
+
+/// View of `a * x + b`, `x - c` is `Addition::new(x, -c)`, `c - x` is `Addition::new(Negation::new(x), c)` and `x - y` is `Addition::new(x, Negation::new(y))`.
+pub type Scaled<X, B> = Addition<Times<X, B>, B>;
+
+pub fn scaled<X, B>(a: B, x: X, b: B) -> Scaled<X, B> where
+  B: PrimInt
+{
+  Addition::new(Times::new(x, a), b)
+}
+
+/// View of `1 - x`, the negation of a boolean variable `x`.
+pub type BoolNot<X, B> = Addition<Negation<X>, B>;
+
+pub fn bool_not<X, R, B>(x: X) -> BoolNot<X, B> where
+  X: ExprInference<Output=R>,
+  R: Bounded<Bound=B>,
+  B: PrimInt
+{
+  Addition::new(Negation::new(x), B::one())
+}
@@ -0,0 +1,228 @@
+// Copyright 2015 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use term::ops::*;
+use term::ExprInference;
+use propagation::events::FDEvent;
+use term::addition::{AdditionOperand, OperandDependencies};
+use gcollections::ops::*;
+use interval::ops::{Range, Whole};
+use num::PrimInt;
+use std::ops::{Add, Sub};
+use std::fmt::{Formatter, Debug, Error};
+
+/// View of `-x`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Negation<X>
+{
+  x: X
+}
+
+impl<X> ExprInference for Negation<X> where
+  X: ExprInference
+{
+  type Output = X::Output;
+}
+
+impl<X> Negation<X> {
+  pub fn new(x: X) -> Negation<X> {
+    Negation {
+      x: x
+    }
+  }
+}
+
+impl<X> Debug for Negation<X> where
+  X: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("-{:?}", self.x))
+  }
+}
+
+// `-b`, `None` if it overflows (`-MIN`).
+fn neg<B: PrimInt>(b: B) -> Option<B> {
+  B::zero().checked_sub(&b)
+}
+
+// The domain given to `x` is intersected with its current domain since `read` may over-approximate `-x` to the whole interval.
+impl<X, B, Domain, Store> StoreMonotonicUpdate<Store, Domain> for Negation<X> where
+  Domain: Bounded<Bound=B> + Range<B> + Whole + Empty + IsEmpty + Intersection<Output=Domain>,
+  B: PrimInt,
+  X: StoreMonotonicUpdate<Store, Domain> + StoreRead<Store, Value=Domain>
+{
+  fn update(&self, store: &mut Store, value: Domain) -> bool {
+    let x = self.x.read(store);
+    if value.is_empty() || x.is_empty() {
+      return self.x.update(store, Domain::empty());
+    }
+    // A bound of `value` at the limit of the whole interval may come from the over-approximation of `read`, it does not restrict `x`. Otherwise `-value.upper()` only overflows if `x` is empty.
+    let whole = Domain::whole();
+    let lb =
+      if value.upper() >= whole.upper() { Some(x.lower()) }
+      else { neg(value.upper()) };
+    let ub =
+      if value.lower() <= whole.lower() { Some(x.upper()) }
+      else { neg(value.lower()) };
+    let x = match (lb, ub) {
+      (Some(lb), Some(ub)) if lb <= ub => x.intersection(&Domain::new(lb, ub)),
+      _ => Domain::empty()
+    };
+    self.x.update(store, x)
+  }
+}
+
+impl<X, B, Domain, Store> StoreRead<Store> for Negation<X> where
+  Domain: Bounded<Bound=B> + Range<B> + Whole + IsEmpty,
+  B: PrimInt,
+  X: StoreRead<Store, Value=Domain>
+{
+  type Value = Domain;
+  fn read(&self, store: &Store) -> Domain {
+    let x = self.x.read(store);
+    if x.is_empty() {
+      return x;
+    }
+    let whole = Domain::whole();
+    match (neg(x.upper()), neg(x.lower())) {
+      (Some(lb), Some(ub)) if lb >= whole.lower() && ub <= whole.upper() => Domain::new(lb, ub),
+      _ => whole
+    }
+  }
+}
+
+// The lower bound of `-x` is the upper bound of `x` and conversely. `FDEvent::Bound` is raised on a modification of either bound so the events are forwarded unchanged.
+impl<X, Event> ViewDependencies<Event> for Negation<X> where
+  X: ViewDependencies<Event>
+{
+  fn dependencies(&self, event: Event) -> Vec<(usize, Event)> {
+    self.x.dependencies(event)
+  }
+}
+
+// `x - y` is the view `Addition::new(x, Negation::new(y))`.
+impl<X, Domain, Store> AdditionOperand<Store, Domain> for Negation<X> where
+  Domain: Add<Output=Domain> + Sub<Output=Domain> + Intersection<Output=Domain>,
+  X: ViewDependencies<FDEvent>,
+  Negation<X>: StoreRead<Store, Value=Domain> + StoreMonotonicUpdate<Store, Domain>
+{
+  fn plus(&self, store: &Store, x: Domain) -> Domain {
+    x + self.read(store)
+  }
+
+  fn minus(&self, store: &Store, value: Domain) -> Domain {
+    value - self.read(store)
+  }
+
+  fn update_operand(&self, store: &mut Store, value: Domain) -> bool {
+    let value = self.read(store).intersection(&value);
+    self.update(store, value)
+  }
+}
+
+impl<X> OperandDependencies<FDEvent> for Negation<X> where
+  X: ViewDependencies<FDEvent>
+{
+  fn operand_dependencies(&self, event: FDEvent) -> Vec<(usize, FDEvent)> {
+    self.dependencies(event)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use gcollections::ops::*;
+  use kernel::*;
+  use kernel::trilean::Trilean::*;
+  use propagation::events::FDEvent;
+  use propagation::events::FDEvent::*;
+  use variable::test::*;
+  use propagators::test::*;
+  use propagators::cmp::*;
+  use term::*;
+  use interval::interval::*;
+  use interval::ops::Whole;
+
+  type Domain = DomainI32;
+  type FDStore = StoreI32;
+
+  #[test]
+  fn x_less_minus_y() {
+    let dom0_10 = (0,10).to_interval();
+    let dom_10_0 = (-10,0).to_interval();
+    let dom_5_5 = (-5,5).to_interval();
+    let dom1_1 = (1,1).to_interval();
+
+    // x < -y
+    x_less_minus_y_test_one(1, dom0_10, dom0_10, False, False, vec![], false);
+    x_less_minus_y_test_one(2, dom0_10, dom_10_0, Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    x_less_minus_y_test_one(3, dom_5_5, dom_5_5, Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    x_less_minus_y_test_one(4, dom_10_0, dom_10_0, Unknown, Unknown, vec![], true);
+    x_less_minus_y_test_one(5, dom1_1, dom_10_0, Unknown, True, vec![(1, Bound)], true);
+  }
+
+  #[test]
+  fn extreme_domains() {
+    let min = i32::min_value();
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc((min, 0).to_interval());
+    let y = store.alloc((0, 10).to_interval());
+    // `-min` overflows, `-x` is approximated by the whole interval.
+    assert_eq!(Negation::new(x).read(&store), Domain::whole());
+    // `-x < y` gives `x` in `-9..0`.
+    subsumption_propagate(1, XLessY::new(Negation::new(x), y), &mut store, Unknown, Unknown, vec![(0, Bound)], true);
+    assert_eq!(x.read(&store), (-9, 0).to_interval());
+
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc((0, 10).to_interval());
+    let y = store.alloc((min, 10).to_interval());
+    // `x < -y` only removes the largest values of `y`.
+    subsumption_propagate(2, XLessY::new(x, Negation::new(y)), &mut store, Unknown, Unknown, vec![(1, Bound)], true);
+    assert_eq!(y.read(&store), (min, -1).to_interval());
+  }
+
+  fn x_less_minus_y_test_one(id: u32, x: Domain, y: Domain,
+    before: Trilean, after: Trilean, expected: Vec<(usize, FDEvent)>, update_success: bool)
+  {
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc(x);
+    let y = store.alloc(y);
+    let x_less_minus_y = XLessY::new(x, Negation::new(y));
+    subsumption_propagate(id, x_less_minus_y, &mut store, before, after, expected, update_success);
+  }
+
+  #[test]
+  fn scaled_view() {
+    let dom0_10 = (0,10).to_interval();
+    let dom0_4 = (0,4).to_interval();
+
+    // x <= 3y - 2
+    x_leq_scaled_y_test_one(1, dom0_10, dom0_10, 3, -2, Unknown, Unknown, vec![(1, Bound)], true);
+    x_leq_scaled_y_test_one(2, dom0_10, dom0_4, 3, -2, Unknown, Unknown, vec![(1, Bound)], true);
+    x_leq_scaled_y_test_one(3, (5,10).to_interval(), dom0_4, 3, -2, Unknown, Unknown, vec![(1, Bound)], true);
+    // x <= -2y + 4
+    x_leq_scaled_y_test_one(4, dom0_10, dom0_10, -2, 4, Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    x_leq_scaled_y_test_one(5, (5,10).to_interval(), dom0_10, -2, 4, False, False, vec![], false);
+  }
+
+  fn x_leq_scaled_y_test_one(id: u32, x: Domain, y: Domain, a: i32, b: i32,
+    before: Trilean, after: Trilean, expected: Vec<(usize, FDEvent)>, update_success: bool)
+  {
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc(x);
+    let y = store.alloc(y);
+    let x_leq_scaled_y = x_leq_y(x, scaled(a, y, b));
+    subsumption_propagate(id, x_leq_scaled_y, &mut store, before, after, expected, update_success);
+  }
+}
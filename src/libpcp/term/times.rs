@@ -0,0 +1,214 @@
+// Copyright 2016 Pierre Talbot (IRCAM)
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use term::ops::*;
+use term::ExprInference;
+use propagators::division::{div_floor, div_ceil};
+use gcollections::ops::*;
+use interval::ops::{Range, Whole};
+use num::PrimInt;
+use std::fmt::{Formatter, Debug, Error};
+
+/// View of `x * c` where `c` is a non-zero constant. The domain of the view is the interval `[x.lower() * c..x.upper() * c]` (or the reverse if `c` is negative), only its bounds are exact. If a bound overflows, the domain of the view is the whole interval.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Times<X, B>
+{
+  x: X,
+  c: B
+}
+
+impl<X, B> ExprInference for Times<X, B> where
+  X: ExprInference
+{
+  type Output = X::Output;
+}
+
+impl<X, B> Times<X, B> where
+  B: PrimInt
+{
+  pub fn new(x: X, c: B) -> Times<X, B> {
+    assert!(c != B::zero(), "`x * 0` is a constant, use `Constant` instead.");
+    Times {
+      x: x,
+      c: c
+    }
+  }
+}
+
+impl<X, B> Debug for Times<X, B> where
+  X: Debug,
+  B: Debug
+{
+  fn fmt(&self, formatter: &mut Formatter) -> Result<(), Error> {
+    formatter.write_fmt(format_args!("{:?} * {:?}", self.x, self.c))
+  }
+}
+
+// The domain given to `x` is intersected with its current domain since `read` may over-approximate `x * c` to the whole interval.
+impl<X, B, Domain, Store> StoreMonotonicUpdate<Store, Domain> for Times<X, B> where
+  Domain: Bounded<Bound=B> + Range<B> + Whole + Empty + IsEmpty + Intersection<Output=Domain>,
+  B: PrimInt,
+  X: StoreMonotonicUpdate<Store, Domain> + StoreRead<Store, Value=Domain>
+{
+  fn update(&self, store: &mut Store, value: Domain) -> bool {
+    let x = self.x.read(store);
+    if value.is_empty() || x.is_empty() {
+      return self.x.update(store, Domain::empty());
+    }
+    // A bound of `value` at the limit of the whole interval may come from the over-approximation of `read`, it does not restrict `x`.
+    let whole = Domain::whole();
+    let lower = if value.lower() <= whole.lower() { None } else { Some(value.lower()) };
+    let upper = if value.upper() >= whole.upper() { None } else { Some(value.upper()) };
+    let (lb, ub) =
+      if self.c > B::zero() { (lower.map(|l| div_ceil(l, self.c)), upper.map(|u| div_floor(u, self.c))) }
+      else { (upper.map(|u| div_ceil(u, self.c)), lower.map(|l| div_floor(l, self.c))) };
+    // A quotient only overflows for `MIN / -1`, it is then greater than `MAX`.
+    let lb = lb.unwrap_or(Some(x.lower()));
+    let ub = ub.map_or(x.upper(), |ub| ub.unwrap_or(B::max_value()));
+    let x = match lb {
+      Some(lb) if lb <= ub => x.intersection(&Domain::new(lb, ub)),
+      _ => Domain::empty()
+    };
+    self.x.update(store, x)
+  }
+}
+
+impl<X, B, Domain, Store> StoreRead<Store> for Times<X, B> where
+  Domain: Bounded<Bound=B> + Range<B> + Whole + Empty + IsEmpty,
+  B: PrimInt,
+  X: StoreRead<Store, Value=Domain>
+{
+  type Value = Domain;
+  fn read(&self, store: &Store) -> Domain {
+    let x = self.x.read(store);
+    if x.is_empty() {
+      return x;
+    }
+    let (lb, ub) =
+      if self.c > B::zero() { (x.lower().checked_mul(&self.c), x.upper().checked_mul(&self.c)) }
+      else { (x.upper().checked_mul(&self.c), x.lower().checked_mul(&self.c)) };
+    let whole = Domain::whole();
+    match (lb, ub) {
+      (Some(lb), Some(ub)) if lb >= whole.lower() && ub <= whole.upper() => Domain::new(lb, ub),
+      _ => whole
+    }
+  }
+}
+
+impl<X, B, Event> ViewDependencies<Event> for Times<X, B> where
+  X: ViewDependencies<Event>
+{
+  fn dependencies(&self, event: Event) -> Vec<(usize, Event)> {
+    self.x.dependencies(event)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use gcollections::ops::*;
+  use kernel::*;
+  use kernel::trilean::Trilean::*;
+  use propagation::events::FDEvent;
+  use propagation::events::FDEvent::*;
+  use variable::test::*;
+  use propagators::test::*;
+  use propagators::cmp::{XLessY, x_leq_y};
+  use interval::interval::*;
+
+  type Domain = DomainI32;
+  type FDStore = StoreI32;
+
+  #[test]
+  fn x_less_y_times_c() {
+    let dom0_10 = (0,10).to_interval();
+    let dom0_5 = (0,5).to_interval();
+    let dom_3_3 = (-3,3).to_interval();
+    let dom1_3 = (1,3).to_interval();
+    let dom3_6 = (3,6).to_interval();
+
+    // x < 2y
+    x_less_y_times_c_test_one(1, dom0_10, dom0_10, 2, Unknown, Unknown, vec![(1, Bound)], true);
+    x_less_y_times_c_test_one(2, dom0_10, dom1_3, 2, Unknown, Unknown, vec![(0, Bound)], true);
+    x_less_y_times_c_test_one(3, dom3_6, dom1_3, 2, Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+    x_less_y_times_c_test_one(4, dom0_5, dom3_6, 2, True, True, vec![], true);
+    // x < -3y
+    x_less_y_times_c_test_one(5, dom0_10, dom0_10, -3, False, False, vec![], false);
+    x_less_y_times_c_test_one(6, dom0_10, dom_3_3, -3, Unknown, Unknown, vec![(0, Bound), (1, Bound)], true);
+  }
+
+  fn x_less_y_times_c_test_one(id: u32, x: Domain, y: Domain, c: i32,
+    before: Trilean, after: Trilean, expected: Vec<(usize, FDEvent)>, update_success: bool)
+  {
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc(x);
+    let y = store.alloc(y);
+    let x_less_y_times_c = XLessY::new(x, Times::new(y, c));
+    subsumption_propagate(id, x_less_y_times_c, &mut store, before, after, expected, update_success);
+  }
+
+  #[test]
+  fn x_leq_y_times_c() {
+    let dom0_10 = (0,10).to_interval();
+    let dom0_5 = (0,5).to_interval();
+    let dom1_3 = (1,3).to_interval();
+    let dom3_6 = (3,6).to_interval();
+
+    // `x <= y * c` is rewritten into `x < (y * c) + 1`, the output domain of the view is inferred from `y`.
+    x_leq_y_times_c_test_one(1, dom0_10, dom0_10, 2, Unknown, Unknown, vec![], true);
+    x_leq_y_times_c_test_one(2, dom3_6, dom1_3, 2, Unknown, Unknown, vec![(1, Bound)], true);
+    x_leq_y_times_c_test_one(3, dom0_5, dom3_6, 2, True, True, vec![], true);
+    x_leq_y_times_c_test_one(4, dom0_10, dom0_10, -3, Unknown, True, vec![(0, Assignment), (1, Assignment)], true);
+  }
+
+  fn x_leq_y_times_c_test_one(id: u32, x: Domain, y: Domain, c: i32,
+    before: Trilean, after: Trilean, expected: Vec<(usize, FDEvent)>, update_success: bool)
+  {
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc(x);
+    let y = store.alloc(y);
+    let x_leq_y_times_c = x_leq_y(x, Times::new(y, c));
+    subsumption_propagate(id, x_leq_y_times_c, &mut store, before, after, expected, update_success);
+  }
+
+  #[test]
+  fn read_overflow() {
+    let mut store: FDStore = FDStore::empty();
+    let x = store.alloc((1, i32::max_value() / 2).to_interval());
+    assert_eq!(Times::new(x, 2).read(&store), (2, i32::max_value() - 1).to_interval());
+    assert_eq!(Times::new(x, 4).read(&store), Domain::whole());
+    assert_eq!(Times::new(x, -3).read(&store), Domain::whole());
+  }
+
+  #[test]
+  fn update_overflow() {
+    let whole = Domain::whole();
+    let mut store: FDStore = FDStore::empty();
+    // `x * 4` is read as the whole interval, its bound at the limit does not restrict `x`.
+    let x = store.alloc((1, i32::max_value() / 2).to_interval());
+    assert!(Times::new(x, 4).update(&mut store, (whole.lower(), 9).to_interval()));
+    assert_eq!(x.read(&store), (1, 2).to_interval());
+    let x = store.alloc((1, i32::max_value() / 2).to_interval());
+    assert!(Times::new(x, -3).update(&mut store, (-9, whole.upper()).to_interval()));
+    assert_eq!(x.read(&store), (1, 3).to_interval());
+    let x = store.alloc((1, i32::max_value() / 2).to_interval());
+    assert!(!Times::new(x, 4).update(&mut store, (whole.lower(), 3).to_interval()));
+
+    let x = store.alloc((1, i32::max_value() / 2).to_interval());
+    let z = store.alloc((0, 10).to_interval());
+    let mut x_times_4_less_z = XLessY::new(Times::new(x, 4), z);
+    assert!(x_times_4_less_z.propagate(&mut store));
+    assert_eq!(x.read(&store), (1, 2).to_interval());
+  }
+}
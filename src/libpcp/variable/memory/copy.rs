@@ -161,3 +161,17 @@ impl<Domain> Snapshot for FrozenCopyMemory<Domain> where
     CopyMemory::restore(variables)
   }
 }
+
+impl<Domain> SendSnapshot for FrozenCopyMemory<Domain> where
+ Domain: Clone + Send
+{
+  type SendLabel = Vec<Domain>;
+
+  fn send_label(&mut self) -> Self::SendLabel {
+    self.variables.deref().clone()
+  }
+
+  fn restore_send(self, label: Self::SendLabel) -> Self::State {
+    CopyMemory::restore(label)
+  }
+}
@@ -228,6 +228,22 @@ impl<Memory, Domain, Event> Snapshot for FrozenStore<Memory, Domain, Event> wher
   }
 }
 
+impl<Memory, Domain, Event> SendSnapshot for FrozenStore<Memory, Domain, Event> where
+ Memory: MemoryConcept<Domain>,
+ Memory::FrozenState: SendSnapshot,
+ Domain: DomainConcept
+{
+  type SendLabel = <Memory::FrozenState as SendSnapshot>::SendLabel;
+
+  fn send_label(&mut self) -> Self::SendLabel {
+    self.frozen_memory.send_label()
+  }
+
+  fn restore_send(self, label: Self::SendLabel) -> Self::State {
+    Store::from_memory(self.frozen_memory.restore_send(label))
+  }
+}
+
 #[cfg(test)]
 pub mod test {
   use super::*;